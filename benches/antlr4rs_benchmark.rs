@@ -2,6 +2,7 @@ use criterion::{black_box, Criterion, criterion_group, criterion_main};
 use antlr4rs::atn_deserializer::ATNDeserializer;
 use antlr4rs::char_stream::{CharStream};
 use antlr4rs::input_stream::{CodePoint32BitStream, StringStream};
+use antlr4rs::int_stream::EOF;
 use antlr4rs::misc::murmur3::{murmur_finish, murmur_init, murmur_update};
 
 fn str_input_stream() {
@@ -53,8 +54,24 @@ fn code_point32_input_stream_into_owned() {
     assert_eq!(v.text(0, 5).into_owned(), "£¤¥¦§");
 }
 
+/// scan a long non-ASCII string with lookahead, like a lexer does, every `la` must be O(1).
+fn str_input_stream_scan(text: &str) {
+    let mut input = StringStream::from(text);
+    let input = &mut input as &mut dyn CharStream;
+    let mut n = 0;
+    while input.la(1) != EOF {
+        black_box(input.la(2));
+        input.consume();
+        n += 1;
+    }
+    assert_eq!(n, input.size());
+    black_box(input.text(0, n as usize));
+}
+
 fn bench_group_input_stream(c: &mut Criterion) {
+    let long_text = "好a§，❤ ".repeat(10_000);
     c.bench_function("create/access string input stream.", |b| b.iter(|| str_input_stream()));
+    c.bench_function("scan 60k chars string input stream.", |b| b.iter(|| str_input_stream_scan(&long_text)));
     c.bench_function("create/access code_point32 input stream.", |b| b.iter(|| code_point32_input_stream()));
     c.bench_function("create/access code_point32 input stream with into_owned invoke.", |b| b.iter(|| code_point32_input_stream_into_owned()));
 }
@@ -11,7 +11,7 @@ pub struct ATN {
     pub(crate) grammar_type: ATNType,
     pub decision2state_nth: Vec<Nth>,
     pub(crate) lexer_actions: Vec<LexerAction>,
-    max_token_type: isize,
+    pub(crate) max_token_type: isize,
     mode_name_to_start_state: HashMap<String, ATNStateType>,
     pub(crate) mode2start_state_nths: Vec<Nth>,
     pub(crate) rule2start_state_nths: Vec<Nth>,
@@ -37,4 +37,40 @@ impl ATN {
             states: vec![],
         }
     }
-}
\ No newline at end of file
+
+    // #[inline(always)]
+    pub fn grammar_type(&self) -> &ATNType {
+        &self.grammar_type
+    }
+
+    // #[inline(always)]
+    pub fn max_token_type(&self) -> isize {
+        self.max_token_type
+    }
+
+    // #[inline(always)]
+    pub fn states(&self) -> &[ATNState] {
+        &self.states
+    }
+
+    // #[inline(always)]
+    pub fn rule2start_state_nths(&self) -> &[Nth] {
+        &self.rule2start_state_nths
+    }
+
+    // #[inline(always)]
+    pub fn rule2stop_state_nths(&self) -> &[Nth] {
+        &self.rule2stop_state_nths
+    }
+
+    /// token type of each rule, for parser it is filled only when the rule bypass transitions are generated.
+    // #[inline(always)]
+    pub fn rule2token_type(&self) -> &[isize] {
+        &self.rule2token_type
+    }
+
+    // #[inline(always)]
+    pub fn mode2start_state_nths(&self) -> &[Nth] {
+        &self.mode2start_state_nths
+    }
+}
@@ -1,5 +1,7 @@
 use std::sync::Arc;
-use crate::misc::murmur3::MurmurHash;
+use crate::atn::ATN;
+use crate::lexer_action_executor::LexerActionExecutor;
+use crate::misc::murmur3::{murmur_finish, murmur_init, murmur_update, MurmurHash};
 use crate::Nth;
 use crate::prediction_context::PredictionContext;
 use crate::semantic_context::SemanticContext;
@@ -9,7 +11,7 @@ pub enum ATNConfigType {
     ParserConfig,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ATNConfig {
     // The ATN state associated with this configuration
     state_nth: Nth,
@@ -21,11 +23,89 @@ pub struct ATNConfig {
     context: Arc<PredictionContext>,
     pub semantic_context: SemanticContext,
     pub reaches_into_outer_context: isize,
+    // lexer only, whether this config has passed through a non-greedy decision,
+    // which makes it give way to the configs that reached an accept state already.
+    passed_through_non_greedy_decision: bool,
+    // lexer only, actions traversed by this config, executed when the token of this config is matched.
+    lexer_action_executor: Option<Arc<LexerActionExecutor>>,
+}
+
+impl ATNConfig {
+    /// [ATNConfig::new_lexer] creates a lexer config predicting `alt` from `state_nth`.
+    // #[inline]
+    pub fn new_lexer(state_nth: Nth, alt: isize, context: Arc<PredictionContext>) -> Self {
+        Self {
+            state_nth,
+            alt,
+            context,
+            semantic_context: SemanticContext::None,
+            reaches_into_outer_context: 0,
+            passed_through_non_greedy_decision: false,
+            lexer_action_executor: None,
+        }
+    }
+
+    /// [ATNConfig::transit] copies this config to the state `state_nth` of `atn` with `context`.
+    // #[inline]
+    pub fn transit(&self, atn: &ATN, state_nth: Nth, context: Arc<PredictionContext>) -> Self {
+        self.transit_with_executor(atn, state_nth, context, self.lexer_action_executor.clone())
+    }
+
+    /// [ATNConfig::transit_with_executor] is [ATNConfig::transit] but replaces the lexer action executor by `executor`.
+    // #[inline]
+    pub fn transit_with_executor(
+        &self,
+        atn: &ATN,
+        state_nth: Nth,
+        context: Arc<PredictionContext>,
+        executor: Option<Arc<LexerActionExecutor>>,
+    ) -> Self {
+        let non_greedy = atn.states[state_nth].to_decision_state().is_some_and(|ds| ds.non_greedy);
+        Self {
+            state_nth,
+            alt: self.alt,
+            context,
+            semantic_context: self.semantic_context.clone(),
+            reaches_into_outer_context: self.reaches_into_outer_context,
+            passed_through_non_greedy_decision: self.passed_through_non_greedy_decision || non_greedy,
+            lexer_action_executor: executor,
+        }
+    }
+
+    // #[inline(always)]
+    pub fn state_nth(&self) -> Nth {
+        self.state_nth
+    }
+
+    // #[inline(always)]
+    pub fn alt(&self) -> isize {
+        self.alt
+    }
+
+    // #[inline(always)]
+    pub fn context(&self) -> &Arc<PredictionContext> {
+        &self.context
+    }
+
+    // #[inline(always)]
+    pub fn passed_through_non_greedy_decision(&self) -> bool {
+        self.passed_through_non_greedy_decision
+    }
+
+    // #[inline(always)]
+    pub fn lexer_action_executor(&self) -> Option<&Arc<LexerActionExecutor>> {
+        self.lexer_action_executor.as_ref()
+    }
 }
 
 impl PartialEq for ATNConfig {
     fn eq(&self, other: &Self) -> bool {
-        todo!()
+        self.state_nth == other.state_nth
+            && self.alt == other.alt
+            && self.passed_through_non_greedy_decision == other.passed_through_non_greedy_decision
+            && (Arc::ptr_eq(&self.context, &other.context) || self.context == other.context)
+            && self.semantic_context == other.semantic_context
+            && self.lexer_action_executor == other.lexer_action_executor
     }
 }
 
@@ -33,6 +113,12 @@ impl PartialEq for ATNConfig {
 impl MurmurHash for ATNConfig {
     // #[inline]
     fn murmur(&self) -> u32 {
-        todo!()
+        let mut h = murmur_init(7);
+        h = murmur_update(h, self.state_nth as u32);
+        h = murmur_update(h, self.alt as u32);
+        h = murmur_update(h, self.context.murmur());
+        h = murmur_update(h, self.passed_through_non_greedy_decision as u32);
+        h = murmur_update(h, self.lexer_action_executor.as_ref().map_or(0, |e| e.murmur()));
+        murmur_finish(h, 5)
     }
-}
\ No newline at end of file
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use crate::atn_config::ATNConfig;
 use crate::misc::murmur3::MurmurHash;
 
@@ -10,12 +11,48 @@ pub struct ATNConfigSet {
     // LL prediction. It will be used to determine how to merge $. With SLL
     // it's a wildcard whereas it is not for LL context merge.
     pub full_ctx: bool,
+
+    // Set when a predicate is evaluated while computing this set, the result
+    // depends on the predicate so it must not be cached as a DFA edge.
+    pub has_semantic_context: bool,
+
+    // murmur hash of config to positions in configs, for fast lookup of duplicated configs.
+    lookup: HashMap<u32, Vec<usize>>,
 }
 
 impl ATNConfigSet {
     // #[inline(always)]
     pub fn new(full_ctx: bool) -> Self {
-        Self { configs: vec![], full_ctx }
+        Self { configs: vec![], full_ctx, has_semantic_context: false, lookup: HashMap::new() }
+    }
+
+    /// [ATNConfigSet::add] appends `config` if there is no equal one in this set, configs keep the order they are added.
+    /// Returns whether `config` is added.
+    // #[inline]
+    pub fn add(&mut self, config: ATNConfig) -> bool {
+        let positions = self.lookup.entry(config.murmur()).or_default();
+        if positions.iter().any(|p| self.configs[*p] == config) {
+            return false;
+        }
+        positions.push(self.configs.len());
+        self.configs.push(config);
+        true
+    }
+
+    // #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.configs.is_empty()
+    }
+
+    // #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.configs.len()
+    }
+}
+
+impl PartialEq for ATNConfigSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.full_ctx == other.full_ctx && self.configs == other.configs
     }
 }
 
@@ -28,4 +65,4 @@ impl MurmurHash for ATNConfigSet {
         }
         h
     }
-}
\ No newline at end of file
+}
@@ -1,8 +1,11 @@
+/// [ATNDeserializeOption] controls how [crate::atn_deserializer::ATNDeserializer] builds the ATN.
 #[derive(Debug)]
 pub struct ATNDeserializeOption {
-    pub(crate) read_only: bool,
-    pub(crate) verify_atn: bool,
-    pub(crate) gen_rule_bypass_transitions: bool,
+    pub read_only: bool,
+    /// check the consistency of the ATN after it has been read.
+    pub verify_atn: bool,
+    /// generate the bypass transitions of each parser rule, which are required by tree pattern matching.
+    pub gen_rule_bypass_transitions: bool,
 }
 
 impl Default for ATNDeserializeOption {
@@ -1,17 +1,93 @@
 /// [ATNDeserializer] deserialize i32 array into ATN struct.
 /// i32 array place data in this order
 /// | serialized-version, atn-states-num, {atn-state-type, rule-index, {contrast-nth}\*}\*, non-greedy-states-num, {non-greedy-nth}\*, precedence-states-num, {precedence-nth}\* |
-use std::slice::Iter;
+use std::cmp::min;
 use crate::atn::ATN;
 use crate::atn_deserialize_option::ATNDeserializeOption;
-use crate::atn_state::{ATNState, ATNStateType};
+use crate::atn_state::{ATN_STATE_BASIC, ATN_STATE_BLOCK_END, ATN_STATE_BLOCK_START, ATNState, ATNStateType};
 use crate::atn_type::ATNType;
+use crate::errors::{ATNDeserializeError, ATNDeserializeErrorKind, ATNSection};
 use crate::interval_set::IntervalSet;
 use crate::lexer_action::LexerAction;
 use crate::Nth;
-use crate::transition::{Transition, TransitionType};
+use crate::transition::{Transition, TransitionType, TRANSITION_ACTION, TRANSITION_PRECEDENCE};
 
-const SERIALIZED_VERSION: isize = 4;
+pub(crate) const SERIALIZED_VERSION: isize = 4;
+
+/// [ATNDataReader] walks through the serialized ATN and remembers the section and offset it is reading,
+/// so every failure can point at the offending value.
+struct ATNDataReader<'a> {
+    data: &'a [i32],
+    offset: usize,
+    section: ATNSection,
+}
+
+impl<'a> ATNDataReader<'a> {
+    // #[inline(always)]
+    fn new(data: &'a [i32]) -> Self {
+        Self { data, offset: 0, section: ATNSection::Header }
+    }
+
+    // #[inline(always)]
+    fn enter(&mut self, section: ATNSection) {
+        self.section = section
+    }
+
+    // #[inline]
+    fn next(&mut self) -> Result<i32, ATNDeserializeError> {
+        match self.data.get(self.offset) {
+            Some(v) => {
+                self.offset += 1;
+                Ok(*v)
+            }
+            None => Err(self.error_at(self.offset, ATNDeserializeErrorKind::UnexpectedEnd)),
+        }
+    }
+
+    /// read a count of elements, a negative count is an error.
+    // #[inline]
+    fn next_count(&mut self) -> Result<usize, ATNDeserializeError> {
+        let v = self.next()?;
+        if v < 0 {
+            return Err(self.error(ATNDeserializeErrorKind::InvalidCount));
+        }
+        Ok(v as usize)
+    }
+
+    /// read the nth of an ATN state, which must be less than `states_num`.
+    // #[inline]
+    fn next_state_nth(&mut self, states_num: usize) -> Result<Nth, ATNDeserializeError> {
+        let v = self.next()?;
+        if v < 0 || v as usize >= states_num {
+            return Err(self.error(ATNDeserializeErrorKind::InvalidStateNth));
+        }
+        Ok(v as Nth)
+    }
+
+    /// capacity to reserve for `n` elements, never more than the rest of data could describe,
+    /// so a corrupted count can not make us allocate a huge vec.
+    // #[inline(always)]
+    fn capacity(&self, n: usize) -> usize {
+        min(n, self.data.len() - self.offset)
+    }
+
+    /// offset of the value read last.
+    // #[inline(always)]
+    fn last_offset(&self) -> usize {
+        self.offset - 1
+    }
+
+    /// an error about the value read last.
+    // #[inline(always)]
+    fn error(&self, kind: ATNDeserializeErrorKind) -> ATNDeserializeError {
+        self.error_at(self.last_offset(), kind)
+    }
+
+    // #[inline]
+    fn error_at(&self, offset: usize, kind: ATNDeserializeErrorKind) -> ATNDeserializeError {
+        ATNDeserializeError { section: self.section, offset, value: self.data.get(offset).copied(), kind }
+    }
+}
 
 #[derive(Debug)]
 pub struct ATNDeserializer {
@@ -21,154 +97,216 @@ pub struct ATNDeserializer {
 impl ATNDeserializer {
     // #[inline]
     pub fn new(options: Option<ATNDeserializeOption>) -> Self {
-        Self { des_opt: options.unwrap_or(ATNDeserializeOption::default()) }
+        Self { des_opt: options.unwrap_or_default() }
     }
 
+    /// [ATNDeserializer::deserialize] is a convenience wrapper of [ATNDeserializer::try_deserialize]
+    /// for ATNs that are known to be valid, such as the ones generated into the source code.
+    /// It panics if `data` is not a valid serialized ATN.
     // #[inline]
     pub fn deserialize(&self, data: &[i32]) -> ATN {
-        let mut data = data.iter();
-        self.check_version(&mut data);
-        let mut atn = self.read_atn(&mut data);
+        match self.try_deserialize(data) {
+            Ok(atn) => atn,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// [ATNDeserializer::try_deserialize] deserialize `data` into an [ATN], returns an [ATNDeserializeError]
+    /// that names the section, offset and value if `data` is truncated or inconsistent.
+    // #[inline]
+    pub fn try_deserialize(&self, data: &[i32]) -> Result<ATN, ATNDeserializeError> {
+        let mut data = ATNDataReader::new(data);
+        self.check_version(&mut data)?;
+        let mut atn = self.read_atn(&mut data)?;
         // parse atn states
-        self.read_states(&mut data, &mut atn);
+        let state_offsets = self.read_states(&mut data, &mut atn)?;
         // parse atn rules
-        self.read_rules(&mut data, &mut atn);
+        self.read_rules(&mut data, &mut atn, &state_offsets)?;
         // parse modes
-        self.read_modes(&mut data, &mut atn);
+        self.read_modes(&mut data, &mut atn)?;
         // parse interval sets and read edges
-        let sets = self.read_sets(&mut data);
-        self.read_edges(&mut data, &mut atn, sets);
+        let sets = self.read_sets(&mut data)?;
+        let action_offsets = self.read_edges(&mut data, &mut atn, sets)?;
         // parse decisions
-        self.read_decisions(&mut data, &mut atn);
+        self.read_decisions(&mut data, &mut atn)?;
         // parse lexer actions
-        self.read_lexer_actions(&mut data, &mut atn);
+        self.read_lexer_actions(&mut data, &mut atn, &action_offsets)?;
         self.mark_precedence_decisions(&mut atn);
-        self.generate_rule_bypass_transition(&mut atn);
-        self.verify_atn(&atn);
-        atn
+        self.generate_rule_bypass_transition(&mut data, &mut atn, &state_offsets)?;
+        self.verify_atn(&data, &atn, &state_offsets)?;
+        Ok(atn)
     }
 
     // #[inline(always)]
-    fn check_version(&self, data: &mut Iter<i32>) {
-        let ver = data.next().unwrap();
-        if (*ver as isize) != SERIALIZED_VERSION {
-            panic!("Could not deserialize ATN with version {} (expected {}.)", ver, SERIALIZED_VERSION)
+    fn check_version(&self, data: &mut ATNDataReader) -> Result<(), ATNDeserializeError> {
+        data.enter(ATNSection::Header);
+        let ver = data.next()?;
+        if (ver as isize) != SERIALIZED_VERSION {
+            return Err(data.error(ATNDeserializeErrorKind::UnsupportedVersion));
         }
+        Ok(())
     }
 
     // #[inline(always)]
-    fn read_atn(&self, data: &mut Iter<i32>) -> ATN {
-        ATN::new(
-            match *data.next().unwrap() {
-                0 => ATNType::Lexer,
-                1 => ATNType::Parser,
-                _ => panic!("Invalid ATN type.")
-            },
-            *data.next().unwrap() as isize,
-        )
+    fn read_atn(&self, data: &mut ATNDataReader) -> Result<ATN, ATNDeserializeError> {
+        let grammar_type = match data.next()? {
+            0 => ATNType::Lexer,
+            1 => ATNType::Parser,
+            _ => return Err(data.error(ATNDeserializeErrorKind::InvalidGrammarType)),
+        };
+        Ok(ATN::new(grammar_type, data.next()? as isize))
     }
 
     /// parse all states into ATN, use enum type to implement in Rust to decrease dynamic or vtable cost.
     /// just store the nth for contrast ATN state type, how clever I am! ^_^
+    /// returns the offset of each state in data, which is used to report the failure of verification.
     // #[inline(always)]
-    fn read_states(&self, data: &mut Iter<i32>, atn: &mut ATN) {
-        let states_num = *data.next().unwrap() as usize;
-        atn.states = Vec::with_capacity(states_num);
+    fn read_states(&self, data: &mut ATNDataReader, atn: &mut ATN) -> Result<Vec<usize>, ATNDeserializeError> {
+        data.enter(ATNSection::States);
+        let states_num = data.next_count()?;
+        atn.states = Vec::with_capacity(data.capacity(states_num));
+        let mut state_offsets = Vec::with_capacity(data.capacity(states_num));
+        // offsets of the contrast nths, they can only be checked once all states are read.
+        let mut contrast_offsets = vec![];
 
         for nth in 0..states_num {
+            state_offsets.push(data.offset);
             // get atn state type.
-            let state_type = *data.next().unwrap() as ATNStateType;
-            // check type, panic if type is invalid.
-            ATNState::check_type(state_type);
+            let state_type = data.next()? as ATNStateType;
+            if !ATNState::is_valid_type(state_type) {
+                return Err(data.error(ATNDeserializeErrorKind::InvalidStateType));
+            }
             // get rule index
-            let rule_idx = *data.next().unwrap() as usize;
+            let rule_idx = data.next()? as usize;
             // create a new atn state
             let mut atn_state = ATNState::new(state_type, rule_idx, nth);
             // push anchors if atn state type is loop end or block start
             if let ATNState::LoopEnd(le) = &mut atn_state {
-                le.loopback_state_nth = *data.next().unwrap() as Nth;
+                le.loopback_state_nth = data.next()? as Nth;
                 le.contrast_set = true;
+                contrast_offsets.push(data.last_offset());
             } else if atn_state.instance_of_block_start() {
                 let bs = atn_state.to_block_start_mut().unwrap();
-                bs.block_end_state_nth = *data.next().unwrap() as Nth;
+                bs.block_end_state_nth = data.next()? as Nth;
                 bs.contrast_set = true;
+                contrast_offsets.push(data.last_offset());
             }
             atn.states.push(atn_state);
         }
 
         // check whether all block start and loopback has been set to it's peer correctly.
-        let non_greedy_states_num = *data.next().unwrap() as usize;
+        for offset in contrast_offsets {
+            let contrast_nth = data.data[offset] as Nth;
+            if contrast_nth >= states_num {
+                return Err(data.error_at(offset, ATNDeserializeErrorKind::InvalidStateNth));
+            }
+            // the block start is the state whose record holds this offset.
+            let nth = state_offsets.partition_point(|&o| o <= offset) - 1;
+            if !atn.states[nth].instance_of_block_start() {
+                continue;
+            }
+            // Block end states can only be associated to a single block start state
+            match atn.states[contrast_nth].to_block_end_mut() {
+                Some(be) if !be.contrast_set => {
+                    be.block_start_state_nth = nth;
+                    be.contrast_set = true;
+                }
+                _ => return Err(data.error_at(offset, ATNDeserializeErrorKind::UnexpectedStateType)),
+            }
+        }
+
+        let non_greedy_states_num = data.next_count()?;
         for _ in 0..non_greedy_states_num {
-            let nth = *data.next().unwrap() as Nth;
-            atn.states[nth].to_decision_state_mut().unwrap().non_greedy = true;
+            let nth = data.next_state_nth(states_num)?;
+            match atn.states[nth].to_decision_state_mut() {
+                Some(ds) => ds.non_greedy = true,
+                None => return Err(data.error(ATNDeserializeErrorKind::UnexpectedStateType)),
+            }
         }
 
-        let precedence_states_num = *data.next().unwrap() as usize;
+        let precedence_states_num = data.next_count()?;
         for _ in 0..precedence_states_num {
-            let nth = *data.next().unwrap() as Nth;
-            atn.states[nth].to_rule_start_state_mut().unwrap().left_recursive = true;
+            let nth = data.next_state_nth(states_num)?;
+            match atn.states[nth].to_rule_start_state_mut() {
+                Some(rs) => rs.left_recursive = true,
+                None => return Err(data.error(ATNDeserializeErrorKind::UnexpectedStateType)),
+            }
         }
+        Ok(state_offsets)
     }
 
     // #[inline(always)]
-    fn read_rules(&self, data: &mut Iter<i32>, atn: &mut ATN) {
-        let rules_num = *data.next().unwrap() as usize;
+    fn read_rules(&self, data: &mut ATNDataReader, atn: &mut ATN, state_offsets: &[usize]) -> Result<(), ATNDeserializeError> {
+        data.enter(ATNSection::Rules);
+        let rules_num = data.next_count()?;
+        atn.rule2start_state_nths = Vec::with_capacity(data.capacity(rules_num));
         if atn.grammar_type == ATNType::Lexer {
             // reallocate the vec for elements, assign a new vec with specify capacity is faster than resize(my guess :) )
-            atn.rule2token_type = Vec::with_capacity(rules_num);
+            atn.rule2token_type = Vec::with_capacity(data.capacity(rules_num));
         }
 
         for _ in 0..rules_num {
-            let rs_nth = *data.next().unwrap() as Nth;
+            let rs_nth = data.next_state_nth(atn.states.len())?;
+            if atn.states[rs_nth].to_rule_start_state().is_none() {
+                return Err(data.error(ATNDeserializeErrorKind::UnexpectedStateType));
+            }
             atn.rule2start_state_nths.push(rs_nth);
 
             if atn.grammar_type == ATNType::Lexer {
-                let token_type = *data.next().unwrap() as isize;
+                let token_type = data.next()? as isize;
                 atn.rule2token_type.push(token_type);
             }
         }
 
         atn.rule2stop_state_nths.resize(rules_num, 0);
 
-        for nth in 0..atn.states.len() {
-            let mut is_stop = false;
-            let mut trg_rule_idx = 0;
-            if let ATNState::RuleStop(r) = &atn.states[nth] {
-                is_stop = true;
-                trg_rule_idx = r.base.rule_idx;
-            }
-            if !is_stop {
-                continue;
+        let stop_states: Vec<(Nth, usize)> = atn.states.iter().enumerate()
+            .filter_map(|(nth, state)| match state {
+                ATNState::RuleStop(r) => Some((nth, r.base.rule_idx)),
+                _ => None,
+            })
+            .collect();
+        for (nth, trg_rule_idx) in stop_states {
+            if trg_rule_idx >= rules_num {
+                // point at the rule index of the stop state record.
+                return Err(data.error_at(state_offsets[nth] + 1, ATNDeserializeErrorKind::InvalidRuleIndex));
             }
             atn.rule2stop_state_nths[trg_rule_idx] = nth;
             // hahaha, what's this?
-            let rule_start = (&mut atn.states[atn.rule2start_state_nths[trg_rule_idx]]).to_rule_start_state_mut().unwrap();
+            let rule_start = atn.states[atn.rule2start_state_nths[trg_rule_idx]].to_rule_start_state_mut().unwrap();
             rule_start.rule_stop_state_nth = nth;
             rule_start.contrast_set = true;
         }
+        Ok(())
     }
 
     // #[inline(always)]
-    fn read_modes(&self, data: &mut Iter<i32>, atn: &mut ATN) {
-        let modes_num = *data.next().unwrap() as usize;
-        atn.mode2start_state_nths = Vec::with_capacity(modes_num);
+    fn read_modes(&self, data: &mut ATNDataReader, atn: &mut ATN) -> Result<(), ATNDeserializeError> {
+        data.enter(ATNSection::Modes);
+        let modes_num = data.next_count()?;
+        atn.mode2start_state_nths = Vec::with_capacity(data.capacity(modes_num));
 
         for _ in 0..modes_num {
-            let token_start_state_nth = *data.next().unwrap() as Nth;
+            let token_start_state_nth = data.next_state_nth(atn.states.len())?;
+            if !matches!(atn.states[token_start_state_nth], ATNState::TokenStart(_)) {
+                return Err(data.error(ATNDeserializeErrorKind::UnexpectedStateType));
+            }
             atn.mode2start_state_nths.push(token_start_state_nth);
         }
+        Ok(())
     }
 
     // #[inline(always)]
-    fn read_sets(&self, data: &mut Iter<i32>) -> Vec<IntervalSet> {
-        let sets_num = *data.next().unwrap() as usize;
-        let mut sets: Vec<IntervalSet> = Vec::with_capacity(sets_num);
+    fn read_sets(&self, data: &mut ATNDataReader) -> Result<Vec<IntervalSet>, ATNDeserializeError> {
+        data.enter(ATNSection::Sets);
+        let sets_num = data.next_count()?;
+        let mut sets: Vec<IntervalSet> = Vec::with_capacity(data.capacity(sets_num));
 
         for _ in 0..sets_num {
             let mut set = IntervalSet::new();
 
-            let n = *data.next().unwrap() as usize;
-            let contains_eof = *data.next().unwrap();
+            let n = data.next_count()?;
+            let contains_eof = data.next()?;
 
             if contains_eof != 0 {
                 set.add_one(-1);
@@ -179,29 +317,51 @@ impl ATNDeserializer {
             // that's: Golang not support right index element included
             // but rust can do it, so just add_range(l, h). :-)
             for _ in 0..n {
-                set.add_range(
-                    *data.next().unwrap() as isize,
-                    *data.next().unwrap() as isize,
-                );
+                let l = data.next()? as isize;
+                let h = data.next()? as isize;
+                set.add_range(l, h);
             }
             sets.push(set)
         }
 
-        sets
+        Ok(sets)
     }
 
     // #[inline(always)]
-    fn read_edges(&self, data: &mut Iter<i32>, atn: &mut ATN, sets: Vec<IntervalSet>) {
+    /// returns the offsets of the lexer action indexes of the action transitions of a lexer ATN,
+    /// they are checked once the lexer actions are read.
+    fn read_edges(&self, data: &mut ATNDataReader, atn: &mut ATN, sets: Vec<IntervalSet>) -> Result<Vec<usize>, ATNDeserializeError> {
         // Thank you Rust borrow checker :), you drove me mad when I write these trashes.
-
-        let edges_num = *data.next().unwrap() as usize;
+        data.enter(ATNSection::Edges);
+        let states_num = atn.states.len();
+        let edges_num = data.next_count()?;
+        let lexer = atn.grammar_type == ATNType::Lexer;
+        let mut action_offsets = vec![];
         for _ in 0..edges_num {
-            let src = *data.next().unwrap() as Nth;
-            let trg = *data.next().unwrap() as Nth;
-            let ttype = *data.next().unwrap() as TransitionType;
-            let arg1 = *data.next().unwrap() as isize;
-            let arg2 = *data.next().unwrap() as isize;
-            let arg3 = *data.next().unwrap() as isize;
+            let src = data.next_state_nth(states_num)?;
+            let trg = data.next_state_nth(states_num)?;
+            let ttype = data.next()? as TransitionType;
+            // precedence predicates are of left recursive parser rules only.
+            if !Transition::is_valid_type(ttype) || (lexer && ttype == TRANSITION_PRECEDENCE) {
+                return Err(data.error(ATNDeserializeErrorKind::InvalidTransitionType));
+            }
+            let arg1 = data.next()? as isize;
+            if Transition::is_rule_type(ttype) {
+                // the target of rule transition is the rule start state.
+                if arg1 < 0 || arg1 as usize >= states_num {
+                    return Err(data.error(ATNDeserializeErrorKind::InvalidStateNth));
+                }
+                if atn.states[arg1 as usize].rule_index() >= atn.rule2start_state_nths.len() {
+                    return Err(data.error(ATNDeserializeErrorKind::InvalidRuleIndex));
+                }
+            } else if Transition::is_set_type(ttype) && (arg1 < 0 || arg1 as usize >= sets.len()) {
+                return Err(data.error(ATNDeserializeErrorKind::InvalidSetIndex));
+            }
+            let arg2 = data.next()? as isize;
+            if lexer && ttype == TRANSITION_ACTION {
+                action_offsets.push(data.last_offset());
+            }
+            let arg3 = data.next()? as isize;
 
             // create a new transition
             let transition = Transition::new(ttype, trg, arg1, arg2, arg3, &sets);
@@ -234,25 +394,6 @@ impl ATNDeserializer {
         let mut plus_block_start_modifies = Vec::with_capacity(atn.states.len() << 1);
         let mut star_loop_entry_modifies = Vec::with_capacity(atn.states.len() << 1);
         for nth in 0..atn.states.len() {
-            if atn.states[nth].instance_of_block_start() {
-                // We need to know the end state to set its start state
-                let mut block_end_state_nth = 0;
-                {
-                    let bs = atn.states[nth].to_block_start().unwrap();
-                    if !bs.contrast_set {
-                        panic!("BlockStart contrast: BlockEnd pos not set.");
-                    }
-                    block_end_state_nth = bs.block_end_state_nth
-                }
-                // Block end states can only be associated to a single block start state
-                let be = atn.states[block_end_state_nth].to_block_end_mut().unwrap();
-                if be.contrast_set {
-                    panic!("BlockEnd contrast: BlockStart pos already set.");
-                }
-                be.block_start_state_nth = nth;
-                be.contrast_set = true;
-            }
-
             let state = &atn.states[nth];
 
             if let ATNState::PlusLoopback(pl) = state {
@@ -272,49 +413,65 @@ impl ATNDeserializer {
             }
         }
 
-        if plus_block_start_modifies.len() != 0 {
-            for (state_ntn, contrast_nth) in plus_block_start_modifies {
-                let mut pbs = atn.states[state_ntn].to_plus_block_start_mut().unwrap();
-                pbs.plus_loopback_state_nth = contrast_nth;
-                pbs.contrast_set = true;
-            }
+        for (state_ntn, contrast_nth) in plus_block_start_modifies {
+            let pbs = atn.states[state_ntn].to_plus_block_start_mut().unwrap();
+            pbs.plus_loopback_state_nth = contrast_nth;
+            pbs.contrast_set = true;
         }
 
-        if star_loop_entry_modifies.len() != 0 {
-            for (state_ntn, contrast_nth) in star_loop_entry_modifies {
-                let mut sle = atn.states[state_ntn].to_star_loop_entry_mut().unwrap();
-                sle.star_loopback_state_nth = contrast_nth;
-                sle.contrast_set = true;
-            }
+        for (state_ntn, contrast_nth) in star_loop_entry_modifies {
+            let sle = atn.states[state_ntn].to_star_loop_entry_mut().unwrap();
+            sle.star_loopback_state_nth = contrast_nth;
+            sle.contrast_set = true;
         }
+        Ok(action_offsets)
     }
 
     // #[inline(always)]
-    fn read_decisions(&self, data: &mut Iter<i32>, atn: &mut ATN) {
-        let decisions_num = *data.next().unwrap() as usize;
-        atn.decision2state_nth = Vec::with_capacity(decisions_num);
+    fn read_decisions(&self, data: &mut ATNDataReader, atn: &mut ATN) -> Result<(), ATNDeserializeError> {
+        data.enter(ATNSection::Decisions);
+        let decisions_num = data.next_count()?;
+        atn.decision2state_nth = Vec::with_capacity(data.capacity(decisions_num));
 
         for i in 0..decisions_num {
-            let pos = *data.next().unwrap() as Nth;
+            let pos = data.next_state_nth(atn.states.len())?;
+            match atn.states[pos].to_decision_state_mut() {
+                Some(ds) => ds.decision = i as isize,
+                None => return Err(data.error(ATNDeserializeErrorKind::UnexpectedStateType)),
+            }
             atn.decision2state_nth.push(pos);
-            atn.states[pos].to_decision_state_mut().unwrap().decision = i as isize;
         }
+        Ok(())
     }
 
     // #[inline(always)]
-    fn read_lexer_actions(&self, data: &mut Iter<i32>, atn: &mut ATN) {
+    fn read_lexer_actions(&self, data: &mut ATNDataReader, atn: &mut ATN, action_offsets: &[usize]) -> Result<(), ATNDeserializeError> {
         if atn.grammar_type != ATNType::Lexer {
-            return;
+            return Ok(());
         }
-        let actions_num = *data.next().unwrap() as usize;
-        atn.lexer_actions = Vec::with_capacity(actions_num);
+        data.enter(ATNSection::LexerActions);
+        let actions_num = data.next_count()?;
+        atn.lexer_actions = Vec::with_capacity(data.capacity(actions_num));
 
         for _ in 0..actions_num {
-            let action_type = *data.next().unwrap();
-            let data1 = *data.next().unwrap() as isize;
-            let data2 = *data.next().unwrap() as isize;
+            let action_type = data.next()?;
+            if !LexerAction::is_valid_type(action_type) {
+                return Err(data.error(ATNDeserializeErrorKind::InvalidLexerActionType));
+            }
+            let data1 = data.next()? as isize;
+            let data2 = data.next()? as isize;
             atn.lexer_actions.push(LexerAction::new(action_type, data1, data2));
         }
+
+        // the failure is of the action transition, which refers to the lexer action.
+        data.enter(ATNSection::Edges);
+        for &offset in action_offsets {
+            let action_idx = data.data[offset];
+            if action_idx < 0 || action_idx as usize >= atn.lexer_actions.len() {
+                return Err(data.error_at(offset, ATNDeserializeErrorKind::InvalidLexerActionIndex));
+            }
+        }
+        Ok(())
     }
 
     /// [ATNDeserializer::mark_precedence_decisions] analyzes the [ATNState::StarLoopEntry] states in the
@@ -326,14 +483,21 @@ impl ATNDeserializer {
             let state = &atn.states[nth];
             // shit codes here, :(
             if let ATNState::StarLoopEntry(sle) = state {
-                let rule_start_nth = atn.rule2start_state_nths[sle.base.base.rule_idx];
+                let rule_start_nth = match atn.rule2start_state_nths.get(sle.base.base.rule_idx) {
+                    Some(n) => *n,
+                    None => continue,
+                };
                 let trg_state = &atn.states[rule_start_nth].to_rule_start_state().unwrap();
                 if trg_state.left_recursive {
-                    let maybe_loop_end_state_nth = state.transitions().last().unwrap().target_nth();
+                    let maybe_loop_end_state_nth = match state.transitions().last() {
+                        Some(t) => t.target_nth(),
+                        None => continue,
+                    };
                     if let ATNState::LoopEnd(le) = &atn.states[maybe_loop_end_state_nth] {
-                        let rs_nth = le.base.transitions.first().unwrap().target_nth();
-                        if let ATNState::RuleStop(_) = &atn.states[rs_nth] {
-                            star_loop_entry_modifies.push(nth)
+                        if let Some(t) = le.base.transitions.first() {
+                            if let ATNState::RuleStop(_) = &atn.states[t.target_nth()] {
+                                star_loop_entry_modifies.push(nth)
+                            }
                         }
                     }
                 }
@@ -346,20 +510,124 @@ impl ATNDeserializer {
         }
     }
 
+    /// [ATNDeserializer::generate_rule_bypass_transition] wraps each parser rule into a bypass block:
+    /// `rule start -> bypass start -> (rule body | match rule token type) -> bypass stop -> rule end`,
+    /// the atom transition on the rule token type is what tree pattern matching depends on.
     // #[inline(always)]
-    fn generate_rule_bypass_transition(&self, atn: &mut ATN) {
-        if !self.des_opt.gen_rule_bypass_transitions {
-            return;
+    fn generate_rule_bypass_transition(&self, data: &mut ATNDataReader, atn: &mut ATN, state_offsets: &[usize]) -> Result<(), ATNDeserializeError> {
+        if !self.des_opt.gen_rule_bypass_transitions || atn.grammar_type != ATNType::Parser {
+            return Ok(());
         }
-        todo!()
+        data.enter(ATNSection::Rules);
+        let rules_num = atn.rule2start_state_nths.len();
+        atn.rule2token_type = (0..rules_num).map(|i| atn.max_token_type + i as isize + 1).collect();
+        for rule_idx in 0..rules_num {
+            self.generate_rule_bypass(data, atn, state_offsets, rule_idx)?;
+        }
+        Ok(())
     }
 
     // #[inline(always)]
-    fn verify_atn(&self, atn: &ATN) {
+    fn generate_rule_bypass(&self, data: &ATNDataReader, atn: &mut ATN, state_offsets: &[usize], rule_idx: usize) -> Result<(), ATNDeserializeError> {
+        let rule_start_nth = atn.rule2start_state_nths[rule_idx];
+
+        let bypass_start_nth = atn.states.len();
+        let bypass_stop_nth = bypass_start_nth + 1;
+        let mut bypass_start = ATNState::new(ATN_STATE_BLOCK_START, rule_idx, bypass_start_nth);
+        {
+            let bs = bypass_start.to_block_start_mut().unwrap();
+            bs.block_end_state_nth = bypass_stop_nth;
+            bs.contrast_set = true;
+            bs.base.decision = atn.decision2state_nth.len() as isize;
+        }
+        atn.decision2state_nth.push(bypass_start_nth);
+        let mut bypass_stop = ATNState::new(ATN_STATE_BLOCK_END, rule_idx, bypass_stop_nth);
+        {
+            let be = bypass_stop.to_block_end_mut().unwrap();
+            be.block_start_state_nth = bypass_start_nth;
+            be.contrast_set = true;
+        }
+        atn.states.push(bypass_start);
+        atn.states.push(bypass_stop);
+
+        // (state nth, transition nth) of the transition that keep its target.
+        let mut exclude_transition = None;
+        let end_nth = if atn.states[rule_start_nth].to_rule_start_state().unwrap().left_recursive {
+            // wrap from the beginning of the rule to the StarLoopEntry
+            let end_nth = atn.states.iter()
+                .filter(|state| state.rule_index() == rule_idx)
+                .filter_map(|state| state.to_star_loop_entry().map(|_| state))
+                .find(|state| {
+                    let maybe_loop_end_state_nth = match state.transitions().last() {
+                        Some(t) => t.target_nth(),
+                        None => return false,
+                    };
+                    match atn.states[maybe_loop_end_state_nth].to_loop_end() {
+                        Some(le) => le.base.epsilon_only_trans && le.base.transitions.first()
+                            .is_some_and(|t| matches!(atn.states[t.target_nth()], ATNState::RuleStop(_))),
+                        None => false,
+                    }
+                })
+                .map(|state| state.state_nth());
+            let end_nth = match end_nth {
+                Some(nth) => nth,
+                None => return Err(data.error_at(
+                    state_offsets[rule_start_nth],
+                    ATNDeserializeErrorKind::Verification {
+                        state_nth: rule_start_nth,
+                        reason: "Couldn't identify final state of the precedence rule prefix section.",
+                    },
+                )),
+            };
+            exclude_transition = Some((atn.states[end_nth].to_star_loop_entry().unwrap().star_loopback_state_nth, 0));
+            end_nth
+        } else {
+            atn.rule2stop_state_nths[rule_idx]
+        };
+
+        // all non-excluded transitions that currently target end state need to target bypass stop instead
+        for (nth, state) in atn.states.iter_mut().enumerate() {
+            for (i, trans) in state.base_atn_state_mut().transitions.iter_mut().enumerate() {
+                if exclude_transition == Some((nth, i)) {
+                    continue;
+                }
+                if trans.target_nth() == end_nth {
+                    trans.set_target_nth(bypass_stop_nth);
+                }
+            }
+        }
+
+        // all transitions leaving the rule start state need to leave bypass start instead,
+        // they are moved from the last one as ANTLR does.
+        let rule_start_transitions = std::mem::take(&mut atn.states[rule_start_nth].base_atn_state_mut().transitions);
+        for trans in rule_start_transitions.into_iter().rev() {
+            atn.states[bypass_start_nth].add_transition(trans, -1);
+        }
+
+        // link the new states
+        atn.states[rule_start_nth].add_transition(Transition::new_epsilon(bypass_start_nth, -1), -1);
+        atn.states[bypass_stop_nth].add_transition(Transition::new_epsilon(end_nth, -1), -1);
+
+        let match_nth = atn.states.len();
+        let mut match_state = ATNState::new(ATN_STATE_BASIC, rule_idx, match_nth);
+        match_state.add_transition(Transition::new_atom(bypass_stop_nth, atn.rule2token_type[rule_idx]), -1);
+        atn.states.push(match_state);
+        atn.states[bypass_start_nth].add_transition(Transition::new_epsilon(match_nth, -1), -1);
+        Ok(())
+    }
+
+    /// [ATNDeserializer::verify_atn] checks the consistency of each state, the error points at the record
+    /// of the inconsistent state in data.
+    fn verify_atn(&self, data: &ATNDataReader, atn: &ATN, state_offsets: &[usize]) -> Result<(), ATNDeserializeError> {
         if !self.des_opt.verify_atn {
-            return;
+            return Ok(());
         }
-        for state in &atn.states {
+        let fail = |nth: usize, reason: &'static str| {
+            // states generated after reading, such as the rule bypass states, have no record in data.
+            let offset = state_offsets.get(nth).copied().unwrap_or(data.data.len());
+            Err(data.error_at(offset, ATNDeserializeErrorKind::Verification { state_nth: nth, reason }))
+        };
+        for (nth, state) in atn.states.iter().enumerate() {
             let base;
             match state {
                 ATNState::Basic(b) => {
@@ -368,19 +636,19 @@ impl ATNDeserializer {
                 ATNState::RuleStart(rs) => {
                     base = &rs.base;
                     if !rs.contrast_set {
-                        panic!("RuleStart's RuleStopState nth not set, it should not happen, it's a bug.");
+                        return fail(nth, "RuleStart's RuleStopState nth not set, it should not happen, it's a bug.");
                     }
                 }
                 ATNState::BlockStart(bs) => {
                     base = &bs.base.base;
                     if !bs.contrast_set {
-                        panic!("BlockStart's BlockEndState nth not set, it should not happen, it's a bug.")
+                        return fail(nth, "BlockStart's BlockEndState nth not set, it should not happen, it's a bug.");
                     }
                 }
                 ATNState::PlusBlockStart(pbs) => {
                     base = &pbs.base.base.base;
                     if !pbs.contrast_set {
-                        panic!("PlusBlockStart's PlusLoopback nth not set, it should not happen, it's a bug.")
+                        return fail(nth, "PlusBlockStart's PlusLoopback nth not set, it should not happen, it's a bug.");
                     }
                 }
                 ATNState::StarBlockStart(sbs) => {
@@ -395,40 +663,40 @@ impl ATNDeserializer {
                 ATNState::BlockEnd(be) => {
                     base = &be.base;
                     if !be.contrast_set {
-                        panic!("BlockEnd's BlockStartState nth not set, it should not happen, it's a bug.")
+                        return fail(nth, "BlockEnd's BlockStartState nth not set, it should not happen, it's a bug.");
                     }
                 }
                 ATNState::StarLoopback(slb) => {
                     base = &slb.base;
                     if base.transitions.len() != 1 {
-                        panic!("StarLoopback transition must have only one transition.")
+                        return fail(nth, "StarLoopback transition must have only one transition.");
                     }
                     match &atn.states[base.transitions[0].target_nth()] {
                         ATNState::StarLoopEntry(_) => (),
-                        _ => panic!("StarLoopback transition must be StarLoopEntry.")
+                        _ => return fail(nth, "StarLoopback transition must be StarLoopEntry."),
                     }
                 }
                 ATNState::StarLoopEntry(sle) => {
                     base = &sle.base.base;
                     if !sle.contrast_set {
-                        panic!("StarLoopEntry's StarLoopback nth not set, it should not happen, it's a bug.")
+                        return fail(nth, "StarLoopEntry's StarLoopback nth not set, it should not happen, it's a bug.");
                     }
                     if base.transitions.len() != 2 {
-                        panic!("StarLoopEntry must have two transitions.")
+                        return fail(nth, "StarLoopEntry must have two transitions.");
                     }
                     match (&atn.states[base.transitions[0].target_nth()], &atn.states[base.transitions[1].target_nth()]) {
                         (ATNState::StarBlockStart(_), ATNState::LoopEnd(_)) => {
                             if sle.base.non_greedy {
-                                panic!("StarLoopEntry (StarBlockStart, LoopEnd) should be Greedy.")
+                                return fail(nth, "StarLoopEntry (StarBlockStart, LoopEnd) should be Greedy.");
                             }
                         }
                         (ATNState::LoopEnd(_), ATNState::StarBlockStart(_)) => {
                             if !sle.base.non_greedy {
-                                panic!("StarLoopEntry (LoopEnd, StarBlockStart) should be NonGreedy.")
+                                return fail(nth, "StarLoopEntry (LoopEnd, StarBlockStart) should be NonGreedy.");
                             }
                         }
                         _ => {
-                            panic!("IllegalState has been insert into StarLoopEntry transitions.")
+                            return fail(nth, "IllegalState has been insert into StarLoopEntry transitions.");
                         }
                     }
                 }
@@ -438,18 +706,19 @@ impl ATNDeserializer {
                 ATNState::LoopEnd(le) => {
                     base = &le.base;
                     if !le.contrast_set {
-                        panic!("LoopEnd's loopback nth not set, it should not happen, it's a bug.")
+                        return fail(nth, "LoopEnd's loopback nth not set, it should not happen, it's a bug.");
                     }
                 }
             }
             if !base.epsilon_only_trans && base.transitions.len() > 1 {
-                panic!("atn state should only has one (epsilon) transition.")
+                return fail(nth, "atn state should only has one (epsilon) transition.");
             }
             if let Some(d) = state.to_decision_state() {
                 if base.transitions.len() > 1 && d.decision < 0 {
-                    panic!("DecisionState decision must set.")
+                    return fail(nth, "DecisionState decision must set.");
                 }
             }
         }
+        Ok(())
     }
-}
\ No newline at end of file
+}
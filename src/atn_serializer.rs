@@ -0,0 +1,232 @@
+/// [ATNSerializer] serialize an [ATN] back into the i32 array that [crate::atn_deserializer::ATNDeserializer] reads,
+/// so `serialize(deserialize(data)) == data` holds for every ATN generated by the ANTLR tool, unless rule bypass
+/// transitions are generated when deserializing, see [ATNSerializer::serialize].
+/// i32 array place data in this order
+/// | serialized-version, grammar-type, max-token-type, states, non-greedy-states, precedence-states,
+/// rules, modes, sets, edges, decisions, lexer-actions(lexer only) |
+use crate::atn::ATN;
+use crate::atn_deserializer::SERIALIZED_VERSION;
+use crate::atn_state::ATNState;
+use crate::atn_type::ATNType;
+use crate::interval_set::IntervalSet;
+use crate::lexer_action::LexerAction;
+use crate::token::TOKEN_EOF;
+use crate::transition::Transition;
+
+#[derive(Debug, Default)]
+pub struct ATNSerializer {}
+
+impl ATNSerializer {
+    // #[inline(always)]
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// [ATNSerializer::serialize] writes `atn` in the layout of serialized version 4.
+    /// Edges of rule stop states are derived by the deserializer, so they are not written.
+    /// The rule bypass states generated by [crate::atn_deserialize_option::ATNDeserializeOption::gen_rule_bypass_transitions]
+    /// are never written by the ANTLR tool, they are written here as ordinary states, so the data is not what
+    /// the [ATN] is deserialized from. It deserializes to the [ATN] with bypass blocks in place, and it should
+    /// not be deserialized with bypass transitions generated again, which wraps each rule once more.
+    pub fn serialize(&self, atn: &ATN) -> Vec<i32> {
+        let mut data = Vec::with_capacity(atn.states.len() << 3);
+        data.push(SERIALIZED_VERSION as i32);
+        data.push(match atn.grammar_type {
+            ATNType::Lexer => 0,
+            ATNType::Parser => 1,
+        });
+        data.push(atn.max_token_type as i32);
+
+        self.write_states(&mut data, atn);
+        self.write_rules(&mut data, atn);
+        self.write_modes(&mut data, atn);
+        let sets = self.collect_sets(atn);
+        self.write_sets(&mut data, &sets);
+        self.write_edges(&mut data, atn, &sets);
+        self.write_decisions(&mut data, atn);
+        self.write_lexer_actions(&mut data, atn);
+        data
+    }
+
+    // #[inline(always)]
+    fn write_states(&self, data: &mut Vec<i32>, atn: &ATN) {
+        data.push(atn.states.len() as i32);
+        let mut non_greedy_nths = vec![];
+        let mut precedence_nths = vec![];
+
+        for state in &atn.states {
+            data.push(state.state_type());
+            // rule index of token start state is -1, which is stored as usize::MAX.
+            data.push(state.rule_index() as i32);
+            if let Some(le) = state.to_loop_end() {
+                data.push(le.loopback_state_nth as i32);
+            } else if let Some(bs) = state.to_block_start() {
+                data.push(bs.block_end_state_nth as i32);
+            }
+
+            if state.to_decision_state().is_some_and(|ds| ds.non_greedy) {
+                non_greedy_nths.push(state.state_nth() as i32);
+            }
+            if state.to_rule_start_state().is_some_and(|rs| rs.left_recursive) {
+                precedence_nths.push(state.state_nth() as i32);
+            }
+        }
+
+        data.push(non_greedy_nths.len() as i32);
+        data.extend(non_greedy_nths);
+        data.push(precedence_nths.len() as i32);
+        data.extend(precedence_nths);
+    }
+
+    // #[inline(always)]
+    fn write_rules(&self, data: &mut Vec<i32>, atn: &ATN) {
+        data.push(atn.rule2start_state_nths.len() as i32);
+        for (rule_idx, rs_nth) in atn.rule2start_state_nths.iter().enumerate() {
+            data.push(*rs_nth as i32);
+            if atn.grammar_type == ATNType::Lexer {
+                data.push(atn.rule2token_type[rule_idx] as i32);
+            }
+        }
+    }
+
+    // #[inline(always)]
+    fn write_modes(&self, data: &mut Vec<i32>, atn: &ATN) {
+        data.push(atn.mode2start_state_nths.len() as i32);
+        data.extend(atn.mode2start_state_nths.iter().map(|nth| *nth as i32));
+    }
+
+    /// collect the interval sets of set transitions in the order they first appear,
+    /// equal sets are shared by transitions like the ANTLR tool does.
+    // #[inline(always)]
+    fn collect_sets<'a>(&self, atn: &'a ATN) -> Vec<&'a IntervalSet> {
+        let mut sets: Vec<&IntervalSet> = vec![];
+        for state in &atn.states {
+            for trans in state.transitions() {
+                let set = match trans {
+                    Transition::Set(s) => &s.interval_set,
+                    Transition::NotSet(ns) => &ns.interval_set,
+                    _ => continue,
+                };
+                if !sets.contains(&set) {
+                    sets.push(set);
+                }
+            }
+        }
+        sets
+    }
+
+    // #[inline(always)]
+    fn write_sets(&self, data: &mut Vec<i32>, sets: &[&IntervalSet]) {
+        data.push(sets.len() as i32);
+        for set in sets {
+            let intervals = set.intervals();
+            let contains_eof = set.contains(TOKEN_EOF);
+            // EOF is written as a flag, drop the interval which only holds it.
+            let skip_first = contains_eof && intervals.first().is_some_and(|i| i.stop == TOKEN_EOF);
+            data.push((intervals.len() - skip_first as usize) as i32);
+            data.push(contains_eof as i32);
+            for (i, interval) in intervals.iter().enumerate() {
+                if i == 0 && skip_first {
+                    continue;
+                }
+                if interval.start == TOKEN_EOF {
+                    data.push(0);
+                } else {
+                    data.push(interval.start as i32);
+                }
+                data.push(interval.stop as i32);
+            }
+        }
+    }
+
+    // #[inline(always)]
+    fn write_edges(&self, data: &mut Vec<i32>, atn: &ATN, sets: &[&IntervalSet]) {
+        let edges_num: usize = atn.states.iter()
+            .filter(|state| !matches!(state, ATNState::RuleStop(_)))
+            .map(|state| state.transitions().len())
+            .sum();
+        data.push(edges_num as i32);
+
+        for state in &atn.states {
+            if let ATNState::RuleStop(_) = state {
+                continue;
+            }
+            for trans in state.transitions() {
+                let src = state.state_nth() as i32;
+                let mut trg = trans.target_nth() as i32;
+                let (mut arg1, mut arg2, mut arg3) = (0, 0, 0);
+                match trans {
+                    Transition::Epsilon(_) | Transition::Wildcard(_) => {}
+                    Transition::Range(r) => {
+                        if r.start == TOKEN_EOF {
+                            arg3 = 1;
+                        } else {
+                            arg1 = r.start as i32;
+                        }
+                        arg2 = r.stop as i32;
+                    }
+                    Transition::Rule(r) => {
+                        // the target of edge is the follow state, arg1 holds the rule start state.
+                        trg = r.follow_state_nth as i32;
+                        arg1 = r.base.target_nth as i32;
+                        arg2 = r.rule_idx as i32;
+                        arg3 = r.precedence as i32;
+                    }
+                    Transition::Predicate(p) => {
+                        arg1 = p.rule_idx as i32;
+                        arg2 = p.pre_idx as i32;
+                        arg3 = p.ctx_dependent as i32;
+                    }
+                    Transition::Atom(a) => {
+                        if a.label == TOKEN_EOF {
+                            arg3 = 1;
+                        } else {
+                            arg1 = a.label as i32;
+                        }
+                    }
+                    Transition::Action(a) => {
+                        arg1 = a.rule_idx as i32;
+                        arg2 = a.action_idx as i32;
+                        arg3 = a.ctx_dependent as i32;
+                    }
+                    Transition::Set(s) => arg1 = Self::set_index(sets, &s.interval_set),
+                    Transition::NotSet(ns) => arg1 = Self::set_index(sets, &ns.interval_set),
+                    Transition::Precedence(p) => arg1 = p.precedence as i32,
+                }
+                data.extend([src, trg, trans.transition_type(), arg1, arg2, arg3]);
+            }
+        }
+    }
+
+    // #[inline(always)]
+    fn set_index(sets: &[&IntervalSet], set: &IntervalSet) -> i32 {
+        sets.iter().position(|s| *s == set).unwrap() as i32
+    }
+
+    // #[inline(always)]
+    fn write_decisions(&self, data: &mut Vec<i32>, atn: &ATN) {
+        data.push(atn.decision2state_nth.len() as i32);
+        data.extend(atn.decision2state_nth.iter().map(|nth| *nth as i32));
+    }
+
+    // #[inline(always)]
+    fn write_lexer_actions(&self, data: &mut Vec<i32>, atn: &ATN) {
+        if atn.grammar_type != ATNType::Lexer {
+            return;
+        }
+        data.push(atn.lexer_actions.len() as i32);
+        for action in &atn.lexer_actions {
+            let (data1, data2) = match action {
+                LexerAction::Channel(c) => (c.channel, 0),
+                LexerAction::Custom(c) => (c.rule_idx, c.action_idx),
+                LexerAction::Mode(m) => (m.mode, 0),
+                LexerAction::PushMode(p) => (p.mode, 0),
+                LexerAction::Type(t) => (t.the_type, 0),
+                LexerAction::More(_) | LexerAction::PopMode(_) | LexerAction::Skip(_) => (0, 0),
+                // indexed custom actions are created while lexing, they never live in ATN.
+                LexerAction::IndexedCustom(_) => panic!("indexed custom lexer action can not be serialized"),
+            };
+            data.extend([action.action_type(), data1 as i32, data2 as i32]);
+        }
+    }
+}
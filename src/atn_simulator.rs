@@ -14,9 +14,9 @@ pub trait ATNSimulator {
 
 #[derive(Debug)]
 pub struct BaseATNSimulator {
-    atn: Arc<ATN>,
-    shared_ctx_cache: Arc<RwLock<PredictionContextCache>>,
-    decision_to_dfa: Arc<Vec<RwLock<DFA>>>,
+    pub(crate) atn: Arc<ATN>,
+    pub(crate) shared_ctx_cache: Arc<RwLock<PredictionContextCache>>,
+    pub(crate) decision_to_dfa: Arc<Vec<RwLock<DFA>>>,
 }
 
 impl BaseATNSimulator {
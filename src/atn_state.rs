@@ -1,48 +1,49 @@
+use std::fmt::{Display, Formatter};
 use crate::Nth;
 use crate::transition::Transition;
 
-pub(crate) type ATNStateType = i32;
+pub type ATNStateType = i32;
 
-const ATN_STATE_BASIC: ATNStateType = 1;
+pub(crate) const ATN_STATE_BASIC: ATNStateType = 1;
 
 /// [ATN_STATE_RULE_START]/[RuleStartState] is the start of todo
-const ATN_STATE_RULE_START: ATNStateType = 2;
+pub(crate) const ATN_STATE_RULE_START: ATNStateType = 2;
 
 /// [ATN_STATE_BLOCK_START]/[BlockStartState] is the start of a regular (...) block.
-const ATN_STATE_BLOCK_START: ATNStateType = 3;
+pub(crate) const ATN_STATE_BLOCK_START: ATNStateType = 3;
 
 /// [ATN_STATE_PLUS_BLOCK_START]/[PlusBlockStartState] is the start of a (A|B|...)+ loop. Technically it is a
 /// decision state; we don't use it for code generation. Somebody might need it,
 /// it is included for completeness. In reality, [ATN_STATE_PLUS_BLOCK_START]/[PlusBlockStartState] is the real
 /// decision-making node for A+.
-const ATN_STATE_PLUS_BLOCK_START: ATNStateType = 4;
+pub(crate) const ATN_STATE_PLUS_BLOCK_START: ATNStateType = 4;
 
 /// [StarBlockStartState] is the block that begins a closure (A|B|...)* loop.
-const ATN_STATE_STAR_BLOCK_START: ATNStateType = 5;
+pub(crate) const ATN_STATE_STAR_BLOCK_START: ATNStateType = 5;
 
 /// [ATN_STATE_TOKEN_START]/[TokenStartState] is the Tokens rule start state linking to each lexer rule start state.
-const ATN_STATE_TOKEN_START: ATNStateType = 6;
+pub(crate) const ATN_STATE_TOKEN_START: ATNStateType = 6;
 
 /// [ATN_STATE_RULE_STOP]/[RuleStopState] is the last node in the ATN for a rule, unless that rule is the
 /// start symbol. In that case, there is one transition to EOF. Later, we might
 /// encode references to all calls to this rule to compute FOLLOW sets for error handling.
-const ATN_STATE_RULE_STOP: ATNStateType = 7;
+pub(crate) const ATN_STATE_RULE_STOP: ATNStateType = 7;
 
 /// [ATN_STATE_BLOCK_END]/[BlockEndState] is a terminal node of a simple (a|b|c) block.
-const ATN_STATE_BLOCK_END: ATNStateType = 8;
+pub(crate) const ATN_STATE_BLOCK_END: ATNStateType = 8;
 
 /// [ATN_STATE_STAR_LOOPBACK]/[StarLoopbackState] is todo
-const ATN_STATE_STAR_LOOPBACK: ATNStateType = 9;
+pub(crate) const ATN_STATE_STAR_LOOPBACK: ATNStateType = 9;
 
 /// [ATN_STATE_STAR_LOOP_ENTRY]/[StarLoopEntryState] is todo
-const ATN_STATE_STAR_LOOP_ENTRY: ATNStateType = 10;
+pub(crate) const ATN_STATE_STAR_LOOP_ENTRY: ATNStateType = 10;
 
 /// [ATN_STATE_PLUS_LOOPBACK]/[PlusLoopbackState] is a decision state for A+ and (A|B)+. It has two
 /// transitions: one to the loop back to start of the block, and one to exit.
-const ATN_STATE_PLUS_LOOPBACK: ATNStateType = 11;
+pub(crate) const ATN_STATE_PLUS_LOOPBACK: ATNStateType = 11;
 
 /// [ATN_STATE_LOOP_END]/[LoopEndState] marks the end of a * or + loop.
-const ATN_STATE_LOOP_END: ATNStateType = 12;
+pub(crate) const ATN_STATE_LOOP_END: ATNStateType = 12;
 
 #[derive(Debug)]
 pub struct BaseATNState {
@@ -79,11 +80,10 @@ pub enum ATNState {
 }
 
 impl ATNState {
+    /// returns whether `state_type` is one of the serialized ATN state types.
     // #[inline(always)]
-    pub fn check_type(state_type: ATNStateType) {
-        if state_type < ATN_STATE_BASIC || state_type > ATN_STATE_LOOP_END {
-            panic!("deserialize an invalid ATN state type, it should not happen, it's a bug.")
-        }
+    pub fn is_valid_type(state_type: ATNStateType) -> bool {
+        (ATN_STATE_BASIC..=ATN_STATE_LOOP_END).contains(&state_type)
     }
 
     // #[inline]
@@ -159,11 +159,27 @@ impl ATNState {
         }
     }
 
+    /// nth(order/position) of this state in [crate::atn::ATN]'s states.
+    // #[inline(always)]
+    pub fn state_nth(&self) -> Nth {
+        self.base_atn_state().state_nth
+    }
+
+    // #[inline(always)]
+    pub fn state_type(&self) -> ATNStateType {
+        self.base_atn_state().state_type
+    }
+
     // #[inline(always)]
     pub fn rule_index(&self) -> usize {
         self.base_atn_state().rule_idx
     }
 
+    // #[inline(always)]
+    pub fn only_has_epsilon_transitions(&self) -> bool {
+        self.base_atn_state().epsilon_only_trans
+    }
+
     // #[inline(always)]
     pub fn transitions(&self) -> &Vec<Transition> {
         &self.base_atn_state().transitions
@@ -247,6 +263,14 @@ impl ATNState {
         }
     }
 
+    // #[inline(always)]
+    pub fn to_loop_end(&self) -> Option<&LoopEndState> {
+        match self {
+            ATNState::LoopEnd(le) => Some(le),
+            _ => None
+        }
+    }
+
     // #[inline(always)]
     pub fn to_decision_state(&self) -> Option<&DecisionState> {
         match self {
@@ -289,6 +313,25 @@ impl ATNState {
         }
     }
 
+    /// name of state type, the same as the serialization names in ANTLR reference runtime.
+    // #[inline(always)]
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ATNState::Basic(_) => "BASIC",
+            ATNState::RuleStart(_) => "RULE_START",
+            ATNState::BlockStart(_) => "BLOCK_START",
+            ATNState::PlusBlockStart(_) => "PLUS_BLOCK_START",
+            ATNState::StarBlockStart(_) => "STAR_BLOCK_START",
+            ATNState::TokenStart(_) => "TOKEN_START",
+            ATNState::RuleStop(_) => "RULE_STOP",
+            ATNState::BlockEnd(_) => "BLOCK_END",
+            ATNState::StarLoopback(_) => "STAR_LOOP_BACK",
+            ATNState::StarLoopEntry(_) => "STAR_LOOP_ENTRY",
+            ATNState::PlusLoopback(_) => "PLUS_LOOP_BACK",
+            ATNState::LoopEnd(_) => "LOOP_END",
+        }
+    }
+
 
     // #[inline(always)]
     fn new_basic(rule_idx: usize, state_nth: Nth) -> Self {
@@ -362,6 +405,13 @@ impl ATNState {
     }
 }
 
+/// [ATNState] is displayed as its state number like ANTLR reference runtime does.
+impl Display for ATNState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}", self.state_nth()))
+    }
+}
+
 #[derive(Debug)]
 pub struct DecisionState {
     pub(crate) base: BaseATNState,
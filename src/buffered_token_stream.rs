@@ -0,0 +1,319 @@
+use std::borrow::Cow;
+use std::rc::Rc;
+use crate::int_stream::IntStream;
+use crate::interval_set::{Interval, IntervalSet};
+use crate::token::{Token, TOKEN_DEFAULT_CHANNEL, TOKEN_EOF, TOKEN_INVALID_TYPE};
+use crate::token_source::TokenSource;
+use crate::token_stream::TokenStream;
+
+// tokens fetched at a time by [BufferedTokenStream::fill].
+const FILL_CHUNK_SIZE: isize = 1000;
+
+/// [BufferedTokenStream] loads tokens from a [TokenSource] on demand and keeps all of them in a buffer, so any
+/// previous token could be accessed by index. This stream does not filter tokens by channel, use
+/// [crate::common_token_stream::CommonTokenStream] for a parser, which skips the tokens off its channel.
+pub struct BufferedTokenStream<TS: TokenSource> {
+    pub(crate) token_source: TS,
+    // all tokens fetched from the source, the index of a token is its position.
+    pub(crate) tokens: Vec<Rc<TS::TK>>,
+    // index of the current token, -1 before the first token is fetched.
+    pub(crate) p: isize,
+    pub(crate) fetched_eof: bool,
+    // the channel of [crate::common_token_stream::CommonTokenStream], tokens on other channels are skipped
+    // by lt, lb, consume and seek.
+    pub(crate) channel: Option<isize>,
+}
+
+impl<TS: TokenSource> BufferedTokenStream<TS> {
+    // #[inline(always)]
+    pub fn new(token_source: TS) -> Self {
+        Self { token_source, tokens: vec![], p: -1, fetched_eof: false, channel: None }
+    }
+
+    // #[inline(always)]
+    pub fn token_source(&self) -> &TS {
+        &self.token_source
+    }
+
+    /// replace the token source, the tokens of the old source are discarded.
+    // #[inline]
+    pub fn set_token_source(&mut self, token_source: TS) {
+        self.token_source = token_source;
+        self.tokens.clear();
+        self.p = -1;
+        self.fetched_eof = false;
+    }
+
+    /// the tokens fetched so far.
+    // #[inline(always)]
+    pub fn tokens(&self) -> &[Rc<TS::TK>] {
+        &self.tokens
+    }
+
+    /// fetch all tokens from the source till EOF.
+    pub fn fill(&mut self) {
+        self.lazy_init();
+        while self.fetch(FILL_CHUNK_SIZE) == FILL_CHUNK_SIZE {}
+    }
+
+    /// Get the token `k` tokens before the current one, [None] if it is before the first token.
+    pub fn lb(&mut self, k: isize) -> Option<Rc<TS::TK>> {
+        self.lazy_init();
+        let channel = match self.channel {
+            None => return usize::try_from(self.p - k).ok().map(|i| Rc::clone(&self.tokens[i])),
+            Some(channel) => channel,
+        };
+        if k == 0 {
+            return None;
+        }
+        let mut i = self.p;
+        for _ in 0..k {
+            if i <= 0 {
+                return None;
+            }
+            i = self.previous_token_on_channel(i - 1, channel);
+        }
+        usize::try_from(i).ok().map(|i| Rc::clone(&self.tokens[i]))
+    }
+
+    /// Get the tokens of index `start..=stop` whose types are in `types`, or all of them if `types` is [None].
+    /// EOF is not included, and `stop` beyond the end of the stream is clamped.
+    pub fn get_tokens(&mut self, start: usize, stop: usize, types: Option<&IntervalSet>) -> Vec<Rc<TS::TK>> {
+        self.lazy_init();
+        self.sync(stop as isize);
+        self.tokens.iter()
+            .take(stop + 1)
+            .skip(start)
+            .take_while(|tk| tk.token_type() != TOKEN_EOF)
+            .filter(|tk| types.is_none_or(|types| types.contains(tk.token_type())))
+            .map(Rc::clone)
+            .collect()
+    }
+
+    /// Get the tokens right of the token at `token_index` till the next token on the default channel,
+    /// which are on `channel`, or on any channel other than the default one if `channel` is [None].
+    /// Panics if `token_index` is beyond the end of the stream.
+    pub fn hidden_tokens_to_right(&mut self, token_index: usize, channel: Option<isize>) -> Vec<Rc<TS::TK>> {
+        self.check_token_index(token_index);
+        let next_on_channel = self.next_token_on_channel(token_index as isize + 1, TOKEN_DEFAULT_CHANNEL);
+        self.filter_for_channel(token_index + 1, next_on_channel as usize, channel)
+    }
+
+    /// Get the tokens left of the token at `token_index` till the previous token on the default channel,
+    /// which are on `channel`, or on any channel other than the default one if `channel` is [None].
+    /// Panics if `token_index` is beyond the end of the stream.
+    pub fn hidden_tokens_to_left(&mut self, token_index: usize, channel: Option<isize>) -> Vec<Rc<TS::TK>> {
+        self.check_token_index(token_index);
+        if token_index == 0 {
+            return vec![];
+        }
+        let prev_on_channel = self.previous_token_on_channel(token_index as isize - 1, TOKEN_DEFAULT_CHANNEL);
+        self.filter_for_channel((prev_on_channel + 1) as usize, token_index - 1, channel)
+    }
+
+    // #[inline]
+    fn check_token_index(&mut self, token_index: usize) {
+        self.lazy_init();
+        self.sync(token_index as isize);
+        if token_index >= self.tokens.len() {
+            panic!("token index {} out of range 0..{}", token_index, self.tokens.len())
+        }
+    }
+
+    // #[inline]
+    fn filter_for_channel(&self, from: usize, to: usize, channel: Option<isize>) -> Vec<Rc<TS::TK>> {
+        self.tokens.iter()
+            .take(to + 1)
+            .skip(from)
+            .filter(|tk| channel.map_or(tk.channel() != TOKEN_DEFAULT_CHANNEL, |channel| tk.channel() == channel))
+            .map(Rc::clone)
+            .collect()
+    }
+
+    // #[inline(always)]
+    pub(crate) fn lazy_init(&mut self) {
+        if self.p == -1 {
+            self.sync(0);
+            self.p = self.adjust_seek_index(0);
+        }
+    }
+
+    /// make sure the token at index `i` is fetched, returns false if the stream ends before it.
+    // #[inline]
+    pub(crate) fn sync(&mut self, i: isize) -> bool {
+        let n = i - self.tokens.len() as isize + 1;
+        n <= 0 || self.fetch(n) >= n
+    }
+
+    /// fetch `n` tokens from the source, returns the number of tokens fetched, which is less than `n` at EOF.
+    pub(crate) fn fetch(&mut self, n: isize) -> isize {
+        if self.fetched_eof {
+            return 0;
+        }
+        for i in 0..n {
+            let tk = self.token_source.next_token();
+            tk.set_token_index(self.tokens.len() as isize);
+            let is_eof = tk.token_type() == TOKEN_EOF;
+            self.tokens.push(Rc::new(tk));
+            if is_eof {
+                self.fetched_eof = true;
+                return i + 1;
+            }
+        }
+        n
+    }
+
+    /// the index of the token to be the current one when seeking to `i`.
+    // #[inline(always)]
+    fn adjust_seek_index(&mut self, i: isize) -> isize {
+        match self.channel {
+            None => i,
+            Some(channel) => self.next_token_on_channel(i, channel),
+        }
+    }
+
+    /// the index of the first token on `channel` from index `i`, or EOF if there is none.
+    pub(crate) fn next_token_on_channel(&mut self, mut i: isize, channel: isize) -> isize {
+        self.sync(i);
+        if i >= self.tokens.len() as isize {
+            return self.tokens.len() as isize - 1;
+        }
+        while self.tokens[i as usize].channel() != channel {
+            if self.tokens[i as usize].token_type() == TOKEN_EOF {
+                return i;
+            }
+            i += 1;
+            self.sync(i);
+        }
+        i
+    }
+
+    /// the index of the last token on `channel` till index `i`, or -1 if there is none.
+    pub(crate) fn previous_token_on_channel(&mut self, mut i: isize, channel: isize) -> isize {
+        self.sync(i);
+        if i >= self.tokens.len() as isize {
+            return self.tokens.len() as isize - 1;
+        }
+        while i >= 0 {
+            let tk = &self.tokens[i as usize];
+            if tk.token_type() == TOKEN_EOF || tk.channel() == channel {
+                return i;
+            }
+            i -= 1;
+        }
+        i
+    }
+}
+
+impl<TS: TokenSource> IntStream for BufferedTokenStream<TS> {
+    /// Panics if the current token is EOF.
+    fn consume(&mut self) {
+        // the current token is not EOF if a token is fetched after it.
+        let skip_eof_check = self.p >= 0 && if self.fetched_eof {
+            self.p < self.tokens.len() as isize - 1
+        } else {
+            self.p < self.tokens.len() as isize
+        };
+        if !skip_eof_check && self.la(1) == TOKEN_EOF {
+            panic!("can not consume EOF")
+        }
+        if self.sync(self.p + 1) {
+            self.p = self.adjust_seek_index(self.p + 1);
+        }
+    }
+
+    // #[inline]
+    fn la(&mut self, i: isize) -> isize {
+        if i == 0 {
+            panic!("undefined invocation: LA(0)")
+        }
+        self.lt(i).map_or(TOKEN_INVALID_TYPE, |tk| tk.token_type())
+    }
+
+    // #[inline(always)]
+    fn index(&self) -> isize {
+        self.p
+    }
+
+    /// seek to the token at `index`, or the next token on the channel of the stream.
+    /// `index` beyond the end of the stream is clamped to EOF.
+    fn seek(&mut self, index: isize) {
+        self.lazy_init();
+        self.sync(index);
+        self.p = self.adjust_seek_index(index.min(self.tokens.len() as isize - 1));
+    }
+
+    /// number of tokens fetched so far, all tokens are fetched after [BufferedTokenStream::fill].
+    // #[inline(always)]
+    fn size(&self) -> isize {
+        self.tokens.len() as isize
+    }
+
+    // #[inline(always)]
+    fn source_name(&self) -> Cow<'_, str> {
+        self.token_source.source_name()
+    }
+}
+
+impl<TS: TokenSource> TokenStream for BufferedTokenStream<TS> {
+    type TK = TS::TK;
+
+    fn lt(&mut self, k: isize) -> Option<Rc<Self::TK>> {
+        self.lazy_init();
+        if k == 0 {
+            return None;
+        }
+        if k < 0 {
+            return self.lb(-k);
+        }
+        let i = match self.channel {
+            None => {
+                let i = self.p + k - 1;
+                self.sync(i);
+                i
+            }
+            Some(channel) => {
+                let mut i = self.p;
+                for _ in 1..k {
+                    if self.sync(i + 1) {
+                        i = self.next_token_on_channel(i + 1, channel);
+                    }
+                }
+                i
+            }
+        };
+        // EOF is the last token.
+        let i = (i as usize).min(self.tokens.len() - 1);
+        Some(Rc::clone(&self.tokens[i]))
+    }
+
+    // #[inline]
+    fn token_at(&self, idx: usize) -> Rc<Self::TK> {
+        match self.tokens.get(idx) {
+            Some(tk) => Rc::clone(tk),
+            None => panic!("token index {} out of range 0..{}", idx, self.tokens.len()),
+        }
+    }
+
+    fn text(&mut self, interval: Interval) -> String {
+        let (Ok(start), Ok(stop)) = (usize::try_from(interval.start), usize::try_from(interval.stop)) else {
+            return String::new();
+        };
+        self.lazy_init();
+        self.sync(interval.stop);
+        let mut text = String::new();
+        for tk in self.tokens.iter().take(stop + 1).skip(start) {
+            if tk.token_type() == TOKEN_EOF {
+                break;
+            }
+            text.push_str(&tk.text());
+        }
+        text
+    }
+
+    // #[inline]
+    fn all_text(&mut self) -> String {
+        self.fill();
+        self.text(Interval::new(0, self.tokens.len() as isize - 1))
+    }
+}
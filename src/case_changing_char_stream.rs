@@ -0,0 +1,115 @@
+use std::borrow::Cow;
+use crate::char_stream::CharStream;
+use crate::int_stream::IntStream;
+
+/// [CaseChangingCharStream] wraps a [CharStream] and upper or lower cases the symbols returned by
+/// [IntStream::la], so a case-insensitive grammar only needs to match one case, [CharStream::text] still
+/// returns the original text.
+///
+/// Case mapping is full Unicode, a char mapped into multiple chars(e.g. `ß` is upper cased as `SS`) is kept as
+/// it is, because a symbol must stay one code point.
+pub struct CaseChangingCharStream<CS: CharStream> {
+    stream: CS,
+    upper: bool,
+}
+
+impl<CS: CharStream> CaseChangingCharStream<CS> {
+    /// returns a stream upper cases the symbols of `stream` if `upper`, otherwise lower cases them.
+    // #[inline(always)]
+    pub fn new(stream: CS, upper: bool) -> Self {
+        Self { stream, upper }
+    }
+
+    // #[inline(always)]
+    pub fn inner(&self) -> &CS {
+        &self.stream
+    }
+
+    // #[inline(always)]
+    pub fn into_inner(self) -> CS {
+        self.stream
+    }
+
+    // #[inline]
+    fn change_case(&self, c: char) -> char {
+        if self.upper {
+            single_char(c.to_uppercase()).unwrap_or(c)
+        } else {
+            single_char(c.to_lowercase()).unwrap_or(c)
+        }
+    }
+}
+
+// #[inline(always)]
+fn single_char(mut chars: impl Iterator<Item=char>) -> Option<char> {
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+impl<CS: CharStream> IntStream for CaseChangingCharStream<CS> {
+    // #[inline(always)]
+    fn consume(&mut self) {
+        self.stream.consume()
+    }
+
+    // #[inline]
+    fn la(&mut self, i: isize) -> isize {
+        let c = self.stream.la(i);
+        if c <= 0 {
+            return c;
+        }
+        match char::from_u32(c as u32) {
+            Some(ch) => self.change_case(ch) as isize,
+            None => c,
+        }
+    }
+
+    // #[inline(always)]
+    fn mark(&mut self) -> isize {
+        self.stream.mark()
+    }
+
+    // #[inline(always)]
+    fn release(&mut self, marker: isize) {
+        self.stream.release(marker)
+    }
+
+    // #[inline(always)]
+    fn index(&self) -> isize {
+        self.stream.index()
+    }
+
+    // #[inline(always)]
+    fn seek(&mut self, index: isize) {
+        self.stream.seek(index)
+    }
+
+    // #[inline(always)]
+    fn size(&self) -> isize {
+        self.stream.size()
+    }
+
+    // #[inline(always)]
+    fn source_name(&self) -> Cow<'_, str> {
+        self.stream.source_name()
+    }
+}
+
+impl<CS: CharStream> CharStream for CaseChangingCharStream<CS> {
+    // #[inline(always)]
+    fn text(&self, start: usize, end: usize) -> Cow<'_, str> {
+        self.stream.text(start, end)
+    }
+
+    // #[inline(always)]
+    fn byte_offset(&self, index: isize) -> Option<usize> {
+        self.stream.byte_offset(index)
+    }
+
+    // #[inline(always)]
+    fn utf8_text(&self) -> Option<&str> {
+        self.stream.utf8_text()
+    }
+}
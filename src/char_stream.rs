@@ -7,4 +7,11 @@ pub trait CharStream: IntStream {
     /// Text intercept as characters within this input stream.
     /// Guaranteed to not throw an exception
     fn text(&self, start: usize, end: usize) -> Cow<'_, str>;
-}
\ No newline at end of file
+
+    /// byte offset of the char at `index` in the UTF-8 text of this stream, `index` of [IntStream::size] is
+    /// the length of text. [None] if the stream is not UTF-8 text or does not know the offset.
+    fn byte_offset(&self, _index: isize) -> Option<usize> { None }
+
+    /// the whole UTF-8 text of this stream if it is in memory, [CharStream::byte_offset] is an offset in it.
+    fn utf8_text(&self) -> Option<&str> { None }
+}
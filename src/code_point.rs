@@ -1,6 +1,8 @@
 use std::borrow::Cow;
+use std::cell::Cell;
 use std::char::REPLACEMENT_CHARACTER;
 use std::fmt::Debug;
+use crate::errors::Utf16Error;
 
 pub trait CodePoints {
     /// code point at the `pos` of [CodePoints] and try to convert to [u32].
@@ -12,15 +14,21 @@ pub trait CodePoints {
     /// returns the text for the interval `start`..`end` of characters within this CodePoints
     /// include the end index, the symbol of each must convert to character
     fn text_range(&self, start: usize, end: usize) -> Cow<'_, str>;
+
+    /// byte offset of the code point at `pos` in the UTF-8 text, `pos` of [CodePoints::size] is the length of text.
+    /// [None] if the code points are not UTF-8 text or `pos` is out of range.
+    fn byte_offset(&self, _pos: usize) -> Option<usize> { None }
+
+    /// the code points as UTF-8 text, [None] if they are not UTF-8 text.
+    fn as_utf8(&self) -> Option<&str> { None }
 }
 
-impl CodePoints for String {
+/// [str] is indexed by chars, text is borrowed from it. A char is found by scanning from the start,
+/// use [Utf8] to access chars in O(1).
+impl CodePoints for str {
     // #[inline]
     fn code_point_at(&self, pos: usize) -> Option<u32> {
-        if pos >= self.len() {
-            return None;
-        }
-        Some(self.chars().nth(pos).unwrap_or(REPLACEMENT_CHARACTER) as u32)
+        self.chars().nth(pos).map(|c| c as u32)
     }
 
     // #[inline]
@@ -42,6 +50,43 @@ impl CodePoints for String {
         let byte_idx_end = byte_idx_by_chars_pass_through(self, byte_idx_start, end - start + 1);
         Cow::Borrowed(&self[byte_idx_start..byte_idx_end])
     }
+
+    // #[inline]
+    fn byte_offset(&self, pos: usize) -> Option<usize> {
+        self.char_indices().map(|(i, _)| i).chain(std::iter::once(self.len())).nth(pos)
+    }
+
+    // #[inline(always)]
+    fn as_utf8(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+impl CodePoints for String {
+    // #[inline(always)]
+    fn code_point_at(&self, pos: usize) -> Option<u32> {
+        self.as_str().code_point_at(pos)
+    }
+
+    // #[inline(always)]
+    fn size(&self) -> usize {
+        self.as_str().size()
+    }
+
+    // #[inline(always)]
+    fn text_range(&self, start: usize, end: usize) -> Cow<'_, str> {
+        self.as_str().text_range(start, end)
+    }
+
+    // #[inline(always)]
+    fn byte_offset(&self, pos: usize) -> Option<usize> {
+        self.as_str().byte_offset(pos)
+    }
+
+    // #[inline(always)]
+    fn as_utf8(&self) -> Option<&str> {
+        Some(self.as_str())
+    }
 }
 
 /// T convert to `u32` and as `isize`, due to `isize` not implementation the trait `From<u16>`
@@ -76,6 +121,215 @@ impl<T: ?Sized + Copy + Debug + Into<u32>> CodePoints for Vec<T> {
     }
 }
 
+/// [SurrogatePolicy] defines how an unpaired surrogate of UTF-16 input is decoded, see [Utf16].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SurrogatePolicy {
+    /// decode it as U+FFFD, which is what a lossy decoder does.
+    #[default]
+    Replace,
+    /// keep it as a symbol of its own value, so a grammar could match it, it is U+FFFD in text.
+    Preserve,
+    /// fail the decoding with [Utf16Error].
+    Error,
+}
+
+/// [Utf16] is UTF-16 input decoded into code points, surrogate pairs are decoded as one code point
+/// and indexed as one symbol, the way ANTLR indexes input.
+#[derive(Debug, Clone)]
+pub struct Utf16 {
+    code_points: Vec<u32>,
+}
+
+impl Utf16 {
+    /// decode `units`, unpaired surrogates are handled by `policy`.
+    pub fn decode(units: &[u16], policy: SurrogatePolicy) -> Result<Self, Utf16Error> {
+        let mut code_points = Vec::with_capacity(units.len());
+        let mut index = 0;
+        for decoded in char::decode_utf16(units.iter().copied()) {
+            match decoded {
+                Ok(c) => {
+                    code_points.push(c as u32);
+                    index += c.len_utf16();
+                }
+                Err(e) => {
+                    let unit = e.unpaired_surrogate();
+                    match policy {
+                        SurrogatePolicy::Replace => code_points.push(REPLACEMENT_CHARACTER as u32),
+                        SurrogatePolicy::Preserve => code_points.push(unit as u32),
+                        SurrogatePolicy::Error => return Err(Utf16Error { index, unit }),
+                    }
+                    index += 1;
+                }
+            }
+        }
+        Ok(Self { code_points })
+    }
+}
+
+impl CodePoints for Utf16 {
+    // #[inline(always)]
+    fn code_point_at(&self, pos: usize) -> Option<u32> {
+        self.code_points.code_point_at(pos)
+    }
+
+    // #[inline(always)]
+    fn size(&self) -> usize {
+        self.code_points.len()
+    }
+
+    // #[inline(always)]
+    fn text_range(&self, start: usize, end: usize) -> Cow<'_, str> {
+        self.code_points.text_range(start, end)
+    }
+}
+
+// chars of a block of [Utf8], the byte offset of the first char of each block is indexed.
+const UTF8_CHARS_PER_BLOCK: usize = 128;
+
+/// [Utf8] is UTF-8 text indexed by chars, text is borrowed from `S`. ASCII text needs no index, otherwise the byte
+/// offset of every 128th char is indexed when created, and a char is found by scanning at most 127 chars of its block,
+/// so a char is accessed in O(1) and the index takes 1/16 of the text at most, see [Utf8::index_size].
+/// The last char found by scanning is remembered, so scanning chars in order visits each char once.
+#[derive(Debug, Clone)]
+pub struct Utf8<S = String> {
+    text: S,
+    // number of chars
+    len: usize,
+    // byte offset of the first char of each block followed by the length of text, empty if text is ASCII.
+    block_offsets: Vec<usize>,
+    // char index and byte offset of the last char found by scanning a block.
+    cursor: Cell<(usize, usize)>,
+}
+
+impl<S: AsRef<str>> Utf8<S> {
+    pub fn new(text: S) -> Self {
+        let str = text.as_ref();
+        if str.is_ascii() {
+            return Self { len: str.len(), text, block_offsets: vec![], cursor: Cell::default() };
+        }
+        let mut len = 0;
+        let mut block_offsets = Vec::with_capacity(str.len() / UTF8_CHARS_PER_BLOCK + 2);
+        for (i, _) in str.char_indices() {
+            if len % UTF8_CHARS_PER_BLOCK == 0 {
+                block_offsets.push(i);
+            }
+            len += 1;
+        }
+        block_offsets.push(str.len());
+        block_offsets.shrink_to_fit();
+        Self { text, len, block_offsets, cursor: Cell::default() }
+    }
+
+    // #[inline(always)]
+    pub fn as_str(&self) -> &str {
+        self.text.as_ref()
+    }
+
+    // #[inline(always)]
+    pub fn into_inner(self) -> S {
+        self.text
+    }
+
+    /// bytes taken by the char index on the heap, 0 if text is ASCII.
+    // #[inline(always)]
+    pub fn index_size(&self) -> usize {
+        self.block_offsets.capacity() * size_of::<usize>()
+    }
+
+    /// byte offset of the char `pos`, `pos` is at most the number of chars.
+    // #[inline]
+    fn offset_of(&self, pos: usize) -> usize {
+        if self.block_offsets.is_empty() {
+            return pos;
+        }
+        let (block, k) = (pos / UTF8_CHARS_PER_BLOCK, pos % UTF8_CHARS_PER_BLOCK);
+        let start = self.block_offsets[block];
+        if k == 0 {
+            return start;
+        }
+        let end = self.block_offsets[block + 1];
+        // a block of ASCII has a byte for each char.
+        if end - start == UTF8_CHARS_PER_BLOCK.min(self.len - block * UTF8_CHARS_PER_BLOCK) {
+            return start + k;
+        }
+        // scan from the last char found if it is before `pos` in the block.
+        let (mut from_pos, mut from) = (pos - k, start);
+        let (cursor_pos, cursor_offset) = self.cursor.get();
+        if cursor_pos / UTF8_CHARS_PER_BLOCK == block && cursor_pos <= pos {
+            (from_pos, from) = (cursor_pos, cursor_offset);
+        }
+        // `pos` is the number of chars if it is not found in the last block.
+        let offset = from + self.as_str()[from..end].char_indices().nth(pos - from_pos).map_or(end - from, |(i, _)| i);
+        self.cursor.set((pos, offset));
+        offset
+    }
+}
+
+impl<S: AsRef<str>> CodePoints for Utf8<S> {
+    // #[inline]
+    fn code_point_at(&self, pos: usize) -> Option<u32> {
+        if pos >= self.size() {
+            return None;
+        }
+        self.as_str()[self.offset_of(pos)..].chars().next().map(|c| c as u32)
+    }
+
+    // #[inline]
+    fn size(&self) -> usize {
+        self.len
+    }
+
+    // #[inline]
+    fn text_range(&self, start: usize, end: usize) -> Cow<'_, str> {
+        let size = self.size();
+        if start > end || start >= size {
+            return Cow::Borrowed("");
+        }
+        let end = end.min(size - 1);
+        Cow::Borrowed(&self.as_str()[self.offset_of(start)..self.offset_of(end + 1)])
+    }
+
+    // #[inline]
+    fn byte_offset(&self, pos: usize) -> Option<usize> {
+        (pos <= self.size()).then(|| self.offset_of(pos))
+    }
+
+    // #[inline(always)]
+    fn as_utf8(&self) -> Option<&str> {
+        Some(self.as_str())
+    }
+}
+
+/// [MappedUtf8] is a memory-mapped UTF-8 file, it is validated once when mapped,
+/// and read as a [str] over the mapped bytes without copying them, see [Utf8].
+#[cfg(feature = "mmap")]
+#[derive(Debug)]
+pub struct MappedUtf8 {
+    map: memmap2::Mmap,
+}
+
+#[cfg(feature = "mmap")]
+impl MappedUtf8 {
+    /// map `file` into memory, returns [std::io::ErrorKind::InvalidData] if it is not UTF-8.
+    ///
+    /// # Safety
+    /// the file must not be modified or truncated while it is mapped, see [memmap2::Mmap].
+    pub unsafe fn map(file: &std::fs::File) -> std::io::Result<Self> {
+        let map = memmap2::Mmap::map(file)?;
+        std::str::from_utf8(&map).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        Ok(Self { map })
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl AsRef<str> for MappedUtf8 {
+    // #[inline(always)]
+    fn as_ref(&self) -> &str {
+        // the bytes are validated when mapped.
+        unsafe { std::str::from_utf8_unchecked(&self.map) }
+    }
+}
+
 /// convert the char index: get the byte index from byte index: `start_char_byte_idx` and pass through `chars_num`
 // #[inline]
 fn byte_idx_by_chars_pass_through(s: &str, start_char_byte_idx: usize, mut chars_num: usize) -> usize {
@@ -1,26 +1,137 @@
+use std::borrow::Cow;
 use std::rc::Rc;
+use crate::buffered_token_stream::BufferedTokenStream;
+use crate::int_stream::IntStream;
+use crate::interval_set::{Interval, IntervalSet};
+use crate::token::Token;
 use crate::token_source::TokenSource;
+use crate::token_stream::TokenStream;
 
-// CommonTokenStream is an implementation of TokenStream that loads tokens from
-// a TokenSource on-demand and places the tokens in a buffer to provide access
-// to any previous token by index. This token stream ignores the value of
-// Token.getChannel. If your parser requires the token stream filter tokens to
-// only those on a particular channel, such as Token.DEFAULT_CHANNEL or
-// Token.HIDDEN_CHANNEL, use a filtering token stream such a CommonTokenStream.
+/// [CommonTokenStream] is a [BufferedTokenStream] which only sees the tokens on its channel, usually
+/// [crate::token::TOKEN_DEFAULT_CHANNEL]. [TokenStream::lt], [CommonTokenStream::lb], [IntStream::consume] and
+/// [IntStream::seek] skip the tokens on other channels, but they are still in the buffer, and they are
+/// indexed and counted by [IntStream::size] as well, so [TokenStream::text] includes their text.
 pub struct CommonTokenStream<TS: TokenSource> {
-    pub(crate) token_source: TS,
-
-    pub(crate) channel: isize,
-    pub(crate) tokens: Vec<Rc<<TS as TokenSource>::TK>>,
+    pub(crate) base: BufferedTokenStream<TS>,
 }
 
 impl<TS: TokenSource> CommonTokenStream<TS> {
     // #[inline(always)]
     pub fn new(lexer: TS, channel: isize) -> Self {
-        Self {
-            channel,
-            tokens: vec![],
-            token_source: lexer,
-        }
+        let mut base = BufferedTokenStream::new(lexer);
+        base.channel = Some(channel);
+        Self { base }
+    }
+
+    // #[inline(always)]
+    pub fn channel(&self) -> isize {
+        self.base.channel.unwrap()
+    }
+
+    // #[inline(always)]
+    pub fn token_source(&self) -> &TS {
+        self.base.token_source()
+    }
+
+    // #[inline(always)]
+    pub fn set_token_source(&mut self, token_source: TS) {
+        self.base.set_token_source(token_source)
+    }
+
+    // #[inline(always)]
+    pub fn tokens(&self) -> &[Rc<TS::TK>] {
+        self.base.tokens()
+    }
+
+    // #[inline(always)]
+    pub fn fill(&mut self) {
+        self.base.fill()
+    }
+
+    /// Get the token `k` tokens on the channel before the current one, [None] if there is none.
+    // #[inline(always)]
+    pub fn lb(&mut self, k: isize) -> Option<Rc<TS::TK>> {
+        self.base.lb(k)
+    }
+
+    /// see [BufferedTokenStream::get_tokens], tokens on all channels are included.
+    // #[inline(always)]
+    pub fn get_tokens(&mut self, start: usize, stop: usize, types: Option<&IntervalSet>) -> Vec<Rc<TS::TK>> {
+        self.base.get_tokens(start, stop, types)
+    }
+
+    /// see [BufferedTokenStream::hidden_tokens_to_right].
+    // #[inline(always)]
+    pub fn hidden_tokens_to_right(&mut self, token_index: usize, channel: Option<isize>) -> Vec<Rc<TS::TK>> {
+        self.base.hidden_tokens_to_right(token_index, channel)
+    }
+
+    /// see [BufferedTokenStream::hidden_tokens_to_left].
+    // #[inline(always)]
+    pub fn hidden_tokens_to_left(&mut self, token_index: usize, channel: Option<isize>) -> Vec<Rc<TS::TK>> {
+        self.base.hidden_tokens_to_left(token_index, channel)
+    }
+
+    /// number of tokens on the channel of the stream, EOF included. All tokens are fetched.
+    pub fn number_of_on_channel_tokens(&mut self) -> usize {
+        self.fill();
+        let channel = self.channel();
+        self.base.tokens.iter().filter(|tk| tk.channel() == channel).count()
+    }
+}
+
+impl<TS: TokenSource> IntStream for CommonTokenStream<TS> {
+    // #[inline(always)]
+    fn consume(&mut self) {
+        self.base.consume()
+    }
+
+    // #[inline(always)]
+    fn la(&mut self, i: isize) -> isize {
+        self.base.la(i)
+    }
+
+    // #[inline(always)]
+    fn index(&self) -> isize {
+        self.base.index()
+    }
+
+    // #[inline(always)]
+    fn seek(&mut self, index: isize) {
+        self.base.seek(index)
+    }
+
+    // #[inline(always)]
+    fn size(&self) -> isize {
+        self.base.size()
+    }
+
+    // #[inline(always)]
+    fn source_name(&self) -> Cow<'_, str> {
+        self.base.source_name()
+    }
+}
+
+impl<TS: TokenSource> TokenStream for CommonTokenStream<TS> {
+    type TK = TS::TK;
+
+    // #[inline(always)]
+    fn lt(&mut self, k: isize) -> Option<Rc<Self::TK>> {
+        self.base.lt(k)
+    }
+
+    // #[inline(always)]
+    fn token_at(&self, idx: usize) -> Rc<Self::TK> {
+        self.base.token_at(idx)
+    }
+
+    // #[inline(always)]
+    fn text(&mut self, interval: Interval) -> String {
+        self.base.text(interval)
     }
-}
\ No newline at end of file
+
+    // #[inline(always)]
+    fn all_text(&mut self) -> String {
+        self.base.all_text()
+    }
+}
@@ -1,8 +1,9 @@
+use std::collections::HashMap;
 use crate::atn::ATN;
 use crate::atn_config_set::ATNConfigSet;
 use crate::atn_state::ATNState;
 use crate::dfa_state::DFAState;
-use crate::misc::jcollect::JStore;
+use crate::misc::murmur3::MurmurHash;
 use crate::Nth;
 
 #[derive(Debug)]
@@ -11,10 +12,12 @@ pub struct DFA {
     pub atn_start_state: Nth,
     pub decision: usize,
 
-    states: JStore<DFAState>,
+    states: Vec<DFAState>,
+    // murmur hash of state to nths of states, states are unique by their configs.
+    states_lookup: HashMap<u32, Vec<Nth>>,
 
-    // state 0
-    pub s0: Option<DFAState>,
+    /// nth of state 0 in states.
+    pub s0: Option<Nth>,
 
     pub(crate) precedence_dfa: bool,
 }
@@ -27,17 +30,51 @@ impl DFA {
             decision,
             s0: None,
             precedence_dfa: false,
-            states: JStore::default(),
+            states: vec![],
+            states_lookup: HashMap::new(),
         };
         if let ATNState::StarLoopEntry(sle) = &atn.states[atn_start_state] {
             if sle.precedence_decision {
                 dfa.precedence_dfa = true;
+                // the start state of precedence dfa only holds edges to the real start states,
+                // so it is never looked up by configs.
                 let mut ds = DFAState::new(0, Box::new(ATNConfigSet::new(false)));
                 ds.accept_state = false;
                 ds.requires_full_context = false;
-                dfa.s0 = Some(ds);
+                dfa.states.push(ds);
+                dfa.s0 = Some(0);
             }
         }
         dfa
     }
-}
\ No newline at end of file
+
+    // #[inline(always)]
+    pub fn states(&self) -> &[DFAState] {
+        &self.states
+    }
+
+    // #[inline(always)]
+    pub fn state(&self, nth: Nth) -> &DFAState {
+        &self.states[nth]
+    }
+
+    // #[inline(always)]
+    pub(crate) fn state_mut(&mut self, nth: Nth) -> &mut DFAState {
+        &mut self.states[nth]
+    }
+
+    /// [DFA::add_state] adds `state` if there is no state with the same configs, and returns the nth of the
+    /// state in this DFA, `state_nth` of `state` is assigned by this DFA.
+    // #[inline]
+    pub(crate) fn add_state(&mut self, mut state: DFAState) -> Nth {
+        let nths = self.states_lookup.entry(state.murmur()).or_default();
+        if let Some(existing) = nths.iter().find(|nth| self.states[**nth] == state) {
+            return *existing;
+        }
+        let nth = self.states.len();
+        state.state_nth = nth;
+        nths.push(nth);
+        self.states.push(state);
+        nth
+    }
+}
@@ -1,23 +1,32 @@
 use std::fmt::{Display, Formatter};
+use std::sync::Arc;
 use crate::atn::ATN_INVALID_ALT_NUMBER;
 use crate::atn_config_set::ATNConfigSet;
+use crate::lexer_action_executor::LexerActionExecutor;
 use crate::misc::murmur3::MurmurHash;
 use crate::Nth;
 use crate::semantic_context::SemanticContext;
 
+/// [DFA_ERROR_STATE] is the target of an edge on which no state could be reached,
+/// it is cached in edges to avoid computing the failure again.
+pub const DFA_ERROR_STATE: Nth = usize::MAX;
+
 #[derive(Debug)]
 pub struct DFAState {
     /// nth(order/position) of this state in [crate::dfa::DFA]'s states.
     pub(crate) state_nth: Nth,
     pub(crate) configs: Box<ATNConfigSet>,
-    /// store edges(nth/order/position of other [DFAState]) in [crate::dfa::DFA]'s states.
-    pub edges: Vec<Nth>,
+    /// store edges(nth/order/position of other [DFAState]) in [crate::dfa::DFA]'s states,
+    /// [None] if the edge is not computed yet, [DFA_ERROR_STATE] if the symbol leads to nowhere.
+    pub edges: Vec<Option<Nth>>,
     pub accept_state: bool,
     /// [prediction] is the ttype we match or alt we predict if the state is accept.
     /// Set to [ATN_INVALID_ALT_NUMBER] when [predicates] is not empty or [requires_full_context] is true.
     pub prediction: isize,
     pub requires_full_context: bool,
     pub predicates: Vec<PredPrediction>,
+    /// lexer only, actions to execute when the token of this accept state is matched.
+    pub lexer_action_executor: Option<Arc<LexerActionExecutor>>,
 }
 
 /// [PredPrediction] maps a predicate to a predicted alternative.
@@ -34,16 +43,17 @@ impl Display for PredPrediction {
     }
 }
 
+/// two [DFAState]s are equal if they have the same configs, which is the identity of a DFA state.
 impl PartialEq for DFAState {
     fn eq(&self, other: &Self) -> bool {
-        todo!()
+        self.configs == other.configs
     }
 }
 
 impl MurmurHash for DFAState {
     // #[inline]
     fn murmur(&self) -> u32 {
-        todo!()
+        self.configs.murmur()
     }
 }
 
@@ -58,6 +68,7 @@ impl DFAState {
             prediction: ATN_INVALID_ALT_NUMBER,
             requires_full_context: false,
             predicates: vec![],
+            lexer_action_executor: None,
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,180 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+use crate::atn::ATN;
+use crate::atn_state::ATNState;
+use crate::atn_type::ATNType;
+use crate::interval_set::IntervalSet;
+use crate::Nth;
+use crate::recognizer::Recognizer;
+use crate::token::TOKEN_EOF;
+use crate::transition::Transition;
+
+/// [DOTGenerator] renders an [ATN] as a graphviz DOT graph, which helps a lot when a grammar misbehaves.
+/// Nodes are labeled by state number, state type, rule name and decision number.
+/// Edges are labeled by transition, atoms, ranges and sets are shown as characters for a lexer [ATN],
+/// or as token names from the vocabulary of `recog` for a parser [ATN].
+/// Without `recog`, rules and tokens are shown by their index and type.
+pub struct DOTGenerator<'a> {
+    atn: &'a ATN,
+    recog: Option<&'a dyn Recognizer>,
+}
+
+impl<'a> DOTGenerator<'a> {
+    // #[inline(always)]
+    pub fn new(atn: &'a ATN, recog: Option<&'a dyn Recognizer>) -> Self {
+        Self { atn, recog }
+    }
+
+    /// [DOTGenerator::atn_to_dot] renders all states and transitions of the [ATN],
+    /// including the edges of rule stop states back to the follow states.
+    pub fn atn_to_dot(&self) -> String {
+        let nths: Vec<Nth> = (0..self.atn.states.len()).collect();
+        self.render(&nths, false)
+    }
+
+    /// [DOTGenerator::rule_to_dot] renders the sub-ATN of rule `rule_idx`.
+    /// A rule transition is drawn to its follow state and labeled by the invoked rule, the invoked rule
+    /// is not rendered, neither are the edges leaving the rule stop state.
+    /// Returns [None] if there is no such rule.
+    pub fn rule_to_dot(&self, rule_idx: usize) -> Option<String> {
+        let start_nth = *self.atn.rule2start_state_nths.get(rule_idx)?;
+        Some(self.render(&self.reachable_from(start_nth), true))
+    }
+
+    /// [DOTGenerator::mode_to_dot] renders the sub-ATN of lexer mode `mode`, which starts from the token start
+    /// state of the mode and reaches all lexer rules of the mode.
+    /// Returns [None] if there is no such mode.
+    pub fn mode_to_dot(&self, mode: usize) -> Option<String> {
+        let start_nth = *self.atn.mode2start_state_nths.get(mode)?;
+        Some(self.render(&self.reachable_from(start_nth), true))
+    }
+
+    /// states reachable from `start_nth` without descending into invoked rules, in order of state number.
+    // #[inline(always)]
+    fn reachable_from(&self, start_nth: Nth) -> Vec<Nth> {
+        let mut visited = vec![false; self.atn.states.len()];
+        let mut queue = VecDeque::from([start_nth]);
+        visited[start_nth] = true;
+        while let Some(nth) = queue.pop_front() {
+            let state = &self.atn.states[nth];
+            if let ATNState::RuleStop(_) = state {
+                continue;
+            }
+            for trans in state.transitions() {
+                let trg_nth = Self::edge_target(trans, true);
+                if !visited[trg_nth] {
+                    visited[trg_nth] = true;
+                    queue.push_back(trg_nth);
+                }
+            }
+        }
+        (0..visited.len()).filter(|nth| visited[*nth]).collect()
+    }
+
+    // #[inline(always)]
+    fn edge_target(trans: &Transition, sub_atn: bool) -> Nth {
+        match trans {
+            Transition::Rule(r) if sub_atn => r.follow_state_nth,
+            _ => trans.target_nth(),
+        }
+    }
+
+    // #[inline(always)]
+    fn render(&self, nths: &[Nth], sub_atn: bool) -> String {
+        let mut dot = String::from("digraph ATN {\n  rankdir=LR;\n");
+        for nth in nths {
+            let state = &self.atn.states[*nth];
+            let shape = match state {
+                ATNState::RuleStop(_) => "doublecircle",
+                _ => "circle",
+            };
+            let _ = writeln!(dot, "  s{} [shape={} label=\"{}\"];", nth, shape, escape(&self.state_label(state)));
+        }
+        for nth in nths {
+            let state = &self.atn.states[*nth];
+            if sub_atn && matches!(state, ATNState::RuleStop(_)) {
+                continue;
+            }
+            for trans in state.transitions() {
+                let style = if trans.is_epsilon() { " style=dashed" } else { "" };
+                let _ = writeln!(dot, "  s{} -> s{} [label=\"{}\"{}];",
+                                 nth, Self::edge_target(trans, sub_atn), escape(&self.transition_label(trans)), style);
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    // #[inline(always)]
+    fn state_label(&self, state: &ATNState) -> String {
+        let mut label = format!("s{}\n{}", state.state_nth(), state.type_name());
+        // token start state of lexer belongs to no rule.
+        if state.rule_index() != usize::MAX {
+            let _ = write!(label, "\n{}", self.rule_name(state.rule_index()));
+        }
+        if let Some(ds) = state.to_decision_state() {
+            if ds.decision >= 0 {
+                let _ = write!(label, "\nd={}", ds.decision);
+            }
+        }
+        label
+    }
+
+    // #[inline(always)]
+    fn transition_label(&self, trans: &Transition) -> String {
+        match trans {
+            Transition::Epsilon(_) => "ε".to_string(),
+            Transition::Rule(r) => self.rule_name(r.rule_idx as usize),
+            Transition::Atom(a) => self.token_name(a.label),
+            Transition::Range(r) => format!("{}..{}", self.token_name(r.start), self.token_name(r.stop)),
+            Transition::Set(s) => self.set_label(&s.interval_set),
+            Transition::NotSet(ns) => format!("~{}", self.set_label(&ns.interval_set)),
+            _ => trans.to_string(),
+        }
+    }
+
+    // #[inline(always)]
+    fn set_label(&self, set: &IntervalSet) -> String {
+        let elements: Vec<String> = set.intervals().iter()
+            .map(|i| if i.start == i.stop {
+                self.token_name(i.start)
+            } else {
+                format!("{}..{}", self.token_name(i.start), self.token_name(i.stop))
+            })
+            .collect();
+        format!("{{{}}}", elements.join(", "))
+    }
+
+    // #[inline(always)]
+    fn rule_name(&self, rule_idx: usize) -> String {
+        match self.recog.and_then(|r| r.rule_names().get(rule_idx)) {
+            Some(name) => name.to_string(),
+            None => format!("rule_{}", rule_idx),
+        }
+    }
+
+    /// characters for lexer, literal or symbolic names for parser.
+    // #[inline(always)]
+    fn token_name(&self, ttype: isize) -> String {
+        if ttype == TOKEN_EOF {
+            return "EOF".to_string();
+        }
+        if self.atn.grammar_type == ATNType::Lexer {
+            return match char::from_u32(ttype as u32) {
+                Some(c) => format!("'{}'", c.escape_default()),
+                None => ttype.to_string(),
+            };
+        }
+        match self.recog {
+            Some(recog) => recog.vocabulary().display_name(ttype).into_owned(),
+            None => ttype.to_string(),
+        }
+    }
+}
+
+/// escape label for a double quoted DOT string, new lines are kept as `\n` of DOT.
+// #[inline(always)]
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
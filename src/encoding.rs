@@ -0,0 +1,159 @@
+use std::char::REPLACEMENT_CHARACTER;
+use std::fmt::{Display, Formatter};
+use crate::errors::DecodeError;
+
+/// [Encoding] is the encoding of byte input, see [decode].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+    /// each byte is a code point of U+0000..=U+00FF.
+    Latin1,
+}
+
+impl Encoding {
+    /// detect the BOM at the start of `bytes`, returns the encoding and the length of the BOM.
+    pub fn detect_bom(bytes: &[u8]) -> Option<(Encoding, usize)> {
+        // the BOM of UTF-32LE starts with the one of UTF-16LE, so it is checked first.
+        match bytes {
+            [0xFF, 0xFE, 0x00, 0x00, ..] => Some((Encoding::Utf32Le, 4)),
+            [0x00, 0x00, 0xFE, 0xFF, ..] => Some((Encoding::Utf32Be, 4)),
+            [0xEF, 0xBB, 0xBF, ..] => Some((Encoding::Utf8, 3)),
+            [0xFF, 0xFE, ..] => Some((Encoding::Utf16Le, 2)),
+            [0xFE, 0xFF, ..] => Some((Encoding::Utf16Be, 2)),
+            _ => None,
+        }
+    }
+
+    // #[inline(always)]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Utf32Le => "UTF-32LE",
+            Encoding::Utf32Be => "UTF-32BE",
+            Encoding::Latin1 => "ISO-8859-1",
+        }
+    }
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// [DecodingPolicy] defines how an invalid sequence of byte input is decoded, see [decode].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DecodingPolicy {
+    /// decode it as U+FFFD.
+    #[default]
+    Replace,
+    /// fail the decoding with [DecodeError].
+    Error,
+}
+
+/// [decode] decodes `bytes` in `encoding`, or in the encoding of its BOM if `encoding` is [None],
+/// UTF-8 is assumed if there is no BOM either. The BOM is dropped if it is of the encoding used.
+/// Invalid sequences, including an incomplete one at the end, are handled by `policy`.
+pub fn decode(bytes: &[u8], encoding: Option<Encoding>, policy: DecodingPolicy) -> Result<String, DecodeError> {
+    let bom = Encoding::detect_bom(bytes);
+    let encoding = encoding.or(bom.map(|(e, _)| e)).unwrap_or(Encoding::Utf8);
+    let start = match bom {
+        Some((e, len)) if e == encoding => len,
+        _ => 0,
+    };
+    let mut decoder = Decoder { text: String::with_capacity(bytes.len()), encoding, policy };
+    match encoding {
+        Encoding::Utf8 => decoder.utf8(bytes, start)?,
+        Encoding::Utf16Le => decoder.utf16(bytes, start, u16::from_le_bytes)?,
+        Encoding::Utf16Be => decoder.utf16(bytes, start, u16::from_be_bytes)?,
+        Encoding::Utf32Le => decoder.utf32(bytes, start, u32::from_le_bytes)?,
+        Encoding::Utf32Be => decoder.utf32(bytes, start, u32::from_be_bytes)?,
+        Encoding::Latin1 => decoder.text.extend(bytes[start..].iter().map(|&b| b as char)),
+    }
+    Ok(decoder.text)
+}
+
+struct Decoder {
+    text: String,
+    encoding: Encoding,
+    policy: DecodingPolicy,
+}
+
+impl Decoder {
+    /// handle the invalid sequence at byte `index`.
+    // #[inline]
+    fn invalid(&mut self, index: usize) -> Result<(), DecodeError> {
+        match self.policy {
+            DecodingPolicy::Replace => {
+                self.text.push(REPLACEMENT_CHARACTER);
+                Ok(())
+            }
+            DecodingPolicy::Error => Err(DecodeError { encoding: self.encoding, index }),
+        }
+    }
+
+    fn utf8(&mut self, bytes: &[u8], mut index: usize) -> Result<(), DecodeError> {
+        while index < bytes.len() {
+            match std::str::from_utf8(&bytes[index..]) {
+                Ok(s) => {
+                    self.text.push_str(s);
+                    index = bytes.len();
+                }
+                Err(e) => {
+                    let valid = &bytes[index..index + e.valid_up_to()];
+                    // the bytes are validated by `from_utf8`.
+                    self.text.push_str(unsafe { std::str::from_utf8_unchecked(valid) });
+                    index += e.valid_up_to();
+                    self.invalid(index)?;
+                    index += e.error_len().unwrap_or(bytes.len() - index);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn utf16(&mut self, bytes: &[u8], start: usize, unit: fn([u8; 2]) -> u16) -> Result<(), DecodeError> {
+        let chunks = bytes[start..].chunks_exact(2);
+        let rest = chunks.remainder().len();
+        let mut index = start;
+        for decoded in char::decode_utf16(chunks.map(|c| unit([c[0], c[1]]))) {
+            match decoded {
+                Ok(c) => {
+                    self.text.push(c);
+                    index += c.len_utf16() * 2;
+                }
+                Err(_) => {
+                    self.invalid(index)?;
+                    index += 2;
+                }
+            }
+        }
+        if rest > 0 {
+            self.invalid(index)?;
+        }
+        Ok(())
+    }
+
+    fn utf32(&mut self, bytes: &[u8], start: usize, unit: fn([u8; 4]) -> u32) -> Result<(), DecodeError> {
+        let chunks = bytes[start..].chunks_exact(4);
+        let rest = chunks.remainder().len();
+        let mut index = start;
+        for c in chunks {
+            match char::from_u32(unit([c[0], c[1], c[2], c[3]])) {
+                Some(c) => self.text.push(c),
+                None => self.invalid(index)?,
+            }
+            index += 4;
+        }
+        if rest > 0 {
+            self.invalid(index)?;
+        }
+        Ok(())
+    }
+}
@@ -11,3 +11,52 @@ pub trait ErrorListener {
         _err: Option<&ANTLRError>,
     ) {}
 }
+
+/// [ProxyErrorListener] dispatches errors to all of its delegates in the order they are added,
+/// a recognizer notifies its listeners through it.
+#[derive(Default)]
+pub struct ProxyErrorListener {
+    delegates: Vec<Box<dyn ErrorListener>>,
+}
+
+impl ProxyErrorListener {
+    // #[inline(always)]
+    pub fn new() -> Self {
+        Self { delegates: vec![] }
+    }
+
+    // #[inline(always)]
+    pub fn add(&mut self, l: Box<dyn ErrorListener>) {
+        self.delegates.push(l)
+    }
+
+    // #[inline(always)]
+    pub fn clear(&mut self) {
+        self.delegates.clear()
+    }
+
+    // #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.delegates.len()
+    }
+
+    // #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.delegates.is_empty()
+    }
+}
+
+impl ErrorListener for ProxyErrorListener {
+    fn syntax_error(
+        &mut self,
+        recognizer: &dyn Recognizer,
+        line: isize,
+        column: isize,
+        msg: &str,
+        err: Option<&ANTLRError>,
+    ) {
+        for l in &mut self.delegates {
+            l.syntax_error(recognizer, line, column, msg, err)
+        }
+    }
+}
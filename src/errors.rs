@@ -1,5 +1,8 @@
 use std::error::Error;
+use std::fmt::{Display, Formatter};
 use std::rc::Rc;
+use crate::atn_type::ATNType;
+use crate::encoding::Encoding;
 
 #[derive(Clone, Debug)]
 pub enum ANTLRError {
@@ -19,4 +22,268 @@ pub struct NoViableError {}
 pub struct InputMismatchError {}
 
 #[derive(Clone, Debug)]
-pub struct PredicateError {}
\ No newline at end of file
+pub struct PredicateError {}
+
+/// [ATNSection] names the part of a serialized ATN that
+/// [crate::atn_deserializer::ATNDeserializer] was reading when it failed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ATNSection {
+    /// serialized version, grammar type and max token type.
+    Header,
+    States,
+    Rules,
+    Modes,
+    Sets,
+    Edges,
+    Decisions,
+    LexerActions,
+}
+
+impl Display for ATNSection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ATNSection::Header => "header",
+            ATNSection::States => "states",
+            ATNSection::Rules => "rules",
+            ATNSection::Modes => "modes",
+            ATNSection::Sets => "sets",
+            ATNSection::Edges => "edges",
+            ATNSection::Decisions => "decisions",
+            ATNSection::LexerActions => "lexer actions",
+        })
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ATNDeserializeErrorKind {
+    /// the data ends before the section is complete.
+    UnexpectedEnd,
+    UnsupportedVersion,
+    InvalidGrammarType,
+    /// a count of elements is negative.
+    InvalidCount,
+    InvalidStateType,
+    /// refer to an ATN state that does not exist.
+    InvalidStateNth,
+    /// refer to an ATN state whose type is not allowed there.
+    UnexpectedStateType,
+    InvalidRuleIndex,
+    /// a transition type that does not exist, or a precedence predicate in a lexer ATN.
+    InvalidTransitionType,
+    InvalidSetIndex,
+    InvalidLexerActionType,
+    /// an action transition of a lexer ATN refers to a lexer action that does not exist.
+    InvalidLexerActionIndex,
+    /// the ATN has been read completely, but the state at `state_nth` is inconsistent.
+    Verification { state_nth: usize, reason: &'static str },
+}
+
+impl Display for ATNDeserializeErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ATNDeserializeErrorKind::UnexpectedEnd => f.write_str("unexpected end of data"),
+            ATNDeserializeErrorKind::UnsupportedVersion => f.write_str("unsupported serialized version"),
+            ATNDeserializeErrorKind::InvalidGrammarType => f.write_str("invalid grammar type"),
+            ATNDeserializeErrorKind::InvalidCount => f.write_str("invalid count"),
+            ATNDeserializeErrorKind::InvalidStateType => f.write_str("invalid state type"),
+            ATNDeserializeErrorKind::InvalidStateNth => f.write_str("invalid state number"),
+            ATNDeserializeErrorKind::UnexpectedStateType => f.write_str("unexpected state type"),
+            ATNDeserializeErrorKind::InvalidRuleIndex => f.write_str("invalid rule index"),
+            ATNDeserializeErrorKind::InvalidTransitionType => f.write_str("invalid transition type"),
+            ATNDeserializeErrorKind::InvalidSetIndex => f.write_str("invalid set index"),
+            ATNDeserializeErrorKind::InvalidLexerActionType => f.write_str("invalid lexer action type"),
+            ATNDeserializeErrorKind::InvalidLexerActionIndex => f.write_str("invalid lexer action index"),
+            ATNDeserializeErrorKind::Verification { state_nth, reason } => {
+                f.write_fmt(format_args!("verification of state {} failed: {}", state_nth, reason))
+            }
+        }
+    }
+}
+
+/// [ATNDeserializeError] is returned by [crate::atn_deserializer::ATNDeserializer::try_deserialize]
+/// when the serialized ATN is truncated or inconsistent.
+/// `offset` is the position in the `&[i32]` where the problem was found and `value` is the
+/// element at that position, it is [None] when the data ends before `offset`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ATNDeserializeError {
+    pub section: ATNSection,
+    pub offset: usize,
+    pub value: Option<i32>,
+    pub kind: ATNDeserializeErrorKind,
+}
+
+impl Display for ATNDeserializeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("could not deserialize ATN: {} in {} section at offset {}", self.kind, self.section, self.offset))?;
+        if let Some(v) = self.value {
+            f.write_fmt(format_args!(" (value {})", v))?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ATNDeserializeError {}
+
+/// [InterpFileError] is returned by [crate::interp_file::InterpFile] when a `.interp` file
+/// can not be read or does not follow the layout written by the ANTLR tool,
+/// or by an interpreter created with an ATN of the other grammar type.
+/// `line` is 1-based.
+#[derive(Debug)]
+pub enum InterpFileError {
+    Io(std::io::Error),
+    /// a required section such as `rule names:` is not found.
+    MissingSection(&'static str),
+    /// a line that is neither a section header nor belongs to a section.
+    UnexpectedLine { line: usize, text: String },
+    /// the `atn:` section is not a list of integers.
+    InvalidATN { line: usize, text: String },
+    ATNDeserialize(ATNDeserializeError),
+    /// the ATN is not of the grammar type the interpreter requires.
+    UnexpectedGrammarType { expected: ATNType, found: ATNType },
+}
+
+impl Display for InterpFileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InterpFileError::Io(e) => f.write_fmt(format_args!("could not read interp file: {}", e)),
+            InterpFileError::MissingSection(s) => f.write_fmt(format_args!("missing section '{}' in interp file", s)),
+            InterpFileError::UnexpectedLine { line, text } => f.write_fmt(format_args!("unexpected line {} in interp file: '{}'", line, text)),
+            InterpFileError::InvalidATN { line, text } => f.write_fmt(format_args!("invalid atn at line {} in interp file: '{}'", line, text)),
+            InterpFileError::ATNDeserialize(e) => e.fmt(f),
+            InterpFileError::UnexpectedGrammarType { expected, found } => {
+                let expected = match expected {
+                    ATNType::Lexer => "lexer",
+                    ATNType::Parser => "parser",
+                };
+                f.write_fmt(format_args!("the ATN must be a {} ATN, but got {:?}", expected, found))
+            }
+        }
+    }
+}
+
+impl Error for InterpFileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            InterpFileError::Io(e) => Some(e),
+            InterpFileError::ATNDeserialize(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for InterpFileError {
+    fn from(e: std::io::Error) -> Self {
+        InterpFileError::Io(e)
+    }
+}
+
+/// [TokensFileError] is returned by [crate::vocabulary::Vocabulary] when a `.tokens` file
+/// can not be read or has a line which is not `name=type`. `line` is 1-based.
+#[derive(Debug)]
+pub enum TokensFileError {
+    Io(std::io::Error),
+    InvalidLine { line: usize, text: String },
+}
+
+impl Display for TokensFileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokensFileError::Io(e) => f.write_fmt(format_args!("could not read tokens file: {}", e)),
+            TokensFileError::InvalidLine { line, text } => f.write_fmt(format_args!("invalid line {} in tokens file: '{}'", line, text)),
+        }
+    }
+}
+
+impl Error for TokensFileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TokensFileError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for TokensFileError {
+    fn from(e: std::io::Error) -> Self {
+        TokensFileError::Io(e)
+    }
+}
+
+impl From<ATNDeserializeError> for InterpFileError {
+    fn from(e: ATNDeserializeError) -> Self {
+        InterpFileError::ATNDeserialize(e)
+    }
+}
+
+/// [Utf16Error] is returned when UTF-16 input has an unpaired surrogate and
+/// [crate::code_point::SurrogatePolicy::Error] is used, `index` is the position of the surrogate in the code units.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Utf16Error {
+    pub index: usize,
+    pub unit: u16,
+}
+
+impl Display for Utf16Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("unpaired surrogate 0x{:X} at index {} of UTF-16 input", self.unit, self.index))
+    }
+}
+
+impl Error for Utf16Error {}
+
+/// [DecodeError] is returned when byte input has an invalid sequence and
+/// [crate::encoding::DecodingPolicy::Error] is used, `index` is the byte offset of the sequence.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DecodeError {
+    pub encoding: Encoding,
+    pub index: usize,
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("invalid {} sequence at byte {} of input", self.encoding, self.index))
+    }
+}
+
+impl Error for DecodeError {}
+
+/// [UnbufferedTextError] is returned when the text of `start..=end` is asked from an unbuffered stream,
+/// but the chars are not in its buffer of `buffer_start..buffer_end` anymore, or are not read yet.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnbufferedTextError {
+    pub start: isize,
+    pub end: isize,
+    pub buffer_start: isize,
+    pub buffer_end: isize,
+}
+
+impl Display for UnbufferedTextError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "text of {}..={} is out of the buffer {}..{} of unbuffered stream, mark the stream to keep the text",
+            self.start, self.end, self.buffer_start, self.buffer_end,
+        ))
+    }
+}
+
+impl Error for UnbufferedTextError {}
+
+/// [UnbufferedTokenError] is returned when the token at `index` is asked from an unbuffered token stream,
+/// but it is not in its buffer of `buffer_start..buffer_end` anymore.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnbufferedTokenError {
+    pub index: isize,
+    pub buffer_start: isize,
+    pub buffer_end: isize,
+}
+
+impl Display for UnbufferedTokenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "token {} is out of the buffer {}..{} of unbuffered token stream, mark the stream to keep the tokens",
+            self.index, self.buffer_start, self.buffer_end,
+        ))
+    }
+}
+
+impl Error for UnbufferedTokenError {}
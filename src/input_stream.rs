@@ -1,24 +1,49 @@
 use std::borrow::Cow;
 use std::cmp::min;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
 use crate::char_stream::CharStream;
-use crate::code_point::CodePoints;
+#[cfg(feature = "mmap")]
+use crate::code_point::MappedUtf8;
+use crate::code_point::{CodePoints, SurrogatePolicy, Utf16, Utf8};
+use crate::encoding::{decode, DecodingPolicy, Encoding};
+use crate::errors::{DecodeError, Utf16Error};
 use crate::int_stream::{EOF, IntStream};
 
 const INPUT_STREAM_SOURCE_NAME: &'static str = "source from string";
 
 /// [InputStream] is the implementation for [CharStream], only visual in lib.
 /// we do not expect to export this struct to pub
+#[derive(Debug)]
 pub struct InputStream<T> {
     index: isize,
     size: isize,
     data: T,
+    source_name: Option<String>,
 }
 
-pub type StringStream = InputStream<String>;
+/// [StringStream] indexes the chars of a string once, so a char is accessed in O(1), see [Utf8].
+pub type StringStream = InputStream<Utf8>;
+/// [SharedStringStream] is a [StringStream] of a shared text, tokens could be spans of it,
+/// see [crate::token_factory::SpanTokenFactory].
+pub type SharedStringStream = InputStream<Utf8<Arc<str>>>;
+/// [ByteStream] takes each byte as a symbol, which is right for Latin-1 only,
+/// use [StringStream::from_bytes] to decode byte input of other encodings.
 pub type ByteStream = InputStream<Vec<u8>>;
 pub type CodePoint8BitStream = InputStream<Vec<u8>>;
+/// [CodePoint16BitStream] takes each `u16` as a symbol, which is right for UCS-2 only,
+/// use [Utf16Stream] for UTF-16 input.
 pub type CodePoint16BitStream = InputStream<Vec<u16>>;
 pub type CodePoint32BitStream = InputStream<Vec<u32>>;
+/// [Utf16Stream] decodes UTF-16 input, see [Utf16].
+pub type Utf16Stream = InputStream<Utf16>;
+/// [FileStream] is the text of a file, its source name is the path, see [FileStream::open].
+pub type FileStream = StringStream;
+/// [MmapStream] lexes a memory-mapped UTF-8 file without copying it, see [MmapStream::open].
+#[cfg(feature = "mmap")]
+pub type MmapStream = InputStream<Utf8<MappedUtf8>>;
 
 impl<T: CodePoints> InputStream<T> {
     /// returns a new [InputStream] and owned/clone the data from the `input`
@@ -28,19 +53,98 @@ impl<T: CodePoints> InputStream<T> {
             index: 0,
             size: input.size() as isize,
             data: input,
+            source_name: None,
         }
     }
+
+    /// set the name reported by [IntStream::source_name], e.g. the path of a file.
+    // #[inline(always)]
+    pub fn set_source_name(&mut self, source_name: impl Into<String>) {
+        self.source_name = Some(source_name.into())
+    }
+
+    /// [InputStream::set_source_name] of the builder style.
+    // #[inline(always)]
+    pub fn with_source_name(mut self, source_name: impl Into<String>) -> Self {
+        self.set_source_name(source_name);
+        self
+    }
 }
 
-impl<T: ToOwned + ?Sized> From<&T> for InputStream<T::Owned> where T::Owned: CodePoints {
+impl StringStream {
+    /// decode `bytes` into a stream, see [decode] for how the encoding is chosen and `policy` is applied.
+    /// Symbols are code points whatever the encoding is, so are the indexes of the stream.
+    // #[inline]
+    pub fn from_bytes(bytes: &[u8], encoding: Option<Encoding>, policy: DecodingPolicy) -> Result<Self, DecodeError> {
+        Ok(Self::new(Utf8::new(decode(bytes, encoding, policy)?)))
+    }
+}
+
+impl FileStream {
+    /// read the file at `path` into a stream named by the path, it is decoded in the encoding of its BOM,
+    /// or UTF-8 without BOM. Returns [io::ErrorKind::InvalidData] if the file has an invalid sequence.
     // #[inline(always)]
-    fn from(input: &T) -> Self {
-        let owned = input.to_owned();
-        Self {
-            index: 0,
-            size: owned.size() as isize,
-            data: owned,
-        }
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::open_with(path, None, DecodingPolicy::Error)
+    }
+
+    /// read the file at `path` into a stream named by the path, see [StringStream::from_bytes].
+    pub fn open_with(path: impl AsRef<Path>, encoding: Option<Encoding>, policy: DecodingPolicy) -> io::Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)?;
+        let stream = Self::from_bytes(&bytes, encoding, policy)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(stream.with_source_name(path.to_string_lossy()))
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl MmapStream {
+    /// map the UTF-8 file at `path` into a stream named by the path, the file is not read into memory,
+    /// which suits large files. Returns [io::ErrorKind::InvalidData] if the file is not UTF-8.
+    ///
+    /// # Safety
+    /// the file must not be modified or truncated while the stream is alive, see [MappedUtf8::map].
+    pub unsafe fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let file = fs::File::open(path)?;
+        Ok(Self::new(Utf8::new(MappedUtf8::map(&file)?)).with_source_name(path.to_string_lossy()))
+    }
+}
+
+impl Utf16Stream {
+    /// decode UTF-16 `units` into a stream, unpaired surrogates are handled by `policy`.
+    // #[inline]
+    pub fn from_utf16(units: &[u16], policy: SurrogatePolicy) -> Result<Self, Utf16Error> {
+        Ok(Self::new(Utf16::decode(units, policy)?))
+    }
+}
+
+impl From<String> for StringStream {
+    // #[inline(always)]
+    fn from(input: String) -> Self {
+        Self::new(Utf8::new(input))
+    }
+}
+
+impl From<Arc<str>> for SharedStringStream {
+    // #[inline(always)]
+    fn from(input: Arc<str>) -> Self {
+        Self::new(Utf8::new(input))
+    }
+}
+
+impl From<&str> for StringStream {
+    // #[inline(always)]
+    fn from(input: &str) -> Self {
+        Self::new(Utf8::new(input.to_owned()))
+    }
+}
+
+impl<T: Clone> From<&[T]> for InputStream<Vec<T>> where Vec<T>: CodePoints {
+    // #[inline(always)]
+    fn from(input: &[T]) -> Self {
+        Self::new(input.to_vec())
     }
 }
 
@@ -112,7 +216,7 @@ impl<T: CodePoints> IntStream for InputStream<T> {
 
     // #[inline]
     fn source_name(&self) -> Cow<'_, str> {
-        Cow::Borrowed(INPUT_STREAM_SOURCE_NAME)
+        Cow::Borrowed(self.source_name.as_deref().unwrap_or(INPUT_STREAM_SOURCE_NAME))
     }
 }
 
@@ -121,4 +225,14 @@ impl<T: CodePoints> CharStream for InputStream<T> {
     fn text(&self, start: usize, end: usize) -> Cow<'_, str> {
         self.data.text_range(start, end)
     }
+
+    // #[inline]
+    fn byte_offset(&self, index: isize) -> Option<usize> {
+        usize::try_from(index).ok().and_then(|i| self.data.byte_offset(i))
+    }
+
+    // #[inline(always)]
+    fn utf8_text(&self) -> Option<&str> {
+        self.data.as_utf8()
+    }
 }
\ No newline at end of file
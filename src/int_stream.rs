@@ -20,6 +20,20 @@ pub trait IntStream {
     /// Return `EOF` if `i` points to position at or beyond the end of the stream
     fn la(&mut self, i: isize) -> isize;
 
+    /// A mark provides a guarantee that [IntStream::seek] operations will be valid over a "marked range" extending
+    /// from the index where mark() was called to the current index. This allows the use of streaming input sources
+    /// by specifying the minimum buffering requirements to support arbitrary lookahead during prediction.
+    /// The returned mark is an opaque handle passed to [IntStream::release] when the guarantees provided by
+    /// the marked range are no longer necessary. Marks must be released in the reverse order they are made.
+    /// Streams holding all input in memory need no marks, they return -1 and release nothing.
+    fn mark(&mut self) -> isize {
+        -1
+    }
+
+    /// release the `marker` returned by the last [IntStream::mark] which is not released yet,
+    /// a streaming input could discard the symbols not pinned by any marks.
+    fn release(&mut self, _marker: isize) {}
+
     fn index(&self) -> isize;
 
     fn seek(&mut self, index: isize);
@@ -0,0 +1,142 @@
+use std::borrow::Cow;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::atn::ATN;
+use crate::atn_deserialize_option::ATNDeserializeOption;
+use crate::atn_deserializer::ATNDeserializer;
+use crate::char_stream::CharStream;
+use crate::errors::InterpFileError;
+use crate::lexer_interpreter::LexerInterpreter;
+use crate::recognizer::{BaseRecognizer, Names};
+use crate::vocabulary::Vocabulary;
+
+const LITERAL_NAMES_SECTION: &str = "token literal names:";
+const SYMBOLIC_NAMES_SECTION: &str = "token symbolic names:";
+const RULE_NAMES_SECTION: &str = "rule names:";
+const CHANNEL_NAMES_SECTION: &str = "channel names:";
+const MODE_NAMES_SECTION: &str = "mode names:";
+const ATN_SECTION: &str = "atn:";
+
+/// [InterpFile] holds what the ANTLR tool writes into a `.interp` file: the name tables and the [ATN]
+/// of a lexer or parser, so a grammar can be loaded at runtime without generating code.
+///
+/// The name tables are owned by the file and shared with the recognizers created from it,
+/// they are freed when the file and all of its recognizers are dropped.
+#[derive(Debug)]
+pub struct InterpFile {
+    pub literal_names: Names,
+    pub symbolic_names: Names,
+    pub rule_names: Names,
+    /// empty for a parser grammar.
+    pub channel_names: Names,
+    /// empty for a parser grammar.
+    pub mode_names: Names,
+    pub atn: Arc<ATN>,
+}
+
+impl InterpFile {
+    /// [InterpFile::load] reads and parses the `.interp` file at `path`.
+    // #[inline]
+    pub fn load(path: impl AsRef<Path>, options: Option<ATNDeserializeOption>) -> Result<Self, InterpFileError> {
+        let content = fs::read_to_string(path)?;
+        Self::parse(&content, options)
+    }
+
+    /// [InterpFile::parse] parses the content of a `.interp` file,
+    /// `null` in name tables is turned into an empty name like the generated code does.
+    pub fn parse(content: &str, options: Option<ATNDeserializeOption>) -> Result<Self, InterpFileError> {
+        let mut literal_names = None;
+        let mut symbolic_names = None;
+        let mut rule_names = None;
+        let mut channel_names = None;
+        let mut mode_names = None;
+        let mut serialized_atn = None;
+
+        let mut lines = content.lines().enumerate().map(|(i, l)| (i + 1, l.trim_end()));
+        while let Some((line, text)) = lines.next() {
+            let names = match text {
+                "" => continue,
+                LITERAL_NAMES_SECTION => &mut literal_names,
+                SYMBOLIC_NAMES_SECTION => &mut symbolic_names,
+                RULE_NAMES_SECTION => &mut rule_names,
+                CHANNEL_NAMES_SECTION => &mut channel_names,
+                MODE_NAMES_SECTION => &mut mode_names,
+                ATN_SECTION => {
+                    let (line, text) = lines.next().unwrap_or((line + 1, ""));
+                    serialized_atn = Some(Self::parse_atn(line, text)?);
+                    continue;
+                }
+                _ => return Err(InterpFileError::UnexpectedLine { line, text: text.to_string() }),
+            };
+            // names are listed one per line until a blank line.
+            *names = Some(lines.by_ref()
+                .map(|(_, l)| l)
+                .take_while(|l| !l.is_empty())
+                .map(|l| if l == "null" { Cow::Borrowed("") } else { Cow::Owned(l.to_string()) })
+                .collect::<Names>());
+        }
+
+        let serialized_atn = serialized_atn.ok_or(InterpFileError::MissingSection(ATN_SECTION))?;
+        let atn = ATNDeserializer::new(options).try_deserialize(&serialized_atn)?;
+        Ok(Self {
+            literal_names: literal_names.ok_or(InterpFileError::MissingSection(LITERAL_NAMES_SECTION))?,
+            symbolic_names: symbolic_names.ok_or(InterpFileError::MissingSection(SYMBOLIC_NAMES_SECTION))?,
+            rule_names: rule_names.ok_or(InterpFileError::MissingSection(RULE_NAMES_SECTION))?,
+            channel_names: channel_names.unwrap_or_default(),
+            mode_names: mode_names.unwrap_or_default(),
+            atn: Arc::new(atn),
+        })
+    }
+
+    /// the atn is written in one line: `[4, 1, 88, ...]`
+    // #[inline(always)]
+    fn parse_atn(line: usize, text: &str) -> Result<Vec<i32>, InterpFileError> {
+        let invalid = || InterpFileError::InvalidATN { line, text: text.to_string() };
+        let list = text.trim().strip_prefix('[').and_then(|t| t.strip_suffix(']')).ok_or_else(invalid)?;
+        list.split(',')
+            .map(|v| v.trim().parse::<i32>().map_err(|_| invalid()))
+            .collect()
+    }
+
+    /// [InterpFile::new_recognizer] creates a [BaseRecognizer] sharing the name tables of this file.
+    // #[inline]
+    pub fn new_recognizer(&self, grammar_file_name: &'static str) -> BaseRecognizer {
+        BaseRecognizer::with_names(
+            Arc::clone(&self.rule_names),
+            Arc::clone(&self.literal_names),
+            Arc::clone(&self.symbolic_names),
+            grammar_file_name,
+        )
+    }
+
+    /// [InterpFile::vocabulary] creates a [Vocabulary] of the token names of this file.
+    // #[inline(always)]
+    pub fn vocabulary(&self) -> Vocabulary {
+        Vocabulary::with_names(&self.literal_names, &self.symbolic_names)
+    }
+
+    /// [InterpFile::new_lexer_interpreter] is [InterpFile::try_new_lexer_interpreter] but panics
+    /// if this is not the file of a lexer grammar.
+    // #[inline]
+    pub fn new_lexer_interpreter<CS: CharStream>(&self, grammar_file_name: &'static str, input: CS) -> LexerInterpreter<CS> {
+        self.try_new_lexer_interpreter(grammar_file_name, input).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// [InterpFile::try_new_lexer_interpreter] creates a [LexerInterpreter] lexing `input` with the ATN of this file,
+    /// see [LexerInterpreter::try_new].
+    // #[inline]
+    pub fn try_new_lexer_interpreter<CS: CharStream>(&self, grammar_file_name: &'static str, input: CS) -> Result<LexerInterpreter<CS>, InterpFileError> {
+        LexerInterpreter::try_new(
+            grammar_file_name,
+            Arc::clone(&self.atn),
+            Arc::clone(&self.rule_names),
+            Arc::clone(&self.literal_names),
+            Arc::clone(&self.symbolic_names),
+            Arc::clone(&self.channel_names),
+            Arc::clone(&self.mode_names),
+            input,
+        )
+    }
+}
@@ -1,4 +1,5 @@
 use std::cmp::{max, min};
+use std::fmt::{Display, Formatter};
 
 /// [Interval] represents interval equivalent to start..=stop
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -9,11 +10,12 @@ pub struct Interval {
 
 impl Interval {
     // #[inline(always)]
-    fn new(start: isize, stop: isize) -> Self {
+    pub fn new(start: isize, stop: isize) -> Self {
         Self { start, stop }
     }
 }
 
+/// [IntervalSet] equals to another one if they contain the same intervals, no matter whether it is read only.
 #[derive(Debug, Clone)]
 pub struct IntervalSet {
     intervals: Vec<Interval>,
@@ -26,6 +28,16 @@ impl IntervalSet {
         Self { intervals: vec![], read_only: false }
     }
 
+    // #[inline(always)]
+    pub fn intervals(&self) -> &[Interval] {
+        &self.intervals
+    }
+
+    // #[inline]
+    pub fn contains(&self, v: isize) -> bool {
+        self.intervals.iter().any(|i| i.start <= v && v <= i.stop)
+    }
+
     // #[inline(always)]
     pub fn add_one(&mut self, v: isize) {
         self.add_range(v, v)
@@ -93,4 +105,35 @@ impl IntervalSet {
         // just add it.
         self.intervals.push(v);
     }
-}
\ No newline at end of file
+}
+
+impl PartialEq for IntervalSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.intervals == other.intervals
+    }
+}
+
+impl Eq for IntervalSet {}
+
+/// [IntervalSet] is displayed like ANTLR reference runtime does: `{1, 3..5, <EOF>}`,
+/// braces are omitted if there is only one element.
+impl Display for IntervalSet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.intervals.is_empty() {
+            return f.write_str("{}");
+        }
+        let single = self.intervals.len() == 1 && self.intervals[0].start == self.intervals[0].stop;
+        let elements: Vec<String> = self.intervals.iter()
+            .map(|i| match (i.start, i.stop) {
+                (-1, -1) => "<EOF>".to_string(),
+                (a, b) if a == b => a.to_string(),
+                (a, b) => format!("{}..{}", a, b),
+            })
+            .collect();
+        if single {
+            f.write_str(&elements[0])
+        } else {
+            f.write_fmt(format_args!("{{{}}}", elements.join(", ")))
+        }
+    }
+}
@@ -1,11 +1,15 @@
+use std::borrow::Cow;
 use std::cell::RefCell;
 use std::rc::Rc;
 use crate::error_listener::ErrorListener;
 use crate::char_stream::CharStream;
 use crate::errors::ANTLRError;
+use crate::int_stream::EOF;
 use crate::lexer_atn_simulator::LexerATNSimulator;
+use crate::lexer_snapshot::LexerSnapshot;
 use crate::recognizer::Recognizer;
 use crate::rule_context::RuleContext;
+use crate::vocabulary::Vocabulary;
 use crate::token::{TOKEN_DEFAULT_CHANNEL, TOKEN_EOF, TOKEN_INVALID_TYPE};
 use crate::token_factory::TokenFactory;
 use crate::token_source::TokenSource;
@@ -14,12 +18,159 @@ pub const LEXER_DEFAULT_MODE: isize = 0;
 pub const LEXER_MORE: isize = -2;
 pub const LEXER_SKIP: isize = -3;
 
+/// [error_display] escapes the text of an error message, so non-printable characters are visible.
+pub fn error_display(text: &str) -> String {
+    let mut display = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\n' => display.push_str("\\n"),
+            '\r' => display.push_str("\\r"),
+            '\t' => display.push_str("\\t"),
+            c if c.is_control() => display.extend(c.escape_unicode()),
+            c => display.push(c),
+        }
+    }
+    display
+}
+
+pub const LEXER_MIN_CHAR_VALUE: isize = 0x0000;
+pub const LEXER_MAX_CHAR_VALUE: isize = 0x10FFFF;
+
 pub trait Lexer: TokenSource + Recognizer {
+    /// create a token of the current type, text and channel from the recorded start to the current position,
+    /// it is returned by the current [TokenSource::next_token].
     fn emit(&mut self) -> Self::TK;
+
+    fn mode(&self) -> isize;
+
+    /// switch to `mode`, it takes effect from the next token.
+    fn set_mode(&mut self, mode: isize);
+
+    /// remember the current mode and switch to `mode`.
+    fn push_mode(&mut self, mode: isize);
+
+    /// switch back to the mode before the last [Lexer::push_mode], returns the mode switched to,
+    /// or [None] and stay in the current mode if no mode is pushed.
+    fn pop_mode(&mut self) -> Option<isize>;
+
+    /// token type of the current token, [LEXER_SKIP] and [LEXER_MORE] are also types.
+    fn token_type(&self) -> isize;
+
+    fn set_type(&mut self, ttype: isize);
+
+    fn channel(&self) -> isize;
+
+    fn set_channel(&mut self, channel: isize);
+
+    /// text of the current token, which is the text matched so far unless it is set by [Lexer::set_text].
+    fn text(&self) -> Cow<'_, str>;
+
+    /// override the text of the current token.
+    fn set_text(&mut self, text: String);
+
+    /// the char stream being lexed, lexer actions seek it to where they should be executed.
+    fn input_stream(&mut self) -> &mut dyn CharStream;
+
+    /// the state to resume lexing from the current position, it is taken between tokens.
+    fn snapshot(&self) -> LexerSnapshot;
+
+    /// resume lexing from `snapshot`, the input could be another one which has the same chars
+    /// before the index of `snapshot`.
+    fn restore(&mut self, snapshot: &LexerSnapshot);
+
+    /// take the errors recorded since the last call, in the order they occur.
+    fn take_errors(&mut self) -> Vec<ANTLRError>;
+
+    /// collect the tokens till EOF like [TokenSource::all_tokens], along with the errors recorded meanwhile.
+    // #[inline]
+    fn tokens_with_errors(&mut self) -> (Vec<Self::TK>, Vec<ANTLRError>) where Self: Sized {
+        let tokens = self.all_tokens();
+        (tokens, self.take_errors())
+    }
+
+    /// instruct the lexer to skip creating a token for current lexer rule
+    /// and look for another token.
+    fn skip(&mut self) {
+        self.set_type(LEXER_SKIP)
+    }
+
+    /// instruct the lexer to keep the text matched so far and continue matching,
+    /// the text becomes the prefix of the next token.
+    fn more(&mut self) {
+        self.set_type(LEXER_MORE)
+    }
+}
+
+/// [LexerRecognizer] is the recognizer of a lexer grammar. Like those of a generated lexer of ANTLR, its
+/// predicates and custom actions are given the lexer: a predicate reads the position, text and mode of the
+/// token being matched through a [LexerView], and an action could change the type, channel, text and mode
+/// of the token. They are forwarded to [Recognizer::sempred] and [Recognizer::action] by default.
+pub trait LexerRecognizer: Recognizer {
+    /// evaluate the predicate `pred_idx` of rule `rule_idx` where `lexer` is matching a token.
+    // #[inline(always)]
+    fn lexer_sempred(&self, _lexer: &mut LexerView<'_>, rule_idx: isize, pred_idx: isize) -> bool {
+        self.sempred(None, rule_idx, pred_idx)
+    }
+
+    /// execute the action `action_idx` of rule `rule_idx` on `lexer`, this recognizer is
+    /// [BaseLexer::recognizer_mut] of it.
+    // #[inline(always)]
+    fn lexer_action<LAS, TF, CS>(lexer: &mut BaseLexer<Self, LAS, TF, CS>, rule_idx: isize, action_idx: isize)
+        where Self: Sized, LAS: LexerATNSimulator, TF: TokenFactory, CS: CharStream {
+        lexer.recognizer.action(None, rule_idx, action_idx)
+    }
+}
+
+/// [LexerView] is the lexer seen by a predicate, the simulator is in the middle of a match so the lexer
+/// itself could not be borrowed. The position is where the predicate is in the token.
+pub struct LexerView<'a> {
+    pub(crate) input: &'a mut dyn CharStream,
+    pub(crate) start_index: isize,
+    pub(crate) line: isize,
+    pub(crate) column: isize,
+    pub(crate) mode: isize,
+}
+
+impl LexerView<'_> {
+    // #[inline(always)]
+    pub fn line(&self) -> isize {
+        self.line
+    }
+
+    // #[inline(always)]
+    pub fn char_position_in_line(&self) -> isize {
+        self.column
+    }
+
+    /// the index of the char the predicate is in front of.
+    // #[inline(always)]
+    pub fn char_index(&self) -> isize {
+        self.input.index()
+    }
+
+    // #[inline(always)]
+    pub fn mode(&self) -> isize {
+        self.mode
+    }
+
+    /// text matched from the start of the token to the predicate.
+    // #[inline]
+    pub fn text(&self) -> Cow<'_, str> {
+        if self.input.index() <= self.start_index {
+            return Cow::Borrowed("");
+        }
+        self.input.text(self.start_index as usize, (self.input.index() - 1) as usize)
+    }
+
+    /// look ahead the `i`th char from the predicate, see [crate::int_stream::IntStream::la].
+    // #[inline(always)]
+    pub fn la(&mut self, i: isize) -> isize {
+        self.input.la(i)
+    }
 }
 
 pub struct BaseLexer<R, LAS, TF, CS>
-    where R: Recognizer,
+    where R: LexerRecognizer,
           LAS: LexerATNSimulator,
           TF: TokenFactory,
           CS: CharStream
@@ -49,10 +200,16 @@ pub struct BaseLexer<R, LAS, TF, CS>
     mode_stack: Vec<isize>,
     mode: isize,
     text: Option<String>,
+    // emit a token of [TOKEN_INVALID_TYPE] for the chars starting no token, instead of dropping them.
+    emit_error_tokens: bool,
+    // errors recorded, taken by [Lexer::take_errors]
+    errors: Vec<ANTLRError>,
+    // the largest index of the chars examined by the matches so far, see [LexerSnapshot::lookahead_index]
+    lookahead_index: isize,
 }
 
 impl<R, LAS, TF, CS> BaseLexer<R, LAS, TF, CS>
-    where R: Recognizer,
+    where R: LexerRecognizer,
           LAS: LexerATNSimulator,
           TF: TokenFactory,
           CS: CharStream
@@ -74,21 +231,39 @@ impl<R, LAS, TF, CS> BaseLexer<R, LAS, TF, CS>
             mode_stack: vec![],
             mode: LEXER_DEFAULT_MODE,
             text: None,
+            emit_error_tokens: false,
+            errors: vec![],
+            lookahead_index: -1,
         }
     }
 }
 
 impl<R, LAS, TF, CS> BaseLexer<R, LAS, TF, CS>
-    where CS: CharStream, LAS: LexerATNSimulator, R: Recognizer, TF: TokenFactory {
+    where CS: CharStream, LAS: LexerATNSimulator, R: LexerRecognizer, TF: TokenFactory {
     pub fn emit_token(&mut self, tk: TF::TK) {
         self.token = Some(tk)
     }
 
+    /// create the token matched from the token start to the current char.
+    // #[inline]
+    fn create_token(&self) -> TF::TK {
+        self.factory.create(
+            &self.input,
+            self.this_type,
+            self.text.clone(),
+            self.channel,
+            self.token_start_idx,
+            self.char_index() - 1,
+            self.token_start_line,
+            self.token_start_column,
+        )
+    }
+
     pub fn emit_eof(&mut self) {
         let eof = self.factory.create(
             &self.input,
             TOKEN_EOF,
-            self.text.take(),
+            None,
             TOKEN_DEFAULT_CHANNEL,
             self.input.index(),
             self.input.index() - 1,
@@ -98,19 +273,101 @@ impl<R, LAS, TF, CS> BaseLexer<R, LAS, TF, CS>
         self.emit_token(eof)
     }
 
-    pub fn emit(&mut self) -> TF::TK {
-        todo!()
+    // #[inline(always)]
+    pub fn recognizer(&self) -> &R {
+        &self.recognizer
+    }
+
+    /// the recognizer of the grammar, custom actions reach their own state through it,
+    /// see [LexerRecognizer::lexer_action].
+    // #[inline(always)]
+    pub fn recognizer_mut(&mut self) -> &mut R {
+        &mut self.recognizer
+    }
+
+    /// the index of the char the lexer is examining.
+    // #[inline(always)]
+    pub fn char_index(&self) -> isize {
+        self.input.index()
+    }
+
+    /// reset the lexer to lex from the beginning of its input stream.
+    pub fn reset(&mut self) {
+        self.input.seek(0);
+        self.token = None;
+        self.this_type = TOKEN_INVALID_TYPE;
+        self.channel = TOKEN_DEFAULT_CHANNEL;
+        self.token_start_idx = -1;
+        self.token_start_column = -1;
+        self.token_start_line = -1;
+        self.text = None;
+        self.hit_eof = false;
+        self.mode = LEXER_DEFAULT_MODE;
+        self.mode_stack.clear();
+        self.errors.clear();
+        self.lookahead_index = -1;
+        self.interpreter.reset();
+    }
+
+    /// whether the chars starting no token are emitted as tokens of [TOKEN_INVALID_TYPE].
+    // #[inline(always)]
+    pub fn emit_error_tokens(&self) -> bool {
+        self.emit_error_tokens
+    }
+
+    /// By default, the chars starting no token are reported and dropped. Set `emit` to emit them as
+    /// a token of [TOKEN_INVALID_TYPE] instead, so every char of the input is covered by some token
+    /// unless it is skipped by the grammar.
+    // #[inline(always)]
+    pub fn set_emit_error_tokens(&mut self, emit: bool) {
+        self.emit_error_tokens = emit
+    }
+
+    /// see [LexerATNSimulator::tab_width].
+    // #[inline(always)]
+    pub fn tab_width(&self) -> isize {
+        self.interpreter.tab_width()
+    }
+
+    /// columns of tokens count a tab to the next multiple of `width`, see [LexerATNSimulator::set_tab_width].
+    // #[inline(always)]
+    pub fn set_tab_width(&mut self, width: isize) {
+        self.interpreter.set_tab_width(width)
+    }
+
+    /// report the chars from the token start to where the match failed to error listeners.
+    // #[inline]
+    fn notify_listeners(&mut self, e: &ANTLRError) {
+        // the char where the match failed is included unless it is EOF, size of a streaming input is unknown.
+        let stop = if self.input.la(1) == EOF { self.input.index() - 1 } else { self.input.index() };
+        let text = error_display(&self.input.text(self.token_start_idx as usize, stop as usize));
+        let msg = format!("token recognition error at: '{}'", text);
+        let dispatch = self.recognizer.error_listener_dispatch();
+        dispatch.borrow_mut().syntax_error(self, self.token_start_line, self.token_start_column, &msg, Some(e));
+    }
+
+    /// recover from a char that starts no token by dropping it.
+    // #[inline]
+    fn recover(&mut self, e: ANTLRError) {
+        if self.input.la(1) != EOF {
+            // skip a char and try again
+            self.interpreter.consume(&mut self.input);
+        }
+        self.errors.push(e.clone());
+        self.recognizer.set_error(e);
     }
 }
 
 impl<R, LAS, TF, CS> TokenSource for BaseLexer<R, LAS, TF, CS>
-    where CS: CharStream, LAS: LexerATNSimulator, R: Recognizer, TF: TokenFactory
+    where CS: CharStream, LAS: LexerATNSimulator, R: LexerRecognizer, TF: TokenFactory
 {
     type TK = TF::TK;
     // type CS = CS;
 
     // parse the next token
     fn next_token(&mut self) -> Self::TK {
+        // a streaming input keeps the chars of the token till the token is created.
+        let token_start_marker = self.input.mark();
         'outer: loop {
             // if stream hit the eof
             if self.hit_eof {
@@ -121,17 +378,54 @@ impl<R, LAS, TF, CS> TokenSource for BaseLexer<R, LAS, TF, CS>
             // reset all status
             self.token = None;
             self.channel = TOKEN_DEFAULT_CHANNEL;
+            // token start position: char index
+            self.token_start_idx = self.input.index();
             // token start position: column index
             self.token_start_column = self.interpreter.char_position_in_line();
             // line index
             self.token_start_line = self.line();
             self.text = None;
 
-            let idx = self.input.index();
-            'inner: loop {
-                // self.interpreter.match_()
+            loop {
+                self.this_type = TOKEN_INVALID_TYPE;
+                let start_index = self.input.index();
+                let matched = self.interpreter.match_(&mut self.input, self.mode, &self.recognizer);
+                self.lookahead_index = self.lookahead_index.max(self.interpreter.lookahead_index());
+                let ttype = match matched {
+                    Ok(ttype) => {
+                        // actions of the matched token, they may change type, channel and mode.
+                        if let Some(executor) = self.interpreter.take_lexer_action_executor() {
+                            executor.execute(self, start_index);
+                        }
+                        ttype
+                    }
+                    Err(e) => {
+                        self.notify_listeners(&e);
+                        self.recover(e);
+                        // an error token is emitted as its type is left invalid.
+                        if self.emit_error_tokens { TOKEN_INVALID_TYPE } else { LEXER_SKIP }
+                    }
+                };
+                if self.input.la(1) == EOF {
+                    self.hit_eof = true;
+                }
+                // actions of the rule may have set the type already
+                if self.this_type == TOKEN_INVALID_TYPE {
+                    self.this_type = ttype;
+                }
+                if self.this_type == LEXER_SKIP {
+                    continue 'outer;
+                }
+                if self.this_type != LEXER_MORE {
+                    break;
+                }
             }
+            if self.token.is_none() {
+                self.token = Some(self.create_token());
+            }
+            break;
         }
+        self.input.release(token_start_marker);
         self.token.take().unwrap()
     }
 
@@ -143,32 +437,41 @@ impl<R, LAS, TF, CS> TokenSource for BaseLexer<R, LAS, TF, CS>
         self.interpreter.char_position_in_line()
     }
 
+    // #[inline(always)]
+    fn source_name(&self) -> Cow<'_, str> {
+        self.input.source_name()
+    }
+
     // fn input_stream(&self) -> &Self::CS {
     //     &self.input
     // }
 }
 
 impl<R, LAS, TF, CS> Recognizer for BaseLexer<R, LAS, TF, CS>
-    where CS: CharStream, LAS: LexerATNSimulator, R: Recognizer, TF: TokenFactory
+    where CS: CharStream, LAS: LexerATNSimulator, R: LexerRecognizer, TF: TokenFactory
 {
-    fn literal_names(&self) -> &[&str] {
+    fn literal_names(&self) -> &[Cow<'static, str>] {
         self.recognizer.literal_names()
     }
 
-    fn symbolic_names(&self) -> &[&str] {
+    fn symbolic_names(&self) -> &[Cow<'static, str>] {
         self.recognizer.symbolic_names()
     }
 
-    fn rule_names(&self) -> &[&str] {
+    fn vocabulary(&self) -> &Vocabulary {
+        self.recognizer.vocabulary()
+    }
+
+    fn rule_names(&self) -> &[Cow<'static, str>] {
         self.recognizer.rule_names()
     }
 
-    fn sempred(&self, _local_ctx: Rc<dyn RuleContext>, _rule_idx: isize, _action_idx: isize) -> bool {
-        todo!()
+    fn sempred(&self, local_ctx: Option<Rc<dyn RuleContext>>, rule_idx: isize, pred_idx: isize) -> bool {
+        self.recognizer.sempred(local_ctx, rule_idx, pred_idx)
     }
 
-    fn precpred(&self, _local_ctx: Rc<dyn RuleContext>, _precedence: isize) -> bool {
-        todo!()
+    fn precpred(&self, local_ctx: Option<Rc<dyn RuleContext>>, precedence: isize) -> bool {
+        self.recognizer.precpred(local_ctx, precedence)
     }
 
     /*fn atn(&self) -> &ATN {
@@ -183,8 +486,9 @@ impl<R, LAS, TF, CS> Recognizer for BaseLexer<R, LAS, TF, CS>
         self.recognizer.set_state(state)
     }
 
-    fn action(&self, _local_ctx: Rc<dyn RuleContext>, _rule_idx: isize, _action_idx: isize) {
-        todo!()
+    /// the action is executed by [LexerRecognizer::lexer_action] on this lexer, `local_ctx` is [None] for a lexer.
+    fn action(&mut self, _local_ctx: Option<Rc<dyn RuleContext>>, rule_idx: isize, action_idx: isize) {
+        R::lexer_action(self, rule_idx, action_idx)
     }
 
     fn add_error_listener(&mut self, l: Box<dyn ErrorListener>) {
@@ -213,13 +517,106 @@ impl<R, LAS, TF, CS> Recognizer for BaseLexer<R, LAS, TF, CS>
 }
 
 impl<R, LAS, TF, CS> Lexer for BaseLexer<R, LAS, TF, CS>
-    where R: Recognizer,
+    where R: LexerRecognizer,
           LAS: LexerATNSimulator,
           TF: TokenFactory,
           CS: CharStream
 {
+    /// the token is cloned to be returned, [TokenSource::next_token] emits the token without a clone.
     fn emit(&mut self) -> Self::TK {
-        todo!()
+        let tk = self.create_token();
+        self.emit_token(tk.clone());
+        tk
+    }
+
+    // #[inline(always)]
+    fn mode(&self) -> isize {
+        self.mode
+    }
+
+    // #[inline(always)]
+    fn set_mode(&mut self, mode: isize) {
+        self.mode = mode
+    }
+
+    // #[inline]
+    fn push_mode(&mut self, mode: isize) {
+        self.mode_stack.push(self.mode);
+        self.mode = mode
+    }
+
+    // #[inline]
+    fn pop_mode(&mut self) -> Option<isize> {
+        self.mode = self.mode_stack.pop()?;
+        Some(self.mode)
+    }
+
+    // #[inline(always)]
+    fn token_type(&self) -> isize {
+        self.this_type
+    }
+
+    // #[inline(always)]
+    fn set_type(&mut self, ttype: isize) {
+        self.this_type = ttype
+    }
+
+    // #[inline(always)]
+    fn channel(&self) -> isize {
+        self.channel
+    }
+
+    // #[inline(always)]
+    fn set_channel(&mut self, channel: isize) {
+        self.channel = channel
+    }
+
+    // #[inline]
+    fn text(&self) -> Cow<'_, str> {
+        match &self.text {
+            Some(text) => Cow::Borrowed(text),
+            None => self.interpreter.text(&self.input),
+        }
+    }
+
+    // #[inline(always)]
+    fn set_text(&mut self, text: String) {
+        self.text = Some(text)
+    }
+
+    // #[inline(always)]
+    fn input_stream(&mut self) -> &mut dyn CharStream {
+        &mut self.input
+    }
+
+    // #[inline]
+    fn snapshot(&self) -> LexerSnapshot {
+        LexerSnapshot {
+            mode: self.mode,
+            mode_stack: self.mode_stack.clone(),
+            line: self.interpreter.line(),
+            column: self.interpreter.char_position_in_line(),
+            index: self.input.index(),
+            lookahead_index: self.lookahead_index,
+        }
+    }
+
+    // #[inline]
+    fn restore(&mut self, snapshot: &LexerSnapshot) {
+        self.input.seek(snapshot.index);
+        self.interpreter.set_line(snapshot.line);
+        self.interpreter.set_char_position_in_line(snapshot.column);
+        self.mode = snapshot.mode;
+        self.mode_stack.clone_from(&snapshot.mode_stack);
+        self.lookahead_index = snapshot.lookahead_index;
+        self.token = None;
+        self.text = None;
+        self.hit_eof = false;
+    }
+
+    // #[inline(always)]
+    fn take_errors(&mut self) -> Vec<ANTLRError> {
+        std::mem::take(&mut self.errors)
     }
 }
 
@@ -1,6 +1,9 @@
-pub(crate) type LexerActionType = i32;
+use crate::lexer::Lexer;
+use crate::misc::murmur3::{murmur_finish, murmur_init, murmur_update, MurmurHash};
 
-// transition types
+pub type LexerActionType = i32;
+
+// lexer action types
 const LEXER_ACTION_CHANNEL: LexerActionType = 0;
 const LEXER_ACTION_CUSTOM: LexerActionType = 1;
 const LEXER_ACTION_MODE: LexerActionType = 2;
@@ -12,7 +15,7 @@ const LEXER_ACTION_TYPE: LexerActionType = 7;
 
 // Although using one structure for each type looks very redundant,
 // please don't optimize it yet in order to keep the code stylistically uniform
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum LexerAction {
     Channel(ChannelLexerAction),
     Custom(CustomLexerAction),
@@ -26,6 +29,52 @@ pub enum LexerAction {
 }
 
 impl LexerAction {
+    /// returns whether `action_type` is one of the serialized lexer action types.
+    // #[inline(always)]
+    pub fn is_valid_type(action_type: LexerActionType) -> bool {
+        (LEXER_ACTION_CHANNEL..=LEXER_ACTION_TYPE).contains(&action_type)
+    }
+
+    // #[inline(always)]
+    pub fn action_type(&self) -> LexerActionType {
+        match self {
+            LexerAction::Channel(c) => c.base.action_type,
+            LexerAction::Custom(c) => c.base.action_type,
+            LexerAction::Mode(m) => m.base.action_type,
+            LexerAction::More(m) => m.action_type,
+            LexerAction::PopMode(p) => p.action_type,
+            LexerAction::PushMode(p) => p.base.action_type,
+            LexerAction::Skip(s) => s.action_type,
+            LexerAction::Type(t) => t.base.action_type,
+            LexerAction::IndexedCustom(i) => i.base.action_type,
+        }
+    }
+
+    /// whether the action must be executed at the position of input where it is traversed,
+    /// rather than the end of token. Only custom actions are, as they may read the input.
+    // #[inline(always)]
+    pub fn is_position_dependent(&self) -> bool {
+        matches!(self, LexerAction::Custom(_) | LexerAction::IndexedCustom(_))
+    }
+
+    /// execute this action against `lexer`, an [LexerAction::IndexedCustom] executes the custom action it holds,
+    /// the input is positioned by [crate::lexer_action_executor::LexerActionExecutor] before.
+    pub fn execute<L: Lexer + ?Sized>(&self, lexer: &mut L) {
+        match self {
+            LexerAction::Channel(c) => lexer.set_channel(c.channel),
+            LexerAction::Custom(c) => lexer.action(None, c.rule_idx, c.action_idx),
+            LexerAction::Mode(m) => lexer.set_mode(m.mode),
+            LexerAction::More(_) => lexer.more(),
+            LexerAction::PopMode(_) => {
+                lexer.pop_mode();
+            }
+            LexerAction::PushMode(p) => lexer.push_mode(p.mode),
+            LexerAction::Skip(_) => lexer.skip(),
+            LexerAction::Type(t) => lexer.set_type(t.the_type),
+            LexerAction::IndexedCustom(i) => i.action.execute(lexer),
+        }
+    }
+
     // #[inline(always)]
     pub fn new(action_type: LexerActionType, data1: isize, data2: isize) -> Self {
         match action_type {
@@ -74,7 +123,7 @@ impl LexerAction {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BaseLexerAction {
     action_type: LexerActionType,
 }
@@ -84,20 +133,20 @@ impl BaseLexerAction {
     fn new(action_type: LexerActionType) -> Self { Self { action_type } }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ChannelLexerAction {
     pub(crate) base: BaseLexerAction,
     pub(crate) channel: isize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CustomLexerAction {
     pub(crate) base: BaseLexerAction,
     pub(crate) rule_idx: isize,
     pub(crate) action_idx: isize,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ModeLexerAction {
     pub(crate) base: BaseLexerAction,
     pub(crate) mode: isize,
@@ -107,7 +156,7 @@ pub type MoreLexerAction = BaseLexerAction;
 
 pub type PopModeLexerAction = BaseLexerAction;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PushModeLexerAction {
     pub(crate) base: BaseLexerAction,
     pub(crate) mode: isize,
@@ -115,28 +164,49 @@ pub struct PushModeLexerAction {
 
 pub type SkipLexerAction = BaseLexerAction;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TypeLexerAction {
     pub(crate) base: BaseLexerAction,
     pub(crate) the_type: isize,
 }
 
-#[derive(Debug)]
+/// [IndexedCustomLexerAction] is a position-dependent action fixed to the offset of input from the start of token,
+/// where the action should be executed. It is created by [crate::lexer_action_executor::LexerActionExecutor]
+/// for the custom actions which are traversed before the end of token.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct IndexedCustomLexerAction {
     pub(crate) base: BaseLexerAction,
     pub(crate) offset: isize,
-    pub(crate) lexer_action_nth: usize,
-    pub(crate) contrast_set: bool,
+    pub(crate) action: Box<LexerAction>,
 }
 
 impl IndexedCustomLexerAction {
     // #[inline(always)]
-    pub fn new(offset: isize, la_type: LexerActionType, la_nth: usize) -> Self {
+    pub fn new(offset: isize, action: LexerAction) -> Self {
         Self {
-            base: BaseLexerAction::new(la_type),
+            base: BaseLexerAction::new(action.action_type()),
             offset,
-            lexer_action_nth: la_nth,
-            contrast_set: true,
+            action: Box::new(action),
         }
     }
-}
\ No newline at end of file
+}
+
+impl MurmurHash for LexerAction {
+    // #[inline]
+    fn murmur(&self) -> u32 {
+        let (data1, data2) = match self {
+            LexerAction::Channel(c) => (c.channel, 0),
+            LexerAction::Custom(c) => (c.rule_idx, c.action_idx),
+            LexerAction::Mode(m) => (m.mode, 0),
+            LexerAction::PushMode(p) => (p.mode, 0),
+            LexerAction::Type(t) => (t.the_type, 0),
+            LexerAction::More(_) | LexerAction::PopMode(_) | LexerAction::Skip(_) => (0, 0),
+            LexerAction::IndexedCustom(i) => (i.offset, i.action.murmur() as isize),
+        };
+        let mut h = murmur_init(0);
+        h = murmur_update(h, self.action_type() as u32);
+        h = murmur_update(h, data1 as u32);
+        h = murmur_update(h, data2 as u32);
+        murmur_finish(h, 3)
+    }
+}
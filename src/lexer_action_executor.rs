@@ -0,0 +1,96 @@
+use std::sync::Arc;
+use crate::lexer::Lexer;
+use crate::lexer_action::{IndexedCustomLexerAction, LexerAction};
+use crate::misc::murmur3::{murmur_finish, murmur_init, murmur_update, MurmurHash};
+
+/// [LexerActionExecutor] executes the lexer actions traversed by a lexer ATN config, in the order they are traversed.
+/// Executors are shared by configs and DFA accept states, so they are immutable once created,
+/// [LexerActionExecutor::append] and [LexerActionExecutor::fix_offset_before_match] create new ones.
+#[derive(Debug)]
+pub struct LexerActionExecutor {
+    lexer_actions: Vec<LexerAction>,
+    cached_hash: u32,
+}
+
+impl LexerActionExecutor {
+    // #[inline]
+    pub fn new(lexer_actions: Vec<LexerAction>) -> Self {
+        let mut h = murmur_init(0);
+        for action in &lexer_actions {
+            h = murmur_update(h, action.murmur());
+        }
+        let cached_hash = murmur_finish(h, lexer_actions.len() as u32);
+        Self { lexer_actions, cached_hash }
+    }
+
+    /// [LexerActionExecutor::append] creates an executor which executes the actions of `executor` followed by `action`,
+    /// `executor` is [None] when `action` is the first action traversed.
+    // #[inline]
+    pub fn append(executor: Option<&Arc<Self>>, action: LexerAction) -> Arc<Self> {
+        let mut lexer_actions = executor.map_or_else(Vec::new, |e| e.lexer_actions.clone());
+        lexer_actions.push(action);
+        Arc::new(Self::new(lexer_actions))
+    }
+
+    /// Position-dependent actions traversed before the end of token are fixed to `offset`, which is the offset
+    /// of input from the start of token where the actions are traversed, so they are executed at that position
+    /// instead of the end of token. Actions fixed already are left alone.
+    /// Returns this executor itself if there is no action to fix.
+    pub fn fix_offset_before_match(self: &Arc<Self>, offset: isize) -> Arc<Self> {
+        if !self.lexer_actions.iter().any(|a| matches!(a, LexerAction::Custom(_))) {
+            return Arc::clone(self);
+        }
+        let lexer_actions = self.lexer_actions.iter()
+            .map(|a| match a {
+                LexerAction::Custom(_) => LexerAction::IndexedCustom(IndexedCustomLexerAction::new(offset, a.clone())),
+                _ => a.clone(),
+            })
+            .collect();
+        Arc::new(Self::new(lexer_actions))
+    }
+
+    // #[inline(always)]
+    pub fn lexer_actions(&self) -> &[LexerAction] {
+        &self.lexer_actions
+    }
+
+    /// Execute the actions against `lexer` when a token is matched, input of `lexer` is at the end of token,
+    /// and `start_index` is where the token starts. Input is positioned for each indexed action,
+    /// and restored to the end of token afterwards.
+    pub fn execute<L: Lexer + ?Sized>(&self, lexer: &mut L, start_index: isize) {
+        let stop_index = lexer.input_stream().index();
+        let mut requires_seek = false;
+        for action in &self.lexer_actions {
+            match action {
+                LexerAction::IndexedCustom(ic) => {
+                    lexer.input_stream().seek(start_index + ic.offset);
+                    requires_seek = start_index + ic.offset != stop_index;
+                }
+                _ if action.is_position_dependent() => {
+                    lexer.input_stream().seek(stop_index);
+                    requires_seek = false;
+                }
+                _ => {}
+            }
+            action.execute(lexer);
+        }
+        if requires_seek {
+            lexer.input_stream().seek(stop_index);
+        }
+    }
+}
+
+impl PartialEq for LexerActionExecutor {
+    fn eq(&self, other: &Self) -> bool {
+        self.cached_hash == other.cached_hash && self.lexer_actions == other.lexer_actions
+    }
+}
+
+impl Eq for LexerActionExecutor {}
+
+impl MurmurHash for LexerActionExecutor {
+    // #[inline(always)]
+    fn murmur(&self) -> u32 {
+        self.cached_hash
+    }
+}
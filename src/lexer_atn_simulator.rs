@@ -1,21 +1,64 @@
+use std::borrow::Cow;
 use std::sync::{Arc, RwLock};
-use crate::atn::ATN;
+use crate::atn::{ATN, ATN_INVALID_ALT_NUMBER};
+use crate::atn_config::ATNConfig;
+use crate::atn_config_set::ATNConfigSet;
 use crate::atn_simulator::{ATNSimulator, BaseATNSimulator};
+use crate::atn_state::ATNState;
 use crate::char_stream::CharStream;
 use crate::dfa::DFA;
-use crate::lexer::LEXER_DEFAULT_MODE;
-use crate::prediction_context::PredictionContextCache;
+use crate::dfa_state::{DFA_ERROR_STATE, DFAState};
+use crate::errors::ANTLRError;
+use crate::int_stream::EOF;
+use crate::lexer_action_executor::LexerActionExecutor;
+use crate::lexer::{LEXER_DEFAULT_MODE, LEXER_MAX_CHAR_VALUE, LEXER_MIN_CHAR_VALUE, LexerRecognizer, LexerView};
+use crate::Nth;
+use crate::prediction_context::{PREDICTION_CONTEXT_EMPTY_RETURN_STATE, PredictionContext, PredictionContextCache};
+use crate::token::TOKEN_EOF;
+use crate::transition::Transition;
+
+/// edges of DFA states are cached for chars in [LEXER_MIN_DFA_EDGE]..=[LEXER_MAX_DFA_EDGE] only,
+/// other chars are always computed through the ATN.
+pub const LEXER_MIN_DFA_EDGE: isize = 0;
+pub const LEXER_MAX_DFA_EDGE: isize = 127;
 
 pub trait LexerATNSimulator: ATNSimulator {
     fn reset(&mut self);
 
-    fn match_(&mut self, input: &mut dyn CharStream, mode: isize) -> isize;
+    /// match the longest token of `mode` from the current position of `input`, returns the token type,
+    /// or [TOKEN_EOF] if `input` is at the end. Predicates of the grammar are evaluated by `recog`,
+    /// see [LexerRecognizer::lexer_sempred].
+    /// [ANTLRError::LexerNoAltErr] is returned if no token could be matched, `input` is left where the
+    /// match failed.
+    fn match_(&mut self, input: &mut dyn CharStream, mode: isize, recog: &dyn LexerRecognizer) -> Result<isize, ANTLRError>;
 
     fn char_position_in_line(&self) -> isize;
 
+    fn set_char_position_in_line(&mut self, column: isize);
+
     fn line(&self) -> isize;
 
-    fn consume(&self, input: &mut dyn CharStream);
+    fn set_line(&mut self, line: isize);
+
+    /// number of columns a tab advances to the next tab stop, 1 by default, which counts a tab as one column.
+    fn tab_width(&self) -> isize;
+
+    /// set the tab width, see [LexerATNSimulator::tab_width]. Panics if `width` is less than 1.
+    fn set_tab_width(&mut self, width: isize);
+
+    /// consume one char of `input` and update line and column.
+    fn consume(&mut self, input: &mut dyn CharStream);
+
+    /// text matched from the start of the last [LexerATNSimulator::match_] to the current position of `input`.
+    fn text<'a>(&self, input: &'a dyn CharStream) -> Cow<'a, str>;
+
+    /// take the actions of the token accepted by the last [LexerATNSimulator::match_],
+    /// they should be executed against the lexer before the token is emitted.
+    fn take_lexer_action_executor(&mut self) -> Option<Arc<LexerActionExecutor>>;
+
+    /// index of the last char examined by the last [LexerATNSimulator::match_], which is past the end of the token
+    /// matched, as the match looks ahead till no longer token could be matched.
+    fn lookahead_index(&self) -> isize;
 }
 
 #[derive(Debug)]
@@ -45,6 +88,17 @@ pub struct BaseLexerATNSimulator {
     base: BaseATNSimulator,
     pub(crate) mode: isize,
     prev_accept: SimState,
+    // the char index where the current match started
+    start_index: isize,
+    // line number 1..n within the input
+    line: isize,
+    // the index of the char we are examining within the current line, 0..n-1
+    column: isize,
+    tab_width: isize,
+    // actions of the token accepted by the last match
+    accept_executor: Option<Arc<LexerActionExecutor>>,
+    // the index of the last char examined by the last match
+    lookahead_index: isize,
 }
 
 impl BaseLexerATNSimulator {
@@ -54,7 +108,358 @@ impl BaseLexerATNSimulator {
             base: BaseATNSimulator::new(atn, shared_ctx_cache, decision_to_dfa),
             mode: LEXER_DEFAULT_MODE,
             prev_accept: SimState::new(),
+            start_index: -1,
+            line: 1,
+            column: 0,
+            tab_width: 1,
+            accept_executor: None,
+            lookahead_index: -1,
+        }
+    }
+
+    /// match through the ATN when the DFA of the mode has no start state yet.
+    // #[inline]
+    fn match_atn(&mut self, atn: &ATN, input: &mut dyn CharStream, dfa: &RwLock<DFA>, recog: &dyn LexerRecognizer) -> Result<isize, ANTLRError> {
+        let start_state_nth = atn.mode2start_state_nths[self.mode as usize];
+        let mut s0_closure = self.compute_start_state(atn, input, start_state_nth, recog);
+        // a start state depends on predicates can not be cached.
+        let suppress_edge = s0_closure.has_semantic_context;
+        s0_closure.has_semantic_context = false;
+        let next = self.add_dfa_state(atn, dfa, s0_closure);
+        if !suppress_edge {
+            dfa.write().unwrap().s0 = Some(next);
+        }
+        self.exec_atn(atn, input, dfa, next, recog)
+    }
+
+    // #[inline]
+    fn exec_atn(&mut self, atn: &ATN, input: &mut dyn CharStream, dfa: &RwLock<DFA>, ds0: Nth, recog: &dyn LexerRecognizer) -> Result<isize, ANTLRError> {
+        if dfa.read().unwrap().state(ds0).accept_state {
+            // allow zero-length tokens
+            self.capture_sim_state(input, ds0);
+        }
+        let mut t = input.la(1);
+        let mut s = ds0;
+        loop {
+            // As we move src->trg, src->trg, we keep track of the previous trg to
+            // avoid looking up the DFA state again, which is expensive.
+            let (target, accept) = match self.existing_target_state(dfa, s, t) {
+                Some(existing) => existing,
+                None => self.compute_target_state(atn, input, dfa, s, t, recog),
+            };
+            if target == DFA_ERROR_STATE {
+                break;
+            }
+            // If this is a consumable input element, make sure to consume before
+            // capturing the accept state so the input index, line, and char
+            // position accurately reflect the state of the interpreter at the
+            // end of the token.
+            if t != EOF {
+                self.consume(input);
+            }
+            if accept {
+                self.capture_sim_state(input, target);
+                if t == EOF {
+                    break;
+                }
+            }
+            t = input.la(1);
+            s = target;
+        }
+        self.fail_or_accept(input, dfa, t)
+    }
+
+    /// get the cached target of edge `t` from DFA state `s`, and whether the target is an accept state.
+    // #[inline]
+    fn existing_target_state(&self, dfa: &RwLock<DFA>, s: Nth, t: isize) -> Option<(Nth, bool)> {
+        if !(LEXER_MIN_DFA_EDGE..=LEXER_MAX_DFA_EDGE).contains(&t) {
+            return None;
+        }
+        let dfa = dfa.read().unwrap();
+        match dfa.state(s).edges.get((t - LEXER_MIN_DFA_EDGE) as usize) {
+            Some(Some(DFA_ERROR_STATE)) => Some((DFA_ERROR_STATE, false)),
+            Some(Some(target)) => Some((*target, dfa.state(*target).accept_state)),
+            _ => None,
+        }
+    }
+
+    /// compute the target of edge `t` from DFA state `s` through the ATN, and add it to the DFA.
+    /// Returns [DFA_ERROR_STATE] if `t` leads to nowhere.
+    // #[inline]
+    fn compute_target_state(&mut self, atn: &ATN, input: &mut dyn CharStream, dfa: &RwLock<DFA>, s: Nth, t: isize, recog: &dyn LexerRecognizer) -> (Nth, bool) {
+        let mut reach = ATNConfigSet::new(false);
+        {
+            let dfa = dfa.read().unwrap();
+            // if we don't find an existing DFA state, fill reach starting from closure.
+            self.reachable_config_set(atn, input, &dfa.state(s).configs, &mut reach, t, recog);
+        }
+        if reach.is_empty() {
+            // we got nowhere on t from s, remember it unless it depends on predicates.
+            if !reach.has_semantic_context {
+                self.add_dfa_edge(dfa, s, t, DFA_ERROR_STATE);
+            }
+            return (DFA_ERROR_STATE, false);
+        }
+        let suppress_edge = reach.has_semantic_context;
+        reach.has_semantic_context = false;
+        let to = self.add_dfa_state(atn, dfa, reach);
+        if !suppress_edge {
+            self.add_dfa_edge(dfa, s, t, to);
+        }
+        (to, dfa.read().unwrap().state(to).accept_state)
+    }
+
+    // #[inline]
+    fn fail_or_accept(&mut self, input: &mut dyn CharStream, dfa: &RwLock<DFA>, t: isize) -> Result<isize, ANTLRError> {
+        // `t` is the char at the current index, no target state is reached from it.
+        self.lookahead_index = input.index();
+        if let Some(accept_nth) = self.prev_accept.dfa_state {
+            input.seek(self.prev_accept.index);
+            self.line = self.prev_accept.line;
+            self.column = self.prev_accept.column;
+            let dfa = dfa.read().unwrap();
+            let accept_state = dfa.state(accept_nth);
+            self.accept_executor = accept_state.lexer_action_executor.clone();
+            return Ok(accept_state.prediction);
+        }
+        // if no accept and EOF is first char, return EOF
+        if t == EOF && input.index() == self.start_index {
+            return Ok(TOKEN_EOF);
+        }
+        Err(ANTLRError::LexerNoAltErr { start_index: self.start_index })
+    }
+
+    /// Given a starting configuration set, figure out all ATN configurations
+    /// we can reach upon input `t`. Parameter `reach` is a return parameter.
+    // #[inline]
+    fn reachable_config_set(&mut self, atn: &ATN, input: &mut dyn CharStream, closure: &ATNConfigSet, reach: &mut ATNConfigSet, t: isize, recog: &dyn LexerRecognizer) {
+        // this is used to skip processing for configs which have a lower priority
+        // than a config that already reached an accept state for the same rule
+        let mut skip_alt = ATN_INVALID_ALT_NUMBER;
+        for c in &closure.configs {
+            let current_alt_reached_accept_state = c.alt() == skip_alt;
+            if current_alt_reached_accept_state && c.passed_through_non_greedy_decision() {
+                continue;
+            }
+            for trans in atn.states[c.state_nth()].transitions() {
+                if let Some(target) = Self::reachable_target(trans, t) {
+                    let executor = c.lexer_action_executor()
+                        .map(|e| e.fix_offset_before_match(input.index() - self.start_index));
+                    let config = c.transit_with_executor(atn, target, Arc::clone(c.context()), executor);
+                    let treat_eof_as_epsilon = t == EOF;
+                    if self.closure(atn, input, config, reach, current_alt_reached_accept_state, true, treat_eof_as_epsilon, recog) {
+                        // any remaining configs for this alt have a lower priority than
+                        // the one that just reached an accept state.
+                        skip_alt = c.alt();
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    // #[inline(always)]
+    fn reachable_target(trans: &Transition, t: isize) -> Option<Nth> {
+        if trans.matches(t, LEXER_MIN_CHAR_VALUE, LEXER_MAX_CHAR_VALUE) {
+            Some(trans.target_nth())
+        } else {
+            None
+        }
+    }
+
+    // #[inline]
+    fn compute_start_state(&mut self, atn: &ATN, input: &mut dyn CharStream, p: Nth, recog: &dyn LexerRecognizer) -> ATNConfigSet {
+        let initial_context = PredictionContext::new_empty();
+        let mut configs = ATNConfigSet::new(false);
+        for (i, trans) in atn.states[p].transitions().iter().enumerate() {
+            let c = ATNConfig::new_lexer(trans.target_nth(), i as isize + 1, Arc::clone(&initial_context));
+            self.closure(atn, input, c, &mut configs, false, false, false, recog);
+        }
+        configs
+    }
+
+    /// Since the alternatives within any lexer decision are ordered by
+    /// preference, this method stops pursuing the closure as soon as an accept
+    /// state is reached. After the first accept state is reached by depth-first
+    /// search from `config`, all other (potentially reachable) states for
+    /// this rule would have a lower priority.
+    /// Returns true if an accept state is reached, otherwise false.
+    #[allow(clippy::too_many_arguments)]
+    fn closure(
+        &mut self,
+        atn: &ATN,
+        input: &mut dyn CharStream,
+        config: ATNConfig,
+        configs: &mut ATNConfigSet,
+        mut current_alt_reached_accept_state: bool,
+        speculative: bool,
+        treat_eof_as_epsilon: bool,
+        recog: &dyn LexerRecognizer,
+    ) -> bool {
+        let state = &atn.states[config.state_nth()];
+        if let ATNState::RuleStop(_) = state {
+            let context = Arc::clone(config.context());
+            if context.has_empty_path() {
+                if context.is_empty() {
+                    configs.add(config);
+                    return true;
+                }
+                configs.add(config.transit(atn, config.state_nth(), PredictionContext::new_empty()));
+                current_alt_reached_accept_state = true;
+            }
+            if !context.is_empty() {
+                for i in 0..context.len() {
+                    let ret_state_nth = context.return_state(i);
+                    if ret_state_nth == PREDICTION_CONTEXT_EMPTY_RETURN_STATE {
+                        continue;
+                    }
+                    let parent = Arc::clone(context.parent(i).unwrap());
+                    let c = config.transit(atn, ret_state_nth, parent);
+                    current_alt_reached_accept_state = self.closure(
+                        atn, input, c, configs, current_alt_reached_accept_state, speculative, treat_eof_as_epsilon, recog,
+                    );
+                }
+            }
+            return current_alt_reached_accept_state;
+        }
+
+        // optimization
+        if !state.only_has_epsilon_transitions()
+            && (!current_alt_reached_accept_state || !config.passed_through_non_greedy_decision()) {
+            configs.add(config.clone());
+        }
+
+        for trans in state.transitions() {
+            if let Some(c) = self.epsilon_target(atn, input, &config, trans, configs, speculative, treat_eof_as_epsilon, recog) {
+                current_alt_reached_accept_state = self.closure(
+                    atn, input, c, configs, current_alt_reached_accept_state, speculative, treat_eof_as_epsilon, recog,
+                );
+            }
         }
+        current_alt_reached_accept_state
+    }
+
+    // side-effect: can alter configs.has_semantic_context
+    #[allow(clippy::too_many_arguments)]
+    fn epsilon_target(
+        &mut self,
+        atn: &ATN,
+        input: &mut dyn CharStream,
+        config: &ATNConfig,
+        trans: &Transition,
+        configs: &mut ATNConfigSet,
+        speculative: bool,
+        treat_eof_as_epsilon: bool,
+        recog: &dyn LexerRecognizer,
+    ) -> Option<ATNConfig> {
+        let target = trans.target_nth();
+        match trans {
+            Transition::Rule(rt) => {
+                let context = PredictionContext::new_singleton(Arc::clone(config.context()), rt.follow_state_nth);
+                Some(config.transit(atn, target, context))
+            }
+            Transition::Predicate(pt) => {
+                // Track traversing semantic predicates. If we traverse,
+                // we cannot add a DFA state for this "reach" computation
+                // because the DFA would not test the predicate again in the
+                // future. Rather than creating collections of semantic predicates
+                // like v3 and testing them on prediction, v4 will test them on the
+                // fly all the time using the ATN not the DFA. This is slower but
+                // semantically it's not used that often. One of the key elements to
+                // this predicate mechanism is not adding DFA states that see
+                // predicates immediately afterwards in the ATN.
+                configs.has_semantic_context = true;
+                if self.evaluate_predicate(input, pt.rule_idx, pt.pre_idx, speculative, recog) {
+                    Some(config.transit(atn, target, Arc::clone(config.context())))
+                } else {
+                    None
+                }
+            }
+            Transition::Action(at) => {
+                if config.context().has_empty_path() {
+                    // execute actions anywhere in the start rule for a token,
+                    // actions of invoked rules are ignored. The action index is checked by the deserializer.
+                    let action = atn.lexer_actions[at.action_idx as usize].clone();
+                    let executor = LexerActionExecutor::append(config.lexer_action_executor(), action);
+                    Some(config.transit_with_executor(atn, target, Arc::clone(config.context()), Some(executor)))
+                } else {
+                    Some(config.transit(atn, target, Arc::clone(config.context())))
+                }
+            }
+            Transition::Epsilon(_) => Some(config.transit(atn, target, Arc::clone(config.context()))),
+            Transition::Atom(_) | Transition::Range(_) | Transition::Set(_)
+            if treat_eof_as_epsilon && trans.matches(EOF, LEXER_MIN_CHAR_VALUE, LEXER_MAX_CHAR_VALUE) => {
+                Some(config.transit(atn, target, Arc::clone(config.context())))
+            }
+            _ => None,
+        }
+    }
+
+    /// Evaluate a predicate specified in the lexer.
+    /// If `speculative` is true, this method was called before consuming the char matched by the predicate,
+    /// so the char is consumed for the predicate and everything is restored afterwards.
+    // #[inline]
+    fn evaluate_predicate(&mut self, input: &mut dyn CharStream, rule_idx: isize, pred_idx: isize, speculative: bool, recog: &dyn LexerRecognizer) -> bool {
+        if !speculative {
+            return self.sempred(input, rule_idx, pred_idx, recog);
+        }
+        let (line, column, index) = (self.line, self.column, input.index());
+        // a streaming input keeps the char consumed till it is seeked back.
+        let marker = input.mark();
+        if input.la(1) != EOF {
+            self.consume(input);
+        }
+        let result = self.sempred(input, rule_idx, pred_idx, recog);
+        self.line = line;
+        self.column = column;
+        input.seek(index);
+        input.release(marker);
+        result
+    }
+
+    /// evaluate a predicate against the lexer at the current position of `input`.
+    // #[inline]
+    fn sempred(&self, input: &mut dyn CharStream, rule_idx: isize, pred_idx: isize, recog: &dyn LexerRecognizer) -> bool {
+        let mut lexer = LexerView { input, start_index: self.start_index, line: self.line, column: self.column, mode: self.mode };
+        recog.lexer_sempred(&mut lexer, rule_idx, pred_idx)
+    }
+
+    // #[inline(always)]
+    fn capture_sim_state(&mut self, input: &dyn CharStream, dfa_state: Nth) {
+        self.prev_accept.index = input.index();
+        self.prev_accept.line = self.line;
+        self.prev_accept.column = self.column;
+        self.prev_accept.dfa_state = Some(dfa_state);
+    }
+
+    // #[inline]
+    fn add_dfa_edge(&self, dfa: &RwLock<DFA>, from: Nth, t: isize, to: Nth) {
+        if !(LEXER_MIN_DFA_EDGE..=LEXER_MAX_DFA_EDGE).contains(&t) {
+            // Only track edges within the DFA bounds
+            return;
+        }
+        let mut dfa = dfa.write().unwrap();
+        let state = dfa.state_mut(from);
+        if state.edges.is_empty() {
+            state.edges = vec![None; (LEXER_MAX_DFA_EDGE - LEXER_MIN_DFA_EDGE + 1) as usize];
+        }
+        state.edges[(t - LEXER_MIN_DFA_EDGE) as usize] = Some(to);
+    }
+
+    /// Add a new DFA state if there isn't one with this set of configurations already.
+    /// This method also detects the first configuration containing an ATN rule stop state,
+    /// which makes the DFA state an accept state predicting the token type of the rule.
+    // #[inline]
+    fn add_dfa_state(&self, atn: &ATN, dfa: &RwLock<DFA>, configs: ATNConfigSet) -> Nth {
+        let mut proposed = DFAState::new(0, Box::new(configs));
+        let first_rule_stop = proposed.configs.configs.iter()
+            .find(|c| matches!(atn.states[c.state_nth()], ATNState::RuleStop(_)));
+        if let Some(config) = first_rule_stop {
+            proposed.accept_state = true;
+            proposed.lexer_action_executor = config.lexer_action_executor().cloned();
+            proposed.prediction = atn.rule2token_type[atn.states[config.state_nth()].rule_index()];
+        }
+        dfa.write().unwrap().add_state(proposed)
     }
 }
 
@@ -64,36 +469,109 @@ impl ATNSimulator for BaseLexerATNSimulator {
         self.base.shared_context_cache()
     }
 
+    // #[inline(always)]
     fn atn(&self) -> &ATN {
-        todo!()
+        self.base.atn()
     }
 
+    // #[inline(always)]
     fn decision_to_dfa(&self) -> &Vec<RwLock<DFA>> {
-        todo!()
+        self.base.decision_to_dfa()
     }
 }
 
 impl LexerATNSimulator for BaseLexerATNSimulator {
+    // #[inline]
     fn reset(&mut self) {
-        todo!()
+        self.prev_accept.reset();
+        self.start_index = -1;
+        self.line = 1;
+        self.column = 0;
+        self.mode = LEXER_DEFAULT_MODE;
+        self.lookahead_index = -1;
     }
 
-    fn match_(&mut self, input: &mut dyn CharStream, mode: isize) -> isize {
+    fn match_(&mut self, input: &mut dyn CharStream, mode: isize, recog: &dyn LexerRecognizer) -> Result<isize, ANTLRError> {
         self.mode = mode;
-
-        input.text(1, 2);
-        todo!()
+        self.start_index = input.index();
+        self.prev_accept.reset();
+        self.accept_executor = None;
+        // hold the ATN and DFAs by their own, so they could be borrowed when self is borrowed as mutable.
+        let atn = Arc::clone(&self.base.atn);
+        let decision_to_dfa = Arc::clone(&self.base.decision_to_dfa);
+        let dfa = &decision_to_dfa[mode as usize];
+        let s0 = dfa.read().unwrap().s0;
+        // a streaming input keeps the chars looked ahead till it is seeked back to the last accept state.
+        let marker = input.mark();
+        let result = match s0 {
+            None => self.match_atn(&atn, input, dfa, recog),
+            Some(s0) => self.exec_atn(&atn, input, dfa, s0, recog),
+        };
+        input.release(marker);
+        result
     }
 
+    // #[inline(always)]
     fn char_position_in_line(&self) -> isize {
-        todo!()
+        self.column
     }
 
+    // #[inline(always)]
+    fn set_char_position_in_line(&mut self, column: isize) {
+        self.column = column
+    }
+
+    // #[inline(always)]
     fn line(&self) -> isize {
-        todo!()
+        self.line
+    }
+
+    // #[inline(always)]
+    fn set_line(&mut self, line: isize) {
+        self.line = line
     }
 
-    fn consume(&self, input: &mut dyn CharStream) {
-        todo!()
+    // #[inline(always)]
+    fn tab_width(&self) -> isize {
+        self.tab_width
+    }
+
+    // #[inline]
+    fn set_tab_width(&mut self, width: isize) {
+        if width < 1 {
+            panic!("tab width must be at least 1, but got {}", width)
+        }
+        self.tab_width = width
     }
-}
\ No newline at end of file
+
+    // #[inline]
+    fn consume(&mut self, input: &mut dyn CharStream) {
+        match input.la(1) {
+            c if c == '\n' as isize => {
+                self.line += 1;
+                self.column = 0;
+            }
+            c if c == '\t' as isize => self.column += self.tab_width - self.column % self.tab_width,
+            _ => self.column += 1,
+        }
+        input.consume();
+    }
+
+    // #[inline]
+    fn text<'a>(&self, input: &'a dyn CharStream) -> Cow<'a, str> {
+        if input.index() <= self.start_index {
+            return Cow::Borrowed("");
+        }
+        input.text(self.start_index as usize, (input.index() - 1) as usize)
+    }
+
+    // #[inline(always)]
+    fn take_lexer_action_executor(&mut self) -> Option<Arc<LexerActionExecutor>> {
+        self.accept_executor.take()
+    }
+
+    // #[inline(always)]
+    fn lookahead_index(&self) -> isize {
+        self.lookahead_index
+    }
+}
@@ -0,0 +1,269 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::{Arc, RwLock};
+use crate::atn::ATN;
+use crate::atn_type::ATNType;
+use crate::char_stream::CharStream;
+use crate::dfa::DFA;
+use crate::error_listener::ErrorListener;
+use crate::errors::{ANTLRError, InterpFileError};
+use crate::lexer::{BaseLexer, Lexer};
+use crate::lexer_atn_simulator::BaseLexerATNSimulator;
+use crate::lexer_snapshot::LexerSnapshot;
+use crate::prediction_context::PredictionContextCache;
+use crate::recognizer::{BaseRecognizer, Names, Recognizer};
+use crate::rule_context::RuleContext;
+use crate::vocabulary::Vocabulary;
+use crate::token_factory::{CommonTokenFactory, TokenFactory};
+use crate::token_source::TokenSource;
+
+/// [LexerInterpreter] tokenizes `CS` with a lexer [ATN] and the name tables of its grammar,
+/// which are usually loaded at runtime, see [crate::interp_file::InterpFile].
+/// No generated lexer is needed, so the grammar can have no actions or predicates of target code,
+/// they are handled by [BaseRecognizer], which means predicates always pass and custom actions do nothing.
+pub struct LexerInterpreter<CS: CharStream> {
+    base: BaseLexer<BaseRecognizer, BaseLexerATNSimulator, CommonTokenFactory, CS>,
+    atn: Arc<ATN>,
+    channel_names: Names,
+    mode_names: Names,
+}
+
+impl<CS: CharStream> LexerInterpreter<CS> {
+    /// [LexerInterpreter::new] is [LexerInterpreter::try_new] but panics if `atn` is not a lexer ATN.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        grammar_file_name: &'static str,
+        atn: Arc<ATN>,
+        rule_names: Names,
+        literal_names: Names,
+        symbolic_names: Names,
+        channel_names: Names,
+        mode_names: Names,
+        input: CS,
+    ) -> Self {
+        Self::try_new(grammar_file_name, atn, rule_names, literal_names, symbolic_names, channel_names, mode_names, input)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// [LexerInterpreter::try_new] creates an interpreter lexing `input` with `atn`,
+    /// DFAs of the modes are created for this interpreter only.
+    /// The name tables are shared, see [crate::recognizer::static_names] for the tables of generated code.
+    /// [InterpFileError::UnexpectedGrammarType] is returned if `atn` is not a lexer ATN.
+    #[allow(clippy::too_many_arguments)]
+    pub fn try_new(
+        grammar_file_name: &'static str,
+        atn: Arc<ATN>,
+        rule_names: Names,
+        literal_names: Names,
+        symbolic_names: Names,
+        channel_names: Names,
+        mode_names: Names,
+        input: CS,
+    ) -> Result<Self, InterpFileError> {
+        if atn.grammar_type != ATNType::Lexer {
+            return Err(InterpFileError::UnexpectedGrammarType { expected: ATNType::Lexer, found: ATNType::Parser });
+        }
+        let decision_to_dfa: Vec<RwLock<DFA>> = atn.mode2start_state_nths.iter()
+            .enumerate()
+            .map(|(mode, start_state_nth)| RwLock::new(DFA::new(&atn, *start_state_nth, mode)))
+            .collect();
+        let interpreter = BaseLexerATNSimulator::new(
+            Arc::clone(&atn),
+            Arc::new(RwLock::new(PredictionContextCache::new())),
+            Arc::new(decision_to_dfa),
+        );
+        let recognizer = BaseRecognizer::with_names(rule_names, literal_names, symbolic_names, grammar_file_name);
+        Ok(Self {
+            base: BaseLexer::new(recognizer, interpreter, CommonTokenFactory::new(), input),
+            atn,
+            channel_names,
+            mode_names,
+        })
+    }
+
+    // #[inline(always)]
+    pub fn atn(&self) -> &ATN {
+        &self.atn
+    }
+
+    // #[inline(always)]
+    pub fn channel_names(&self) -> &[Cow<'static, str>] {
+        &self.channel_names
+    }
+
+    // #[inline(always)]
+    pub fn mode_names(&self) -> &[Cow<'static, str>] {
+        &self.mode_names
+    }
+
+    /// the index of the char the lexer is examining.
+    // #[inline(always)]
+    pub fn char_index(&self) -> isize {
+        self.base.char_index()
+    }
+
+    /// see [BaseLexer::set_emit_error_tokens].
+    // #[inline(always)]
+    pub fn set_emit_error_tokens(&mut self, emit: bool) {
+        self.base.set_emit_error_tokens(emit)
+    }
+
+    /// see [BaseLexer::set_tab_width].
+    // #[inline(always)]
+    pub fn set_tab_width(&mut self, width: isize) {
+        self.base.set_tab_width(width)
+    }
+
+    /// reset the lexer to lex from the beginning of its input stream.
+    // #[inline(always)]
+    pub fn reset(&mut self) {
+        self.base.reset()
+    }
+}
+
+impl<CS: CharStream> TokenSource for LexerInterpreter<CS> {
+    type TK = <CommonTokenFactory as TokenFactory>::TK;
+
+    // #[inline(always)]
+    fn next_token(&mut self) -> Self::TK {
+        self.base.next_token()
+    }
+
+    fn line(&self) -> isize {
+        self.base.line()
+    }
+
+    fn char_position_in_line(&self) -> isize {
+        self.base.char_position_in_line()
+    }
+
+    // #[inline(always)]
+    fn source_name(&self) -> Cow<'_, str> {
+        self.base.source_name()
+    }
+}
+
+impl<CS: CharStream> Recognizer for LexerInterpreter<CS> {
+    fn literal_names(&self) -> &[Cow<'static, str>] {
+        self.base.literal_names()
+    }
+
+    fn symbolic_names(&self) -> &[Cow<'static, str>] {
+        self.base.symbolic_names()
+    }
+
+    fn vocabulary(&self) -> &Vocabulary {
+        self.base.vocabulary()
+    }
+
+    fn rule_names(&self) -> &[Cow<'static, str>] {
+        self.base.rule_names()
+    }
+
+    fn sempred(&self, local_ctx: Option<Rc<dyn RuleContext>>, rule_idx: isize, pred_idx: isize) -> bool {
+        self.base.sempred(local_ctx, rule_idx, pred_idx)
+    }
+
+    fn precpred(&self, local_ctx: Option<Rc<dyn RuleContext>>, precedence: isize) -> bool {
+        self.base.precpred(local_ctx, precedence)
+    }
+
+    fn state(&self) -> isize {
+        self.base.state()
+    }
+
+    fn set_state(&mut self, state: isize) {
+        self.base.set_state(state)
+    }
+
+    fn action(&mut self, local_ctx: Option<Rc<dyn RuleContext>>, rule_idx: isize, action_idx: isize) {
+        self.base.action(local_ctx, rule_idx, action_idx)
+    }
+
+    fn add_error_listener(&mut self, l: Box<dyn ErrorListener>) {
+        self.base.add_error_listener(l)
+    }
+
+    fn remove_error_listeners(&mut self) {
+        self.base.remove_error_listeners()
+    }
+
+    fn error_listener_dispatch(&self) -> Rc<RefCell<dyn ErrorListener>> {
+        self.base.error_listener_dispatch()
+    }
+
+    fn has_error(&self) -> bool {
+        self.base.has_error()
+    }
+
+    fn error(&self) -> Option<&ANTLRError> {
+        self.base.error()
+    }
+
+    fn set_error(&mut self, e: ANTLRError) {
+        self.base.set_error(e)
+    }
+}
+
+impl<CS: CharStream> Lexer for LexerInterpreter<CS> {
+    fn emit(&mut self) -> Self::TK {
+        self.base.emit()
+    }
+
+    fn mode(&self) -> isize {
+        self.base.mode()
+    }
+
+    fn set_mode(&mut self, mode: isize) {
+        self.base.set_mode(mode)
+    }
+
+    fn push_mode(&mut self, mode: isize) {
+        self.base.push_mode(mode)
+    }
+
+    fn pop_mode(&mut self) -> Option<isize> {
+        self.base.pop_mode()
+    }
+
+    fn token_type(&self) -> isize {
+        self.base.token_type()
+    }
+
+    fn set_type(&mut self, ttype: isize) {
+        self.base.set_type(ttype)
+    }
+
+    fn channel(&self) -> isize {
+        self.base.channel()
+    }
+
+    fn set_channel(&mut self, channel: isize) {
+        self.base.set_channel(channel)
+    }
+
+    fn text(&self) -> Cow<'_, str> {
+        self.base.text()
+    }
+
+    fn set_text(&mut self, text: String) {
+        self.base.set_text(text)
+    }
+
+    fn input_stream(&mut self) -> &mut dyn CharStream {
+        self.base.input_stream()
+    }
+
+    fn snapshot(&self) -> LexerSnapshot {
+        self.base.snapshot()
+    }
+
+    fn restore(&mut self, snapshot: &LexerSnapshot) {
+        self.base.restore(snapshot)
+    }
+
+    fn take_errors(&mut self) -> Vec<ANTLRError> {
+        self.base.take_errors()
+    }
+}
@@ -0,0 +1,165 @@
+use std::ops::Range;
+use crate::lexer::{LEXER_DEFAULT_MODE, Lexer};
+use crate::Nth;
+use crate::token::{Token, TOKEN_EOF};
+
+/// [LexerSnapshot] is the state a lexer resumes from, see [Lexer::snapshot] and [Lexer::restore].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexerSnapshot {
+    pub mode: isize,
+    pub mode_stack: Vec<isize>,
+    pub line: isize,
+    pub column: isize,
+    /// index of the char the lexer resumes from.
+    pub index: isize,
+    /// the largest index of the chars examined by the lexer before the snapshot, -1 if none.
+    /// The tokens before the snapshot depend on the chars till this index, which is usually past `index`
+    /// as the lexer looks ahead for a longer token.
+    pub lookahead_index: isize,
+}
+
+/// the state of a lexer before the first char.
+impl Default for LexerSnapshot {
+    fn default() -> Self {
+        Self { mode: LEXER_DEFAULT_MODE, mode_stack: vec![], line: 1, column: 0, index: 0, lookahead_index: -1 }
+    }
+}
+
+/// [TextEdit] replaces chars `start..old_end` of the old buffer by chars `start..new_end` of the new buffer,
+/// indexes are char indexes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextEdit {
+    pub start: isize,
+    pub old_end: isize,
+    pub new_end: isize,
+}
+
+/// [Relexed] is the result of [LineSnapshots::relex]. Tokens `old_range` of the old token buffer are replaced by
+/// `tokens`, the tokens after `old_range` are kept, with their char indexes shifted by `index_delta`
+/// and their lines shifted by `line_delta`.
+#[derive(Debug)]
+pub struct Relexed<TK> {
+    pub old_range: Range<usize>,
+    pub tokens: Vec<TK>,
+    pub index_delta: isize,
+    pub line_delta: isize,
+}
+
+/// [LineSnapshots] keeps a [LexerSnapshot] of the first token boundary on each line of a buffer, along with the
+/// number of the tokens lexed before it, so an edited buffer is relexed from the line of the edit
+/// instead of the beginning.
+#[derive(Debug, Clone, Default)]
+pub struct LineSnapshots {
+    snapshots: Vec<(Nth, LexerSnapshot)>,
+    token_count: usize,
+}
+
+impl LineSnapshots {
+    /// [LineSnapshots::lex] lexes all tokens of `lexer` till EOF, returns the snapshots and the tokens, EOF included.
+    pub fn lex<L: Lexer + ?Sized>(lexer: &mut L) -> (Self, Vec<L::TK>) {
+        let mut snapshots = Self::default();
+        let mut tokens = vec![];
+        loop {
+            snapshots.push_if_new_line(tokens.len(), lexer.snapshot());
+            let tk = lexer.next_token();
+            let eof = tk.token_type() == TOKEN_EOF;
+            tokens.push(tk);
+            if eof {
+                break;
+            }
+        }
+        snapshots.token_count = tokens.len();
+        (snapshots, tokens)
+    }
+
+    // #[inline(always)]
+    pub fn snapshots(&self) -> &[(Nth, LexerSnapshot)] {
+        &self.snapshots
+    }
+
+    /// number of tokens of the buffer, EOF included.
+    // #[inline(always)]
+    pub fn token_count(&self) -> usize {
+        self.token_count
+    }
+
+    /// [LineSnapshots::relex] relexes the buffer after `edit` with `lexer`, which lexes the new buffer.
+    /// Lexing resumes from the last snapshot whose tokens before it examined no char of the edit,
+    /// see [LexerSnapshot::lookahead_index], and stops at the first token boundary after the edit where
+    /// the lexer state matches an old snapshot, the tokens after the boundary are not changed but shifted.
+    /// The snapshots are updated for the new buffer. Empty snapshots, e.g. the default ones, relex the whole buffer.
+    pub fn relex<L: Lexer + ?Sized>(&mut self, lexer: &mut L, edit: &TextEdit) -> Relexed<L::TK> {
+        let first = self.snapshots.iter()
+            .rposition(|(_, s)| s.lookahead_index < edit.start)
+            .unwrap_or(0);
+        let (start_token, start_snapshot) = self.snapshots.get(first).cloned().unwrap_or_default();
+        lexer.restore(&start_snapshot);
+
+        let index_delta = edit.new_end - edit.old_end;
+        let mut snapshots = LineSnapshots::default();
+        let mut tokens = vec![];
+        // (nth of the old snapshot, line delta) where lexing stops.
+        let mut sync = None;
+        loop {
+            let snapshot = lexer.snapshot();
+            if snapshot.index >= edit.new_end {
+                if let Some(nth) = self.matching_snapshot(first, &snapshot, index_delta) {
+                    sync = Some((nth, snapshot.line - self.snapshots[nth].1.line));
+                    break;
+                }
+            }
+            snapshots.push_if_new_line(start_token + tokens.len(), snapshot);
+            let tk = lexer.next_token();
+            let eof = tk.token_type() == TOKEN_EOF;
+            tokens.push(tk);
+            if eof {
+                break;
+            }
+        }
+
+        let (old_end_token, line_delta) = match sync {
+            Some((nth, line_delta)) => (self.snapshots[nth].0, line_delta),
+            None => (self.token_count, 0),
+        };
+        let token_delta = tokens.len() as isize - (old_end_token - start_token) as isize;
+        let mut updated = self.snapshots[..first].to_vec();
+        updated.append(&mut snapshots.snapshots);
+        if let Some((nth, _)) = sync {
+            // the relexed tokens may look further ahead than the old ones.
+            let lookahead_index = lexer.snapshot().lookahead_index;
+            for (token, snapshot) in &self.snapshots[nth..] {
+                let shifted = LexerSnapshot {
+                    line: snapshot.line + line_delta,
+                    index: snapshot.index + index_delta,
+                    lookahead_index: lookahead_index.max(snapshot.lookahead_index + index_delta),
+                    ..snapshot.clone()
+                };
+                let nth = (*token as isize + token_delta) as usize;
+                if updated.last().is_none_or(|(_, last)| shifted.line > last.line) {
+                    updated.push((nth, shifted));
+                }
+            }
+        }
+        self.snapshots = updated;
+        self.token_count = (self.token_count as isize + token_delta) as usize;
+        Relexed { old_range: start_token..old_end_token, tokens, index_delta, line_delta }
+    }
+
+    /// find the old snapshot after `first` the lexer would resume from as `snapshot`,
+    /// if the old buffer is shifted by `index_delta`.
+    // #[inline]
+    fn matching_snapshot(&self, first: usize, snapshot: &LexerSnapshot, index_delta: isize) -> Option<usize> {
+        let old_index = snapshot.index - index_delta;
+        let nth = first + self.snapshots[first..].binary_search_by_key(&old_index, |(_, s)| s.index).ok()?;
+        let old = &self.snapshots[nth].1;
+        (old.mode == snapshot.mode && old.mode_stack == snapshot.mode_stack && old.column == snapshot.column)
+            .then_some(nth)
+    }
+
+    // #[inline(always)]
+    fn push_if_new_line(&mut self, token_nth: Nth, snapshot: LexerSnapshot) {
+        if self.snapshots.last().is_none_or(|(_, last)| snapshot.line > last.line) {
+            self.snapshots.push((token_nth, snapshot));
+        }
+    }
+}
@@ -14,15 +14,21 @@
 pub mod atn;
 pub mod atn_type;
 pub mod recognizer;
+pub mod vocabulary;
 pub mod lexer;
 pub mod input_stream;
+pub mod unbuffered_char_stream;
 pub mod tree;
 pub mod token;
 pub mod int_stream;
 pub mod token_stream;
 pub mod token_source;
+pub mod list_token_source;
+pub mod token_record;
 pub mod char_stream;
+pub mod case_changing_char_stream;
 pub mod code_point;
+pub mod encoding;
 pub mod atn_simulator;
 pub mod prediction_context;
 pub mod dfa;
@@ -35,14 +41,23 @@ pub mod parser_rule_context;
 pub mod rule_context;
 pub mod value;
 pub mod lexer_atn_simulator;
+pub mod lexer_interpreter;
+pub mod lexer_snapshot;
+pub mod text_position;
 pub mod error_listener;
 pub mod errors;
 pub mod lexer_action;
+pub mod lexer_action_executor;
 pub mod parser;
+pub mod buffered_token_stream;
 pub mod common_token_stream;
+pub mod unbuffered_token_stream;
 pub mod parser_atn_simulator;
 pub mod atn_deserializer;
 pub mod atn_deserialize_option;
+pub mod atn_serializer;
+pub mod interp_file;
+pub mod dot_generator;
 pub mod interval_set;
 pub mod transition;
 pub mod semantic_context;
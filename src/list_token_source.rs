@@ -0,0 +1,101 @@
+use crate::input_stream::StringStream;
+use crate::token::{Token, TOKEN_DEFAULT_CHANNEL, TOKEN_EOF};
+use crate::token_factory::{CommonTokenFactory, TokenFactory};
+use crate::token_source::TokenSource;
+
+const EOF_TEXT: &str = "EOF";
+
+/// [ListTokenSource] replays a list of tokens, e.g. tokens of a tokenizer other than an ANTLR lexer,
+/// or tokens replayed by [crate::token_record::read_tokens]. If the list does not end with EOF,
+/// an EOF token is created by the factory right after the last token, on the line and column where it ends.
+pub struct ListTokenSource<TF: TokenFactory = CommonTokenFactory> {
+    tokens: Vec<TF::TK>,
+    // index of the next token to return
+    i: usize,
+    eof: Option<TF::TK>,
+    factory: TF,
+}
+
+impl ListTokenSource {
+    // #[inline(always)]
+    pub fn new(tokens: Vec<<CommonTokenFactory as TokenFactory>::TK>) -> Self {
+        Self::with_factory(tokens, CommonTokenFactory::new())
+    }
+}
+
+impl<TF: TokenFactory> ListTokenSource<TF> {
+    /// creates a source replays `tokens`, the EOF token is created by `factory` if there is no EOF in `tokens`.
+    // #[inline(always)]
+    pub fn with_factory(tokens: Vec<TF::TK>, factory: TF) -> Self {
+        Self { tokens, i: 0, eof: None, factory }
+    }
+
+    // #[inline(always)]
+    pub fn tokens(&self) -> &[TF::TK] {
+        &self.tokens
+    }
+
+    /// start replaying from the first token again.
+    // #[inline(always)]
+    pub fn reset(&mut self) {
+        self.i = 0
+    }
+}
+
+impl<TF: TokenFactory> TokenSource for ListTokenSource<TF> {
+    type TK = TF::TK;
+
+    fn next_token(&mut self) -> Self::TK {
+        if let Some(tk) = self.tokens.get(self.i) {
+            if self.i + 1 == self.tokens.len() && tk.token_type() == TOKEN_EOF {
+                self.eof = Some(tk.clone());
+            }
+            self.i += 1;
+            return tk.clone();
+        }
+        if self.eof.is_none() {
+            // EOF starts right after the last token, there is no char in it.
+            let start = self.tokens.last().map_or(0, |tk| tk.stop() + 1);
+            let eof = self.factory.create(
+                &StringStream::from(""),
+                TOKEN_EOF,
+                Some(EOF_TEXT.to_string()),
+                TOKEN_DEFAULT_CHANNEL,
+                start,
+                start - 1,
+                self.line(),
+                self.char_position_in_line(),
+            );
+            self.eof = Some(eof);
+        }
+        self.eof.clone().unwrap()
+    }
+
+    /// line of the next token, or the line where the last token ends.
+    fn line(&self) -> isize {
+        if let Some(tk) = self.tokens.get(self.i).or(self.eof.as_ref()) {
+            return tk.line();
+        }
+        match self.tokens.last() {
+            Some(last) => last.line() + last.text().matches('\n').count() as isize,
+            None => 1,
+        }
+    }
+
+    /// column of the next token, or the column where the last token ends.
+    fn char_position_in_line(&self) -> isize {
+        if let Some(tk) = self.tokens.get(self.i).or(self.eof.as_ref()) {
+            return tk.column();
+        }
+        match self.tokens.last() {
+            Some(last) => {
+                let text = last.text();
+                match text.rfind('\n') {
+                    Some(i) => text[i + 1..].chars().count() as isize,
+                    None => last.column() + last.stop() - last.start() + 1,
+                }
+            }
+            None => 0,
+        }
+    }
+}
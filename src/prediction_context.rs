@@ -1,7 +1,14 @@
 use std::collections::HashMap;
+use crate::misc::murmur3::{murmur_finish, murmur_init, murmur_update, MurmurHash};
 use crate::Nth;
 use std::sync::{Arc, RwLock};
 
+/// Represents `$` in an array in full context mode, when `$` doesn't mean wildcard:
+/// `$ + x = [$,x]`. Here, `$` = [PREDICTION_CONTEXT_EMPTY_RETURN_STATE].
+pub const PREDICTION_CONTEXT_EMPTY_RETURN_STATE: Nth = usize::MAX;
+
+const PREDICTION_CONTEXT_INITIAL_HASH: u32 = 1;
+
 #[derive(Debug)]
 pub enum PredictionContext {
     Empty,
@@ -23,6 +30,101 @@ pub struct ArrayPredictionContext {
     parents: Vec<Arc<PredictionContext>>,
 }
 
+impl PredictionContext {
+    // #[inline(always)]
+    pub fn new_empty() -> Arc<Self> {
+        Arc::new(PredictionContext::Empty)
+    }
+
+    /// [PredictionContext::new_singleton] pushes `ret_state_nth` onto `parent_ctx`,
+    /// it is the context of invoking a rule which returns to `ret_state_nth`.
+    // #[inline]
+    pub fn new_singleton(parent_ctx: Arc<PredictionContext>, ret_state_nth: Nth) -> Arc<Self> {
+        if ret_state_nth == PREDICTION_CONTEXT_EMPTY_RETURN_STATE && parent_ctx.is_empty() {
+            return parent_ctx;
+        }
+        let mut h = murmur_init(PREDICTION_CONTEXT_INITIAL_HASH);
+        h = murmur_update(h, parent_ctx.murmur());
+        h = murmur_update(h, ret_state_nth as u32);
+        let cached_hash = murmur_finish(h, 2);
+        Arc::new(PredictionContext::Singleton(SingletonPredictionContext { cached_hash, ret_state_nth, parent_ctx }))
+    }
+
+    // #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        matches!(self, PredictionContext::Empty)
+    }
+
+    /// whether `$` is one of the return states of this context.
+    // #[inline]
+    pub fn has_empty_path(&self) -> bool {
+        match self {
+            PredictionContext::Empty => true,
+            PredictionContext::Singleton(_) => false,
+            PredictionContext::Array(a) => a.ret_states_nths.last() == Some(&PREDICTION_CONTEXT_EMPTY_RETURN_STATE),
+        }
+    }
+
+    /// number of return states, [PredictionContext::Empty] is treated as a single `$`.
+    // #[inline]
+    pub fn len(&self) -> usize {
+        match self {
+            PredictionContext::Empty | PredictionContext::Singleton(_) => 1,
+            PredictionContext::Array(a) => a.ret_states_nths.len(),
+        }
+    }
+
+    // #[inline]
+    pub fn return_state(&self, i: usize) -> Nth {
+        match self {
+            PredictionContext::Empty => PREDICTION_CONTEXT_EMPTY_RETURN_STATE,
+            PredictionContext::Singleton(s) => s.ret_state_nth,
+            PredictionContext::Array(a) => a.ret_states_nths[i],
+        }
+    }
+
+    // #[inline]
+    pub fn parent(&self, i: usize) -> Option<&Arc<PredictionContext>> {
+        match self {
+            PredictionContext::Empty => None,
+            PredictionContext::Singleton(s) => Some(&s.parent_ctx),
+            PredictionContext::Array(a) => a.parents.get(i),
+        }
+    }
+}
+
+impl PartialEq for PredictionContext {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (PredictionContext::Empty, PredictionContext::Empty) => true,
+            (PredictionContext::Singleton(a), PredictionContext::Singleton(b)) => {
+                a.cached_hash == b.cached_hash
+                    && a.ret_state_nth == b.ret_state_nth
+                    && (Arc::ptr_eq(&a.parent_ctx, &b.parent_ctx) || a.parent_ctx == b.parent_ctx)
+            }
+            (PredictionContext::Array(a), PredictionContext::Array(b)) => {
+                a.cached_hash == b.cached_hash
+                    && a.ret_states_nths == b.ret_states_nths
+                    && a.parents.iter().zip(&b.parents).all(|(x, y)| Arc::ptr_eq(x, y) || x == y)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Eq for PredictionContext {}
+
+impl MurmurHash for PredictionContext {
+    // #[inline]
+    fn murmur(&self) -> u32 {
+        match self {
+            PredictionContext::Empty => murmur_finish(murmur_init(PREDICTION_CONTEXT_INITIAL_HASH), 0),
+            PredictionContext::Singleton(s) => s.cached_hash,
+            PredictionContext::Array(a) => a.cached_hash,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct PredictionContextCache {
     pub(crate) cache: RwLock<HashMap<PredictionContext, PredictionContext>>,
@@ -33,4 +135,4 @@ impl PredictionContextCache {
     pub fn new() -> Self {
         Self { cache: RwLock::new(HashMap::with_capacity(32)) }
     }
-}
\ No newline at end of file
+}
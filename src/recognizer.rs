@@ -1,19 +1,41 @@
+use std::borrow::Cow;
 use std::cell::RefCell;
 use std::rc::Rc;
-use crate::error_listener::ErrorListener;
+use std::sync::Arc;
+use crate::error_listener::{ErrorListener, ProxyErrorListener};
 use crate::errors::ANTLRError;
+use crate::lexer::LexerRecognizer;
 use crate::rule_context::RuleContext;
+use crate::vocabulary::Vocabulary;
 
+/// [Names] is a name table of a grammar indexed by rule index or token type, it is cheap to clone.
+/// The names of generated code are borrowed, see [static_names], and the names loaded at runtime are owned,
+/// see [crate::interp_file::InterpFile].
+pub type Names = Arc<[Cow<'static, str>]>;
+
+/// [static_names] borrows the name table of generated code, the names are not copied.
+// #[inline]
+pub fn static_names(names: &'static [&'static str]) -> Names {
+    names.iter().map(|&n| Cow::Borrowed(n)).collect()
+}
+
+/// The name tables are slices of [Cow] so they could be owned by a grammar loaded at runtime,
+/// an implementation holding `&'static [&'static str]` tables should hold [Names] instead, see [static_names].
 pub trait Recognizer {
-    fn literal_names(&self) -> &[&str];
+    fn literal_names(&self) -> &[Cow<'static, str>];
 
-    fn symbolic_names(&self) -> &[&str];
+    fn symbolic_names(&self) -> &[Cow<'static, str>];
 
-    fn rule_names(&self) -> &[&str];
+    /// the names of token types, which should be used to name a token type in messages.
+    fn vocabulary(&self) -> &Vocabulary;
 
-    fn sempred(&self, _local_ctx: Rc<dyn RuleContext>, _rule_idx: isize, _action_idx: isize) -> bool;
+    fn rule_names(&self) -> &[Cow<'static, str>];
 
-    fn precpred(&self, _local_ctx: Rc<dyn RuleContext>, _precedence: isize) -> bool;
+    /// evaluate the semantic predicate `pred_idx` of rule `rule_idx`,
+    /// `local_ctx` is [None] when the predicate is evaluated by a lexer.
+    fn sempred(&self, _local_ctx: Option<Rc<dyn RuleContext>>, _rule_idx: isize, _pred_idx: isize) -> bool;
+
+    fn precpred(&self, _local_ctx: Option<Rc<dyn RuleContext>>, _precedence: isize) -> bool;
 
     // fn atn(&self) -> &ATN;
 
@@ -21,12 +43,16 @@ pub trait Recognizer {
 
     fn set_state(&mut self, state: isize);
 
-    fn action(&self, _local_ctx: Rc<dyn RuleContext>, _rule_idx: isize, _action_idx: isize);
+    /// execute the action `action_idx` of rule `rule_idx`,
+    /// `local_ctx` is [None] when the action is executed by a lexer.
+    fn action(&mut self, _local_ctx: Option<Rc<dyn RuleContext>>, _rule_idx: isize, _action_idx: isize);
 
     fn add_error_listener(&mut self, l: Box<dyn ErrorListener>);
 
     fn remove_error_listeners(&mut self);
 
+    /// the listener dispatches errors to all listeners added, it is shared so errors could be reported
+    /// while the recognizer is borrowed.
     fn error_listener_dispatch(&self) -> Rc<RefCell<dyn ErrorListener>>;
 
     fn has_error(&self) -> bool;
@@ -37,17 +63,19 @@ pub trait Recognizer {
 }
 
 pub struct BaseRecognizer {
-    listeners: Vec<Box<dyn ErrorListener>>,
+    listeners: Rc<RefCell<ProxyErrorListener>>,
     state: isize,
 
-    rule_names: &'static [&'static str],
-    literal_names: &'static [&'static str],
-    symbolic_names: &'static [&'static str],
+    rule_names: Names,
+    // literal and symbolic names are the tables of the vocabulary.
+    vocabulary: Arc<Vocabulary>,
     grammar_file_name: &'static str,
     syn_err: Option<ANTLRError>,
 }
 
 impl BaseRecognizer {
+    /// [BaseRecognizer::new] creates a recognizer of the name tables of generated code, a [Vocabulary] is built
+    /// for each recognizer, see [BaseRecognizer::with_vocabulary] to share one between recognizers.
     // #[inline(always)]
     pub fn new(
         rule_names: &'static [&'static str],
@@ -55,12 +83,27 @@ impl BaseRecognizer {
         symbolic_names: &'static [&'static str],
         grammar_file_name: &'static str,
     ) -> Self {
+        let vocabulary = Vocabulary::new(literal_names, symbolic_names);
+        Self::with_vocabulary(static_names(rule_names), Arc::new(vocabulary), grammar_file_name)
+    }
+
+    /// [BaseRecognizer::with_names] creates a recognizer sharing the name tables, which could be owned,
+    /// e.g. the tables of [crate::interp_file::InterpFile]. A [Vocabulary] of the token names is built.
+    pub fn with_names(rule_names: Names, literal_names: Names, symbolic_names: Names, grammar_file_name: &'static str) -> Self {
+        let vocabulary = Vocabulary::with_names(&literal_names, &symbolic_names);
+        Self::with_vocabulary(rule_names, Arc::new(vocabulary), grammar_file_name)
+    }
+
+    /// [BaseRecognizer::with_vocabulary] creates a recognizer sharing the rule names and the vocabulary,
+    /// the token names are the tables of `vocabulary`. Generated code could keep them in statics,
+    /// so nothing is built for each recognizer.
+    // #[inline(always)]
+    pub fn with_vocabulary(rule_names: Names, vocabulary: Arc<Vocabulary>, grammar_file_name: &'static str) -> Self {
         Self {
-            listeners: vec![],
+            listeners: Rc::new(RefCell::new(ProxyErrorListener::new())),
             state: -1,
             rule_names,
-            literal_names,
-            symbolic_names,
+            vocabulary,
             grammar_file_name,
             syn_err: None,
         }
@@ -68,24 +111,29 @@ impl BaseRecognizer {
 }
 
 impl Recognizer for BaseRecognizer {
-    fn literal_names(&self) -> &[&str] {
-        self.literal_names
+    fn literal_names(&self) -> &[Cow<'static, str>] {
+        self.vocabulary.literal_names()
     }
 
-    fn symbolic_names(&self) -> &[&str] {
-        self.symbolic_names
+    fn symbolic_names(&self) -> &[Cow<'static, str>] {
+        self.vocabulary.symbolic_names()
     }
 
-    fn rule_names(&self) -> &[&str] {
-        self.rule_names
+    fn vocabulary(&self) -> &Vocabulary {
+        &self.vocabulary
     }
 
-    fn sempred(&self, _local_ctx: Rc<dyn RuleContext>, _rule_idx: isize, _action_idx: isize) -> bool {
-        todo!()
+    fn rule_names(&self) -> &[Cow<'static, str>] {
+        &self.rule_names
     }
 
-    fn precpred(&self, _local_ctx: Rc<dyn RuleContext>, _precedence: isize) -> bool {
-        todo!()
+    // grammar without predicates always passes
+    fn sempred(&self, _local_ctx: Option<Rc<dyn RuleContext>>, _rule_idx: isize, _pred_idx: isize) -> bool {
+        true
+    }
+
+    fn precpred(&self, _local_ctx: Option<Rc<dyn RuleContext>>, _precedence: isize) -> bool {
+        true
     }
 
     /*fn atn(&self) -> &ATN {
@@ -93,38 +141,39 @@ impl Recognizer for BaseRecognizer {
     }*/
 
     fn state(&self) -> isize {
-        todo!()
+        self.state
     }
 
     fn set_state(&mut self, state: isize) {
-        todo!()
+        self.state = state
     }
 
-    fn action(&self, _local_ctx: Rc<dyn RuleContext>, _rule_idx: isize, _action_idx: isize) {
-        todo!()
-    }
+    // grammar without actions does nothing
+    fn action(&mut self, _local_ctx: Option<Rc<dyn RuleContext>>, _rule_idx: isize, _action_idx: isize) {}
 
     fn add_error_listener(&mut self, l: Box<dyn ErrorListener>) {
-        self.listeners.push(l)
+        self.listeners.borrow_mut().add(l)
     }
 
     fn remove_error_listeners(&mut self) {
-        todo!()
+        self.listeners.borrow_mut().clear()
     }
 
     fn error_listener_dispatch(&self) -> Rc<RefCell<dyn ErrorListener>> {
-        todo!()
+        Rc::clone(&self.listeners) as Rc<RefCell<dyn ErrorListener>>
     }
 
     fn has_error(&self) -> bool {
-        todo!()
+        self.syn_err.is_some()
     }
 
     fn error(&self) -> Option<&ANTLRError> {
-        todo!()
+        self.syn_err.as_ref()
     }
 
     fn set_error(&mut self, e: ANTLRError) {
-        todo!()
+        self.syn_err = Some(e)
     }
-}
\ No newline at end of file
+}
+// predicates and actions of a lexer are those of the recognizer
+impl LexerRecognizer for BaseRecognizer {}
@@ -1,6 +1,6 @@
 use std::fmt::{Display, Formatter};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SemanticContext {
     None,
     Predicate(PredicateSemanticContext),
@@ -38,7 +38,7 @@ pub type PrecedenceSemanticContext = isize;
 pub type ANDSemanticContext = Vec<SemanticContext>;
 pub type ORSemanticContext = Vec<SemanticContext>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PredicateSemanticContext {
     rule_index: usize,
     pred_index: usize,
@@ -0,0 +1,63 @@
+use crate::code_point::CodePoints;
+use crate::token::Token;
+
+/// [TextPosition] is a position in UTF-8 text, with its column counted in each unit a client may use,
+/// e.g. LSP counts UTF-16 code units by default. Lines and columns are 0-based.
+///
+/// `code_point_column` is the column of a token lexed with the default tab width,
+/// see [crate::lexer_atn_simulator::LexerATNSimulator::tab_width].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct TextPosition {
+    pub byte_offset: usize,
+    pub line: usize,
+    pub utf8_column: usize,
+    pub utf16_column: usize,
+    pub code_point_column: usize,
+}
+
+impl TextPosition {
+    /// position of byte `offset` in `text`, the text before it is scanned for lines.
+    /// Panics if `offset` is not at a char boundary.
+    pub fn at_byte(text: &str, offset: usize) -> Self {
+        let line = text.as_bytes()[..offset].iter().filter(|&&b| b == b'\n').count();
+        Self::on_line(text, line, offset)
+    }
+
+    /// start and end positions of `token` in `text`, the input `token` is lexed from.
+    /// The end is the position right after the last char of `token`.
+    /// Only the lines of `token` are scanned if the byte range of `token` is known, otherwise its char
+    /// indexes are converted by scanning the text before it. Returns [None] if `token` is out of `text`.
+    pub fn of_token<T: Token + ?Sized>(text: &str, token: &T) -> Option<(Self, Self)> {
+        let range = match token.byte_range() {
+            Some(range) => range,
+            None => {
+                let start = usize::try_from(token.start()).ok()?;
+                let end = usize::try_from(token.stop() + 1).ok()?.max(start);
+                text.byte_offset(start)?..text.byte_offset(end)?
+            }
+        };
+        if range.start > range.end || text.get(range.clone()).is_none() {
+            return None;
+        }
+        let start = match usize::try_from(token.line() - 1) {
+            Ok(line) => Self::on_line(text, line, range.start),
+            Err(_) => Self::at_byte(text, range.start),
+        };
+        let lines = text.as_bytes()[range.clone()].iter().filter(|&&b| b == b'\n').count();
+        Some((start, Self::on_line(text, start.line + lines, range.end)))
+    }
+
+    /// position of byte `offset` which is on `line`, only the line before `offset` is scanned.
+    // #[inline]
+    fn on_line(text: &str, line: usize, offset: usize) -> Self {
+        let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let prefix = &text[line_start..offset];
+        Self {
+            byte_offset: offset,
+            line,
+            utf8_column: prefix.len(),
+            utf16_column: prefix.encode_utf16().count(),
+            code_point_column: prefix.chars().count(),
+        }
+    }
+}
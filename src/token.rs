@@ -1,7 +1,10 @@
 use std::borrow::Cow;
 use std::fmt::{Debug, Display, Formatter};
+use std::ops::Range;
+use std::sync::Arc;
 use std::sync::atomic::{AtomicIsize, Ordering};
 use crate::int_stream;
+use crate::vocabulary::Vocabulary;
 
 /// A token has properties: text, type, line, character position in the line
 ///  (so we can ignore tabs), token channel, index, and source from which
@@ -39,6 +42,10 @@ pub trait Token: Debug + Display {
 
     fn column(&self) -> isize { 0 }
 
+    /// The byte range of the token in the UTF-8 text of the input, so the text could be sliced
+    /// without rescanning, [None] if the input does not know byte offsets, see [crate::char_stream::CharStream::byte_offset].
+    fn byte_range(&self) -> Option<Range<usize>> { None }
+
     /// Get the text of the token.
     fn text(&self) -> Cow<'_, str>;
 
@@ -63,6 +70,7 @@ pub struct BaseToken {
     column: isize,
     text: String,
     read_only: bool,
+    byte_range: Option<Range<usize>>,
 }
 
 impl BaseToken {
@@ -88,22 +96,100 @@ impl BaseToken {
             column,
             text,
             read_only,
+            byte_range: None,
         }
     }
+
+    /// set the byte range of the token, see [Token::byte_range].
+    // #[inline(always)]
+    pub fn with_byte_range(mut self, byte_range: Option<Range<usize>>) -> Self {
+        self.byte_range = byte_range;
+        self
+    }
 }
 
+/// [Display] of a token is `[@index,start:stop='text',<type>,line:column]` of ANTLR, `\n`, `\r` and `\t` of text
+/// are escaped, and `,channel=N` follows the type if the token is not on the default channel.
 impl Display for BaseToken {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        todo!()
+        write_token(f, self, &self.token_type)
+    }
+}
+
+impl BaseToken {
+    /// display the token with the name of its type in `vocabulary`, e.g. `<ID>` instead of `<3>`, see [TokenDisplay].
+    // #[inline(always)]
+    pub fn display<'a>(&'a self, vocabulary: &'a Vocabulary) -> TokenDisplay<'a, Self> {
+        TokenDisplay::new(self, vocabulary)
+    }
+}
+
+/// [TokenDisplay] displays a token like [BaseToken] does, but the type is displayed as its display name
+/// in the vocabulary, like `<'{'>` or `<ID>`, see [Vocabulary::display_name].
+pub struct TokenDisplay<'a, T: Token + ?Sized> {
+    token: &'a T,
+    vocabulary: &'a Vocabulary,
+}
+
+impl<'a, T: Token + ?Sized> TokenDisplay<'a, T> {
+    // #[inline(always)]
+    pub fn new(token: &'a T, vocabulary: &'a Vocabulary) -> Self {
+        Self { token, vocabulary }
+    }
+}
+
+impl<T: Token + ?Sized> Display for TokenDisplay<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write_token(f, self.token, &self.vocabulary.display_name(self.token.token_type()))
     }
 }
 
+// #[inline]
+fn write_token<T: Token + ?Sized>(f: &mut Formatter<'_>, token: &T, type_name: &dyn Display) -> std::fmt::Result {
+    let text = token.text().replace('\n', "\\n").replace('\r', "\\r").replace('\t', "\\t");
+    f.write_fmt(format_args!("[@{},{}:{}='{}',<{}>", token.token_index(), token.start(), token.stop(), text, type_name))?;
+    if token.channel() != TOKEN_DEFAULT_CHANNEL {
+        f.write_fmt(format_args!(",channel={}", token.channel()))?;
+    }
+    f.write_fmt(format_args!(",{}:{}]", token.line(), token.column()))
+}
+
 impl Token for BaseToken {
     // #[inline]
     fn token_type(&self) -> isize {
         self.token_type
     }
 
+    // #[inline]
+    fn channel(&self) -> isize {
+        self.channel
+    }
+
+    // #[inline]
+    fn start(&self) -> isize {
+        self.start
+    }
+
+    // #[inline]
+    fn stop(&self) -> isize {
+        self.stop
+    }
+
+    // #[inline]
+    fn line(&self) -> isize {
+        self.line
+    }
+
+    // #[inline]
+    fn column(&self) -> isize {
+        self.column
+    }
+
+    // #[inline]
+    fn byte_range(&self) -> Option<Range<usize>> {
+        self.byte_range.clone()
+    }
+
     // #[inline]
     fn text(&self) -> Cow<'_, str> {
         return if self.token_type == TOKEN_EOF {
@@ -130,12 +216,169 @@ impl Clone for BaseToken {
             token_type: self.token_type,
             channel: self.channel,
             start: self.start,
-            stop: self.start,
+            stop: self.stop,
             token_index: AtomicIsize::new(self.token_index()),
             line: self.line,
             column: self.column,
             text: self.text.clone(),
             read_only: self.read_only,
+            byte_range: self.byte_range.clone(),
         }
     }
-}
\ No newline at end of file
+}
+
+/// [SpanToken] is a token whose text is a span of a shared source, so creating it copies no text,
+/// see [crate::token_factory::SpanTokenFactory]. The text is owned only when it is not a span of the source,
+/// e.g. it is set by a lexer action.
+#[derive(Debug)]
+pub struct SpanToken {
+    token_type: isize,
+    channel: isize,
+    start: isize,
+    stop: isize,
+    token_index: AtomicIsize,
+    line: isize,
+    column: isize,
+    source: Arc<str>,
+    // the text of the token if it is not the span of `byte_range` in `source`.
+    text: Option<String>,
+    byte_range: Option<Range<usize>>,
+}
+
+impl SpanToken {
+    /// creates a token of the text `text`, or the span of its byte range in `source` if `text` is [None],
+    /// see [SpanToken::with_byte_range].
+    #[allow(clippy::too_many_arguments)]
+    // #[inline(always)]
+    pub fn new(
+        token_type: isize,
+        channel: isize,
+        start: isize,
+        stop: isize,
+        token_index: AtomicIsize,
+        line: isize,
+        column: isize,
+        source: Arc<str>,
+        text: Option<String>,
+    ) -> Self {
+        Self {
+            token_type,
+            channel,
+            start,
+            stop,
+            token_index,
+            line,
+            column,
+            source,
+            text,
+            byte_range: None,
+        }
+    }
+
+    /// set the byte range of the token in `source`, see [Token::byte_range].
+    // #[inline(always)]
+    pub fn with_byte_range(mut self, byte_range: Option<Range<usize>>) -> Self {
+        self.byte_range = byte_range;
+        self
+    }
+
+    /// the source the text of the token is a span of.
+    // #[inline(always)]
+    pub fn source(&self) -> &Arc<str> {
+        &self.source
+    }
+
+    /// whether the text of the token is owned instead of a span of the source.
+    // #[inline(always)]
+    pub fn is_text_owned(&self) -> bool {
+        self.text.is_some()
+    }
+
+    /// display the token with the name of its type in `vocabulary`, see [TokenDisplay].
+    // #[inline(always)]
+    pub fn display<'a>(&'a self, vocabulary: &'a Vocabulary) -> TokenDisplay<'a, Self> {
+        TokenDisplay::new(self, vocabulary)
+    }
+}
+
+/// see [Display] of [BaseToken].
+impl Display for SpanToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write_token(f, self, &self.token_type)
+    }
+}
+
+impl Token for SpanToken {
+    // #[inline]
+    fn token_type(&self) -> isize {
+        self.token_type
+    }
+
+    // #[inline]
+    fn channel(&self) -> isize {
+        self.channel
+    }
+
+    // #[inline]
+    fn start(&self) -> isize {
+        self.start
+    }
+
+    // #[inline]
+    fn stop(&self) -> isize {
+        self.stop
+    }
+
+    // #[inline]
+    fn line(&self) -> isize {
+        self.line
+    }
+
+    // #[inline]
+    fn column(&self) -> isize {
+        self.column
+    }
+
+    // #[inline]
+    fn byte_range(&self) -> Option<Range<usize>> {
+        self.byte_range.clone()
+    }
+
+    // #[inline]
+    fn text(&self) -> Cow<'_, str> {
+        if self.token_type == TOKEN_EOF {
+            return Cow::Borrowed(TEXT_EOF);
+        }
+        match &self.text {
+            Some(text) => Cow::Borrowed(text),
+            None => Cow::Borrowed(self.byte_range.clone().and_then(|r| self.source.get(r)).unwrap_or("")),
+        }
+    }
+
+    // #[inline]
+    fn token_index(&self) -> isize {
+        self.token_index.load(Ordering::Relaxed)
+    }
+
+    // #[inline]
+    fn set_token_index(&self, idx: isize) {
+        self.token_index.store(idx, Ordering::Relaxed)
+    }
+}
+
+impl Clone for SpanToken {
+    fn clone(&self) -> Self {
+        Self {
+            token_type: self.token_type,
+            channel: self.channel,
+            start: self.start,
+            stop: self.stop,
+            token_index: AtomicIsize::new(self.token_index()),
+            line: self.line,
+            column: self.column,
+            source: Arc::clone(&self.source),
+            text: self.text.clone(),
+            byte_range: self.byte_range.clone(),
+        }
+    }
+}
@@ -1,6 +1,8 @@
+use std::ptr;
+use std::sync::Arc;
 use std::sync::atomic::AtomicIsize;
 use crate::char_stream::CharStream;
-use crate::token::{BaseToken, Token};
+use crate::token::{BaseToken, SpanToken, Token};
 
 pub trait TokenFactory {
     type TK: Token + Clone + ?Sized;
@@ -53,10 +55,69 @@ impl TokenFactory for CommonTokenFactory {
             AtomicIsize::new(-1),
             line,
             column,
-            if let Some(t) = text { t } else {
-                stream.text(start as usize, stop as usize).into_owned()
+            match text {
+                Some(t) => t,
+                // EOF or an empty token matches no text
+                None if stop < start => String::new(),
+                None => stream.text(start as usize, stop as usize).into_owned(),
             },
             false,
-        )
+        ).with_byte_range(stream.byte_offset(start).zip(stream.byte_offset(stop + 1)).map(|(start, end)| start..end))
     }
-}
\ No newline at end of file
+}
+
+/// [SpanTokenFactory] creates [SpanToken]s of `source`, which should be the text of the input stream, e.g.
+/// `SharedStringStream::from(Arc::clone(&source))`. Text is a span of `source` located by the byte offsets
+/// of the stream, it is copied if it is set explicitly, or the text of the stream is not `source` itself,
+/// see [CharStream::utf8_text].
+pub struct SpanTokenFactory {
+    source: Arc<str>,
+}
+
+impl SpanTokenFactory {
+    // #[inline(always)]
+    pub fn new(source: Arc<str>) -> Self { Self { source } }
+
+    // #[inline(always)]
+    pub fn source(&self) -> &Arc<str> {
+        &self.source
+    }
+}
+
+impl TokenFactory for SpanTokenFactory {
+    type TK = SpanToken;
+
+    // #[inline]
+    fn create<S>(
+        &self,
+        stream: &S,
+        token_type: isize,
+        text: Option<String>,
+        channel: isize,
+        start: isize,
+        stop: isize,
+        line: isize,
+        column: isize,
+    ) -> Self::TK where S: CharStream {
+        let byte_range = stream.byte_offset(start).zip(stream.byte_offset(stop + 1)).map(|(start, end)| start..end);
+        // a span of `source` is the text only if the stream is `source`, not a copy or another text.
+        let is_source = byte_range.is_some() && stream.utf8_text().is_some_and(|t| ptr::eq(t, &*self.source));
+        let text = match text {
+            Some(t) => Some(t),
+            None if is_source => None,
+            None if stop < start => Some(String::new()),
+            None => Some(stream.text(start as usize, stop as usize).into_owned()),
+        };
+        SpanToken::new(
+            token_type,
+            channel,
+            start,
+            stop,
+            AtomicIsize::new(-1),
+            line,
+            column,
+            Arc::clone(&self.source),
+            text,
+        ).with_byte_range(byte_range)
+    }
+}
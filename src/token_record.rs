@@ -0,0 +1,163 @@
+use std::io::{self, ErrorKind, Read, Write};
+use std::sync::atomic::AtomicIsize;
+use crate::token::{BaseToken, Token, TOKEN_EOF};
+use crate::token_source::TokenSource;
+
+/// the last byte is the version of the format.
+const MAGIC: &[u8; 8] = b"ANTLRTK\x01";
+
+/// [TokenRecorder] writes tokens in a compact binary format, which is read back by [read_tokens], so a token
+/// stream could be replayed without the lexer, see [crate::list_token_source::ListTokenSource].
+///
+/// A token is recorded as its type, channel, start, stop, line, column and text, as varints relative to
+/// the previous token where it helps. The token index and byte range are not recorded.
+pub struct TokenRecorder<W: Write> {
+    writer: W,
+    // where the previous token ends, and its line
+    prev_end: isize,
+    prev_line: isize,
+}
+
+impl<W: Write> TokenRecorder<W> {
+    /// creates a recorder writes into `writer`, the header is written at once.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        Ok(Self { writer, prev_end: 0, prev_line: 0 })
+    }
+
+    pub fn record<T: Token + ?Sized>(&mut self, token: &T) -> io::Result<()> {
+        let text = if token.token_type() == TOKEN_EOF { Default::default() } else { token.text() };
+        write_varint(&mut self.writer, zigzag(token.token_type()))?;
+        write_varint(&mut self.writer, zigzag(token.channel()))?;
+        write_varint(&mut self.writer, zigzag(token.start() - self.prev_end))?;
+        write_varint(&mut self.writer, zigzag(token.stop() - token.start()))?;
+        write_varint(&mut self.writer, zigzag(token.line() - self.prev_line))?;
+        write_varint(&mut self.writer, zigzag(token.column()))?;
+        write_varint(&mut self.writer, text.len() as u64)?;
+        self.writer.write_all(text.as_bytes())?;
+        self.prev_end = token.stop() + 1;
+        self.prev_line = token.line();
+        Ok(())
+    }
+
+    /// record the tokens of `source` till EOF, returns the tokens recorded, EOF included.
+    pub fn record_all<S: TokenSource>(&mut self, source: &mut S) -> io::Result<Vec<S::TK>> {
+        let mut tokens = vec![];
+        for tk in source.tokens() {
+            self.record(&tk)?;
+            tokens.push(tk);
+        }
+        Ok(tokens)
+    }
+
+    /// flush and give back the writer.
+    // #[inline]
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// [read_tokens] reads the tokens recorded by [TokenRecorder] from `reader`.
+/// Returns [ErrorKind::InvalidData] if it is not a recording, or [ErrorKind::UnexpectedEof] if it is truncated.
+pub fn read_tokens<R: Read>(mut reader: R) -> io::Result<Vec<BaseToken>> {
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("unknown header, it is not a recording or of another version"));
+    }
+    let mut tokens = vec![];
+    let (mut prev_end, mut prev_line) = (0, 0);
+    // a recording ends where a token could start.
+    while let Some(token_type) = read_varint(&mut reader, true)? {
+        // the fields after the type are never [None].
+        let mut next = || read_varint(&mut reader, false).map(Option::unwrap_or_default);
+        let token_type = unzigzag(token_type);
+        let channel = unzigzag(next()?);
+        let start = add_delta(prev_end, next()?)?;
+        let stop = add_delta(start, next()?)?;
+        let line = add_delta(prev_line, next()?)?;
+        let column = unzigzag(next()?);
+        let len = next()?;
+        // the length is not trusted, the text grows as it is read.
+        let mut text = vec![];
+        reader.by_ref().take(len).read_to_end(&mut text)?;
+        if text.len() as u64 != len {
+            return Err(io::Error::new(ErrorKind::UnexpectedEof, "token recording is truncated"));
+        }
+        let text = String::from_utf8(text).map_err(|_| invalid_data("text is not UTF-8"))?;
+        tokens.push(BaseToken::new(token_type, channel, start, stop, AtomicIsize::new(-1), line, column, text, false));
+        (prev_end, prev_line) = (add_delta(stop, zigzag(1))?, line);
+    }
+    Ok(tokens)
+}
+
+// #[inline(always)]
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, format!("invalid token recording: {}", msg))
+}
+
+/// add a delta read from a recording to `base`, a forged delta could overflow.
+// #[inline(always)]
+fn add_delta(base: isize, delta: u64) -> io::Result<isize> {
+    base.checked_add(unzigzag(delta)).ok_or_else(|| invalid_data("position overflows"))
+}
+
+// #[inline(always)]
+fn zigzag(v: isize) -> u64 {
+    ((v << 1) ^ (v >> (isize::BITS - 1))) as u64
+}
+
+// #[inline(always)]
+fn unzigzag(v: u64) -> isize {
+    ((v >> 1) as isize) ^ -((v & 1) as isize)
+}
+
+// #[inline]
+fn write_varint<W: Write>(writer: &mut W, mut v: u64) -> io::Result<()> {
+    let mut buf = [0u8; 10];
+    let mut n = 0;
+    loop {
+        let byte = (v & 0x7F) as u8;
+        v >>= 7;
+        if v == 0 {
+            buf[n] = byte;
+            n += 1;
+            break;
+        }
+        buf[n] = byte | 0x80;
+        n += 1;
+    }
+    writer.write_all(&buf[..n])
+}
+
+/// read a varint, [None] is returned if the reader ends before it and `eof_ok`.
+// #[inline]
+fn read_varint<R: Read>(reader: &mut R, eof_ok: bool) -> io::Result<Option<u64>> {
+    let mut v = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = match read_byte(reader)? {
+            None if shift == 0 && eof_ok => return Ok(None),
+            None => return Err(io::Error::new(ErrorKind::UnexpectedEof, "token recording is truncated")),
+            Some(byte) => byte,
+        };
+        v |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(Some(v));
+        }
+    }
+    Err(invalid_data("varint is too long"))
+}
+
+// #[inline]
+fn read_byte<R: Read>(reader: &mut R) -> io::Result<Option<u8>> {
+    let mut byte = [0u8; 1];
+    loop {
+        match reader.read(&mut byte) {
+            Ok(0) => return Ok(None),
+            Ok(_) => return Ok(Some(byte[0])),
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
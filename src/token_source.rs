@@ -1,4 +1,8 @@
-use crate::token::Token;
+use std::borrow::Cow;
+use std::iter::FusedIterator;
+use crate::token::{Token, TOKEN_EOF};
+
+const TOKEN_SOURCE_NAME_UNKNOWN: &str = "<unknown>";
 
 /// A source of tokens must provide a sequence of tokens via `next_token()`
 //  and also must reveal it's source of characters; {@link CommonToken}'s text is
@@ -24,4 +28,80 @@ pub trait TokenSource {
     fn char_position_in_line(&self) -> isize;
 
     // fn input_stream(&self) -> &Self::CS;
+
+    /// name of the source the tokens come from, e.g. the source name of the input of a lexer.
+    // #[inline(always)]
+    fn source_name(&self) -> Cow<'_, str> {
+        Cow::Borrowed(TOKEN_SOURCE_NAME_UNKNOWN)
+    }
+
+    /// iterate the tokens till EOF, the EOF token is the last one.
+    // #[inline(always)]
+    fn tokens(&mut self) -> Tokens<'_, Self> where Self: Sized {
+        Tokens { source: self, done: false }
+    }
+
+    /// [TokenSource::tokens] but takes the ownership of this source.
+    // #[inline(always)]
+    fn into_tokens(self) -> IntoTokens<Self> where Self: Sized {
+        IntoTokens { source: self, done: false }
+    }
+
+    /// collect the tokens till EOF, the EOF token is the last one.
+    // #[inline(always)]
+    fn all_tokens(&mut self) -> Vec<Self::TK> where Self: Sized {
+        self.tokens().collect()
+    }
+}
+
+/// [Tokens] iterates the tokens of a [TokenSource] till EOF, see [TokenSource::tokens].
+pub struct Tokens<'a, S: TokenSource> {
+    source: &'a mut S,
+    done: bool,
+}
+
+impl<S: TokenSource> Iterator for Tokens<'_, S> {
+    type Item = S::TK;
+
+    // #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        next_until_eof(self.source, &mut self.done)
+    }
+}
+
+impl<S: TokenSource> FusedIterator for Tokens<'_, S> {}
+
+/// [IntoTokens] iterates the tokens of an owned [TokenSource] till EOF, see [TokenSource::into_tokens].
+pub struct IntoTokens<S: TokenSource> {
+    source: S,
+    done: bool,
+}
+
+impl<S: TokenSource> IntoTokens<S> {
+    /// give back the source.
+    // #[inline(always)]
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<S: TokenSource> Iterator for IntoTokens<S> {
+    type Item = S::TK;
+
+    // #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        next_until_eof(&mut self.source, &mut self.done)
+    }
+}
+
+impl<S: TokenSource> FusedIterator for IntoTokens<S> {}
+
+// #[inline(always)]
+fn next_until_eof<S: TokenSource>(source: &mut S, done: &mut bool) -> Option<S::TK> {
+    if *done {
+        return None;
+    }
+    let tk = source.next_token();
+    *done = tk.token_type() == TOKEN_EOF;
+    Some(tk)
 }
\ No newline at end of file
@@ -1,16 +1,32 @@
-use std::borrow::Cow;
 use std::rc::Rc;
 use crate::int_stream::IntStream;
+use crate::interval_set::Interval;
 use crate::token::Token;
+use crate::tree::SyntaxTree;
 
+/// [TokenStream] is a stream of tokens for parsers, [IntStream::la] gives the types of the tokens.
 pub trait TokenStream: IntStream {
     type TK: Token + Clone + ?Sized;
 
-    fn lt(&self) -> Rc<Self::TK>;
+    /// Get the token at offset `k` from the current position like [IntStream::la], `lt(1)` is the current token
+    /// and `lt(-1)` is the previous one. EOF is returned at or beyond the end of the stream.
+    /// [None] is returned if `k` is 0, or `k` is before the first token.
+    fn lt(&mut self, k: isize) -> Option<Rc<Self::TK>>;
 
+    /// Get the token at index `idx` of the stream.
+    /// Panics if the token is not fetched into the buffer, or it is discarded by an unbuffered stream.
     fn token_at(&self, idx: usize) -> Rc<Self::TK>;
 
-    fn all_text(&self) -> Cow<'_, str>;
+    /// Get the text of the tokens in the index interval `start..=stop`, the text of EOF is not included.
+    /// An interval beyond the end of the stream is clamped, and a negative index gives an empty text.
+    fn text(&mut self, interval: Interval) -> String;
 
+    /// Get the text of the tokens `ctx` is matched from, see [TokenStream::text].
+    // #[inline]
+    fn text_of(&mut self, ctx: &dyn SyntaxTree) -> String {
+        self.text(Interval::new(ctx.source_start(), ctx.source_end()))
+    }
 
-}
\ No newline at end of file
+    /// Get the text of all the tokens of the stream, the tokens are fetched till EOF.
+    fn all_text(&mut self) -> String;
+}
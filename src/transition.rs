@@ -1,20 +1,21 @@
+use std::fmt::{Display, Formatter};
 use crate::interval_set::IntervalSet;
 use crate::Nth;
 use crate::token::TOKEN_EOF;
 
-pub(crate) type TransitionType = i32;
+pub type TransitionType = i32;
 
 // transition types
-const TRANSITION_EPSILON: TransitionType = 1;
-const TRANSITION_RANGE: TransitionType = 2;
-const TRANSITION_RULE: TransitionType = 3;
-const TRANSITION_PREDICATE: TransitionType = 4;
-const TRANSITION_ATOM: TransitionType = 5;
-const TRANSITION_ACTION: TransitionType = 6;
-const TRANSITION_SET: TransitionType = 7;
-const TRANSITION_NOT_SET: TransitionType = 8;
-const TRANSITION_WILDCARD: TransitionType = 9;
-const TRANSITION_PRECEDENCE: TransitionType = 10;
+pub(crate) const TRANSITION_EPSILON: TransitionType = 1;
+pub(crate) const TRANSITION_RANGE: TransitionType = 2;
+pub(crate) const TRANSITION_RULE: TransitionType = 3;
+pub(crate) const TRANSITION_PREDICATE: TransitionType = 4;
+pub(crate) const TRANSITION_ATOM: TransitionType = 5;
+pub(crate) const TRANSITION_ACTION: TransitionType = 6;
+pub(crate) const TRANSITION_SET: TransitionType = 7;
+pub(crate) const TRANSITION_NOT_SET: TransitionType = 8;
+pub(crate) const TRANSITION_WILDCARD: TransitionType = 9;
+pub(crate) const TRANSITION_PRECEDENCE: TransitionType = 10;
 
 #[derive(Debug)]
 pub struct BaseTransition {
@@ -75,6 +76,25 @@ impl Transition {
         }
     }
 
+    /// returns whether `ttype` is one of the serialized transition types.
+    // #[inline(always)]
+    pub fn is_valid_type(ttype: TransitionType) -> bool {
+        (TRANSITION_EPSILON..=TRANSITION_PRECEDENCE).contains(&ttype)
+    }
+
+    /// returns whether this is a set or not-set transition, which refer to an interval set by index
+    /// when serialized.
+    // #[inline(always)]
+    pub(crate) fn is_set_type(ttype: TransitionType) -> bool {
+        ttype == TRANSITION_SET || ttype == TRANSITION_NOT_SET
+    }
+
+    /// returns whether this is a rule transition, which serializes the rule start state in `arg1`.
+    // #[inline(always)]
+    pub(crate) fn is_rule_type(ttype: TransitionType) -> bool {
+        ttype == TRANSITION_RULE
+    }
+
     // #[inline(always)]
     pub fn target_nth(&self) -> usize {
         match self {
@@ -91,6 +111,54 @@ impl Transition {
         }
     }
 
+    // #[inline(always)]
+    pub fn transition_type(&self) -> TransitionType {
+        match self {
+            Transition::Epsilon(e) => e.base.trans_type,
+            Transition::Range(r) => r.base.trans_type,
+            Transition::Rule(r) => r.base.trans_type,
+            Transition::Predicate(p) => p.base.trans_type,
+            Transition::Atom(a) => a.base.trans_type,
+            Transition::Action(a) => a.base.trans_type,
+            Transition::Set(s) => s.base.trans_type,
+            Transition::NotSet(ns) => ns.base.trans_type,
+            Transition::Wildcard(w) => w.base.trans_type,
+            Transition::Precedence(p) => p.base.trans_type,
+        }
+    }
+
+    // #[inline(always)]
+    pub(crate) fn set_target_nth(&mut self, nth: Nth) {
+        match self {
+            Transition::Epsilon(e) => e.base.target_nth = nth,
+            Transition::Range(r) => r.base.target_nth = nth,
+            Transition::Rule(r) => r.base.target_nth = nth,
+            Transition::Predicate(p) => p.base.target_nth = nth,
+            Transition::Atom(a) => a.base.target_nth = nth,
+            Transition::Action(a) => a.base.target_nth = nth,
+            Transition::Set(s) => s.base.target_nth = nth,
+            Transition::NotSet(ns) => ns.base.target_nth = nth,
+            Transition::Wildcard(w) => w.base.target_nth = nth,
+            Transition::Precedence(p) => p.base.target_nth = nth,
+        }
+    }
+
+    /// whether `symbol` matches this transition, `min_vocab` and `max_vocab` bound the symbols for
+    /// [Transition::NotSet] and [Transition::Wildcard]. Epsilon transitions match nothing.
+    // #[inline]
+    pub fn matches(&self, symbol: isize, min_vocab: isize, max_vocab: isize) -> bool {
+        match self {
+            Transition::Atom(a) => a.label == symbol,
+            Transition::Range(r) => r.start <= symbol && symbol <= r.stop,
+            Transition::Set(s) => s.interval_set.contains(symbol),
+            Transition::NotSet(ns) => {
+                symbol >= min_vocab && symbol <= max_vocab && !ns.interval_set.contains(symbol)
+            }
+            Transition::Wildcard(_) => symbol >= min_vocab && symbol <= max_vocab,
+            _ => false,
+        }
+    }
+
     // #[inline(always)]
     pub fn is_epsilon(&self) -> bool {
         match self {
@@ -131,7 +199,7 @@ impl Transition {
     }
 
     // #[inline(always)]
-    fn new_atom(trg: Nth, label: isize) -> Self {
+    pub(crate) fn new_atom(trg: Nth, label: isize) -> Self {
         let base = BaseTransition::new(trg, TRANSITION_ATOM);
         Transition::Atom(AtomTransition { base, label })
     }
@@ -167,6 +235,34 @@ impl Transition {
     }
 }
 
+/// [Transition] is displayed in the same form as `toString` of ANTLR reference runtime,
+/// such as `epsilon`, `'a'..'z'`, `pred_1:0`, `~{1, 3..5}`.
+/// The reference runtime has no form for rule transition, it is displayed as `rule_{rule index}:{precedence}` here.
+impl Display for Transition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Transition::Epsilon(_) => f.write_str("epsilon"),
+            Transition::Range(r) => {
+                let c = |v: isize| char::from_u32(v as u32).map_or(v.to_string(), |c| c.to_string());
+                f.write_fmt(format_args!("'{}'..'{}'", c(r.start), c(r.stop)))
+            }
+            Transition::Rule(r) => f.write_fmt(format_args!("rule_{}:{}", r.rule_idx, r.precedence)),
+            Transition::Predicate(p) => f.write_fmt(format_args!("pred_{}:{}", p.rule_idx, p.pre_idx)),
+            Transition::Atom(a) => {
+                // formatted as a set of the label, so EOF is `<EOF>`.
+                let mut label = IntervalSet::new();
+                label.add_one(a.label);
+                f.write_fmt(format_args!("{}", label))
+            }
+            Transition::Action(a) => f.write_fmt(format_args!("action_{}:{}", a.rule_idx, a.action_idx)),
+            Transition::Set(s) => f.write_fmt(format_args!("{}", s.interval_set)),
+            Transition::NotSet(ns) => f.write_fmt(format_args!("~{}", ns.interval_set)),
+            Transition::Wildcard(_) => f.write_str("."),
+            Transition::Precedence(p) => f.write_fmt(format_args!("{} >= _p", p.precedence)),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct EpsilonTransition {
     pub(crate) base: BaseTransition,
@@ -184,30 +280,37 @@ pub struct RangeTransition {
 pub struct RuleTransition {
     pub(crate) base: BaseTransition,
     pub(crate) follow_state_nth: Nth,
-    rule_idx: isize,
+    pub(crate) rule_idx: isize,
     pub(crate) precedence: isize,
 }
 
 #[derive(Debug)]
 pub struct PredicateTransition {
     pub(crate) base: BaseTransition,
-    ctx_dependent: bool,
-    rule_idx: isize,
-    pre_idx: isize,
+    pub(crate) ctx_dependent: bool,
+    pub(crate) rule_idx: isize,
+    pub(crate) pre_idx: isize,
 }
 
 #[derive(Debug)]
 pub struct AtomTransition {
     pub(crate) base: BaseTransition,
-    label: isize,
+    pub(crate) label: isize,
+}
+
+impl AtomTransition {
+    // #[inline(always)]
+    pub fn label(&self) -> isize {
+        self.label
+    }
 }
 
 #[derive(Debug)]
 pub struct ActionTransition {
     pub(crate) base: BaseTransition,
-    ctx_dependent: bool,
-    rule_idx: isize,
-    action_idx: isize,
+    pub(crate) ctx_dependent: bool,
+    pub(crate) rule_idx: isize,
+    pub(crate) action_idx: isize,
 }
 
 #[derive(Debug)]
@@ -0,0 +1,263 @@
+use std::borrow::Cow;
+use std::char::REPLACEMENT_CHARACTER;
+use std::io::{ErrorKind, Read};
+use crate::char_stream::CharStream;
+use crate::errors::UnbufferedTextError;
+use crate::int_stream::{EOF, IntStream};
+
+const UNBUFFERED_CHAR_STREAM_SOURCE_NAME: &str = "<unknown>";
+const READ_CHUNK_SIZE: usize = 8192;
+
+/// [UnbufferedCharStream] decodes UTF-8 from a [Read] incrementally, invalid UTF-8 is decoded as U+FFFD.
+/// Only the chars pinned by outstanding marks(see [IntStream::mark]) are kept in the buffer, so the whole
+/// input never needs to be in memory. [IntStream::seek] and [CharStream::text] only work within the buffer,
+/// and [IntStream::size] is unknown.
+///
+/// An I/O error of the reader ends the stream as EOF, it is kept and could be taken by
+/// [UnbufferedCharStream::take_io_error].
+pub struct UnbufferedCharStream<R: Read> {
+    reader: R,
+    // bytes read but not decoded yet are bytes[byte_pos..]
+    bytes: Vec<u8>,
+    byte_pos: usize,
+    reader_eof: bool,
+    io_error: Option<std::io::Error>,
+
+    // chars of the buffer, the current char is data[p]
+    data: Vec<char>,
+    p: usize,
+    // whether all chars are decoded into data
+    eof: bool,
+    num_markers: usize,
+    // the char before the current one, for la(-1)
+    last_char: isize,
+    // the char before data[0], which is the last char when p == 0
+    last_char_buffer_start: isize,
+    // absolute index of the current char
+    current_char_index: isize,
+    source_name: String,
+}
+
+impl<R: Read> UnbufferedCharStream<R> {
+    // #[inline]
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            bytes: vec![],
+            byte_pos: 0,
+            reader_eof: false,
+            io_error: None,
+            data: vec![],
+            p: 0,
+            eof: false,
+            num_markers: 0,
+            last_char: EOF,
+            last_char_buffer_start: EOF,
+            current_char_index: 0,
+            source_name: UNBUFFERED_CHAR_STREAM_SOURCE_NAME.to_string(),
+        }
+    }
+
+    // #[inline(always)]
+    pub fn set_source_name(&mut self, source_name: impl Into<String>) {
+        self.source_name = source_name.into()
+    }
+
+    /// absolute index of the first char in the buffer.
+    // #[inline(always)]
+    pub fn buffer_start_index(&self) -> isize {
+        self.current_char_index - self.p as isize
+    }
+
+    /// number of chars in the buffer.
+    // #[inline(always)]
+    pub fn buffer_len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// take the I/O error which ended the stream.
+    // #[inline(always)]
+    pub fn take_io_error(&mut self) -> Option<std::io::Error> {
+        self.io_error.take()
+    }
+
+    /// [UnbufferedCharStream::try_text] is [CharStream::text] but returns an error instead of panicking
+    /// when chars of `start..=end` are not in the buffer. `end` past EOF is clamped like other streams.
+    pub fn try_text(&self, start: isize, end: isize) -> Result<Cow<'_, str>, UnbufferedTextError> {
+        let buffer_start = self.buffer_start_index();
+        let buffer_end = buffer_start + self.data.len() as isize;
+        let end = if self.eof { end.min(buffer_end - 1) } else { end };
+        if end < start {
+            return Ok(Cow::Borrowed(""));
+        }
+        if start < buffer_start || end >= buffer_end {
+            return Err(UnbufferedTextError { start, end, buffer_start, buffer_end });
+        }
+        let chars = &self.data[(start - buffer_start) as usize..=(end - buffer_start) as usize];
+        Ok(Cow::Owned(chars.iter().collect()))
+    }
+
+    /// make sure `want` chars from the current one are in the buffer, unless the input ends.
+    // #[inline]
+    fn sync(&mut self, want: usize) {
+        while self.data.len() < self.p + want && !self.eof {
+            match self.read_char() {
+                Some(c) => self.data.push(c),
+                None => self.eof = true,
+            }
+        }
+    }
+
+    // #[inline]
+    fn read_char(&mut self) -> Option<char> {
+        if !self.fill_bytes(1) {
+            return None;
+        }
+        let len = match self.bytes[self.byte_pos] {
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            0xF0..=0xF7 => 4,
+            _ => 1,
+        };
+        self.fill_bytes(len);
+        let available = len.min(self.bytes.len() - self.byte_pos);
+        let sequence = &self.bytes[self.byte_pos..self.byte_pos + available];
+        match std::str::from_utf8(sequence) {
+            Ok(s) => {
+                self.byte_pos += available;
+                s.chars().next()
+            }
+            Err(e) => {
+                // skip the invalid sequence, or the incomplete one at the end of input.
+                self.byte_pos += e.error_len().unwrap_or(available);
+                Some(REPLACEMENT_CHARACTER)
+            }
+        }
+    }
+
+    /// make sure `want` bytes are available to decode, returns false if no byte is available.
+    // #[inline]
+    fn fill_bytes(&mut self, want: usize) -> bool {
+        if self.bytes.len() - self.byte_pos < want && !self.reader_eof {
+            self.bytes.drain(..self.byte_pos);
+            self.byte_pos = 0;
+            let mut chunk = [0u8; READ_CHUNK_SIZE];
+            while self.bytes.len() < want && !self.reader_eof {
+                match self.reader.read(&mut chunk) {
+                    Ok(0) => self.reader_eof = true,
+                    Ok(n) => self.bytes.extend_from_slice(&chunk[..n]),
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => {
+                        self.io_error = Some(e);
+                        self.reader_eof = true;
+                    }
+                }
+            }
+        }
+        self.bytes.len() > self.byte_pos
+    }
+}
+
+impl<R: Read> IntStream for UnbufferedCharStream<R> {
+    // #[inline]
+    fn consume(&mut self) {
+        if self.la(1) == EOF {
+            panic!("can not consume EOF")
+        }
+        self.last_char = self.data[self.p] as isize;
+        if self.p == self.data.len() - 1 && self.num_markers == 0 {
+            // nothing is pinned, start a new buffer from the next char.
+            self.data.clear();
+            self.p = 0;
+            self.last_char_buffer_start = self.last_char;
+        } else {
+            self.p += 1;
+        }
+        self.current_char_index += 1;
+    }
+
+    // #[inline]
+    fn la(&mut self, i: isize) -> isize {
+        match i {
+            0 => panic!("undefined invocation: LA(0)"),
+            -1 => self.last_char,
+            i if i < 0 => match self.p.checked_sub((-i) as usize) {
+                Some(idx) => self.data[idx] as isize,
+                None => panic!("can not look back {} chars beyond the buffer of unbuffered stream", -i),
+            },
+            i => {
+                self.sync(i as usize);
+                match self.data.get(self.p + i as usize - 1) {
+                    Some(c) => *c as isize,
+                    None => EOF,
+                }
+            }
+        }
+    }
+
+    // #[inline]
+    fn mark(&mut self) -> isize {
+        if self.num_markers == 0 {
+            self.last_char_buffer_start = self.last_char;
+        }
+        self.num_markers += 1;
+        -(self.num_markers as isize)
+    }
+
+    // #[inline]
+    fn release(&mut self, marker: isize) {
+        if marker != -(self.num_markers as isize) {
+            panic!("release is called with an invalid marker {}", marker);
+        }
+        self.num_markers -= 1;
+        if self.num_markers == 0 && self.p > 0 {
+            // discard the chars before the current one, they are not pinned anymore.
+            self.data.drain(..self.p);
+            self.p = 0;
+            self.last_char_buffer_start = self.last_char;
+        }
+    }
+
+    // #[inline(always)]
+    fn index(&self) -> isize {
+        self.current_char_index
+    }
+
+    /// seek to `index` within the buffer, seeking forward reads chars into the buffer.
+    /// Panics if `index` is before the buffer.
+    fn seek(&mut self, mut index: isize) {
+        if index == self.current_char_index {
+            return;
+        }
+        if index > self.current_char_index {
+            self.sync((index - self.current_char_index) as usize);
+            index = index.min(self.buffer_start_index() + self.data.len() as isize);
+        }
+        let i = index - self.buffer_start_index();
+        if i < 0 {
+            panic!("can not seek to index {} before the buffer of unbuffered stream starts at {}", index, self.buffer_start_index());
+        }
+        self.p = i as usize;
+        self.current_char_index = index;
+        self.last_char = if self.p == 0 { self.last_char_buffer_start } else { self.data[self.p - 1] as isize };
+    }
+
+    fn size(&self) -> isize {
+        panic!("unbuffered stream can not know its size")
+    }
+
+    // #[inline(always)]
+    fn source_name(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.source_name)
+    }
+}
+
+impl<R: Read> CharStream for UnbufferedCharStream<R> {
+    /// Panics if chars of `start..=end` are not in the buffer, see [UnbufferedCharStream::try_text].
+    fn text(&self, start: usize, end: usize) -> Cow<'_, str> {
+        match self.try_text(start as isize, end as isize) {
+            Ok(text) => text,
+            Err(e) => panic!("{}", e),
+        }
+    }
+}
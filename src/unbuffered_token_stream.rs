@@ -0,0 +1,261 @@
+use std::borrow::Cow;
+use std::rc::Rc;
+use crate::errors::UnbufferedTokenError;
+use crate::int_stream::IntStream;
+use crate::interval_set::Interval;
+use crate::token::{Token, TOKEN_EOF, TOKEN_INVALID_TYPE};
+use crate::token_source::TokenSource;
+use crate::token_stream::TokenStream;
+
+/// [UnbufferedTokenStream] fetches tokens from a [TokenSource] on demand, and only the tokens pinned by
+/// outstanding marks(see [IntStream::mark]) are kept in the buffer, besides the tokens looked ahead.
+/// With [crate::unbuffered_char_stream::UnbufferedCharStream], an input of any length is parsed in bounded memory.
+/// [IntStream::seek], [TokenStream::token_at] and [TokenStream::text] only work within the buffer, and
+/// [IntStream::size] is unknown. Tokens on all channels are seen, there is no channel filtering.
+pub struct UnbufferedTokenStream<TS: TokenSource> {
+    token_source: TS,
+    // tokens of the buffer, the current token is tokens[p]
+    tokens: Vec<Rc<TS::TK>>,
+    p: usize,
+    num_markers: usize,
+    // the token before the current one, for lt(-1)
+    last_token: Option<Rc<TS::TK>>,
+    // the token before tokens[0], which is the last token when p == 0
+    last_token_buffer_start: Option<Rc<TS::TK>>,
+    // absolute index of the current token
+    current_token_index: isize,
+}
+
+impl<TS: TokenSource> UnbufferedTokenStream<TS> {
+    /// creates a stream of `token_source`, the first token is fetched at once.
+    pub fn new(token_source: TS) -> Self {
+        let mut stream = Self {
+            token_source,
+            tokens: vec![],
+            p: 0,
+            num_markers: 0,
+            last_token: None,
+            last_token_buffer_start: None,
+            current_token_index: 0,
+        };
+        stream.sync(1);
+        stream
+    }
+
+    // #[inline(always)]
+    pub fn token_source(&self) -> &TS {
+        &self.token_source
+    }
+
+    /// absolute index of the first token in the buffer.
+    // #[inline(always)]
+    pub fn buffer_start_index(&self) -> isize {
+        self.current_token_index - self.p as isize
+    }
+
+    /// number of tokens in the buffer.
+    // #[inline(always)]
+    pub fn buffer_len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// [UnbufferedTokenStream::try_token_at] is [TokenStream::token_at] but returns an error instead of panicking
+    /// when the token is not in the buffer.
+    pub fn try_token_at(&self, idx: usize) -> Result<Rc<TS::TK>, UnbufferedTokenError> {
+        let i = idx as isize - self.buffer_start_index();
+        match usize::try_from(i).ok().and_then(|i| self.tokens.get(i)) {
+            Some(tk) => Ok(Rc::clone(tk)),
+            None => Err(self.out_of_buffer(idx as isize)),
+        }
+    }
+
+    /// [UnbufferedTokenStream::try_seek] is [IntStream::seek] but returns an error instead of panicking
+    /// when `index` is before the buffer. Seeking forward fetches tokens into the buffer,
+    /// and `index` beyond the end of the stream is clamped to EOF.
+    pub fn try_seek(&mut self, mut index: isize) -> Result<(), UnbufferedTokenError> {
+        if index == self.current_token_index {
+            return Ok(());
+        }
+        if index > self.current_token_index {
+            self.sync((index - self.current_token_index + 1) as usize);
+            index = index.min(self.buffer_start_index() + self.tokens.len() as isize - 1);
+        }
+        let i = usize::try_from(index - self.buffer_start_index()).map_err(|_| self.out_of_buffer(index))?;
+        self.p = i;
+        self.current_token_index = index;
+        self.last_token = match self.p {
+            0 => self.last_token_buffer_start.clone(),
+            p => Some(Rc::clone(&self.tokens[p - 1])),
+        };
+        Ok(())
+    }
+
+    /// [UnbufferedTokenStream::try_text] is [TokenStream::text] but returns an error instead of panicking
+    /// when tokens of the interval are not in the buffer anymore. Tokens after the buffer are fetched.
+    pub fn try_text(&mut self, interval: Interval) -> Result<String, UnbufferedTokenError> {
+        if interval.start < 0 || interval.stop < 0 {
+            return Ok(String::new());
+        }
+        if interval.stop >= self.current_token_index {
+            self.sync((interval.stop - self.current_token_index + 1) as usize);
+        }
+        let buffer_start = self.buffer_start_index();
+        let stop = interval.stop.min(buffer_start + self.tokens.len() as isize - 1);
+        if interval.start > stop {
+            return Ok(String::new());
+        }
+        if interval.start < buffer_start {
+            return Err(self.out_of_buffer(interval.start));
+        }
+        let mut text = String::new();
+        for tk in &self.tokens[(interval.start - buffer_start) as usize..=(stop - buffer_start) as usize] {
+            if tk.token_type() == TOKEN_EOF {
+                break;
+            }
+            text.push_str(&tk.text());
+        }
+        Ok(text)
+    }
+
+    // #[inline]
+    fn out_of_buffer(&self, index: isize) -> UnbufferedTokenError {
+        let buffer_start = self.buffer_start_index();
+        UnbufferedTokenError { index, buffer_start, buffer_end: buffer_start + self.tokens.len() as isize }
+    }
+
+    /// make sure `want` tokens from the current one are in the buffer, unless the stream ends.
+    // #[inline]
+    fn sync(&mut self, want: usize) {
+        while self.tokens.len() < self.p + want && self.fetch() {}
+    }
+
+    /// fetch a token into the buffer, returns false if EOF is fetched already.
+    // #[inline]
+    fn fetch(&mut self) -> bool {
+        if self.tokens.last().is_some_and(|tk| tk.token_type() == TOKEN_EOF) {
+            return false;
+        }
+        let tk = self.token_source.next_token();
+        tk.set_token_index(self.buffer_start_index() + self.tokens.len() as isize);
+        self.tokens.push(Rc::new(tk));
+        true
+    }
+}
+
+impl<TS: TokenSource> IntStream for UnbufferedTokenStream<TS> {
+    /// Panics if the current token is EOF.
+    fn consume(&mut self) {
+        if self.la(1) == TOKEN_EOF {
+            panic!("can not consume EOF")
+        }
+        let last_token = Rc::clone(&self.tokens[self.p]);
+        if self.num_markers == 0 {
+            // nothing is pinned, discard the consumed tokens and keep the tokens looked ahead.
+            self.tokens.drain(..=self.p);
+            self.p = 0;
+            self.last_token_buffer_start = Some(Rc::clone(&last_token));
+        } else {
+            self.p += 1;
+        }
+        self.last_token = Some(last_token);
+        self.current_token_index += 1;
+        self.sync(1);
+    }
+
+    // #[inline]
+    fn la(&mut self, i: isize) -> isize {
+        if i == 0 {
+            panic!("undefined invocation: LA(0)")
+        }
+        self.lt(i).map_or(TOKEN_INVALID_TYPE, |tk| tk.token_type())
+    }
+
+    // #[inline]
+    fn mark(&mut self) -> isize {
+        if self.num_markers == 0 {
+            self.last_token_buffer_start = self.last_token.clone();
+        }
+        self.num_markers += 1;
+        -(self.num_markers as isize)
+    }
+
+    // #[inline]
+    fn release(&mut self, marker: isize) {
+        if marker != -(self.num_markers as isize) {
+            panic!("release is called with an invalid marker {}", marker);
+        }
+        self.num_markers -= 1;
+        if self.num_markers == 0 {
+            // discard the tokens before the current one, they are not pinned anymore.
+            self.tokens.drain(..self.p);
+            self.p = 0;
+            self.last_token_buffer_start = self.last_token.clone();
+        }
+    }
+
+    // #[inline(always)]
+    fn index(&self) -> isize {
+        self.current_token_index
+    }
+
+    /// seek to `index` within the buffer, see [UnbufferedTokenStream::try_seek].
+    /// Panics if `index` is before the buffer.
+    fn seek(&mut self, index: isize) {
+        if let Err(e) = self.try_seek(index) {
+            panic!("{}", e)
+        }
+    }
+
+    fn size(&self) -> isize {
+        panic!("unbuffered stream can not know its size")
+    }
+
+    // #[inline(always)]
+    fn source_name(&self) -> Cow<'_, str> {
+        self.token_source.source_name()
+    }
+}
+
+impl<TS: TokenSource> TokenStream for UnbufferedTokenStream<TS> {
+    type TK = TS::TK;
+
+    /// Panics if `k` is less than -1 and the token is before the buffer.
+    fn lt(&mut self, k: isize) -> Option<Rc<Self::TK>> {
+        match k {
+            0 => None,
+            -1 => self.last_token.clone(),
+            k if k < 0 => match self.p.checked_sub((-k) as usize) {
+                Some(i) => Some(Rc::clone(&self.tokens[i])),
+                None => panic!("can not look back {} tokens beyond the buffer of unbuffered stream", -k),
+            },
+            k => {
+                self.sync(k as usize);
+                // EOF is the last token.
+                let i = (self.p + k as usize - 1).min(self.tokens.len() - 1);
+                Some(Rc::clone(&self.tokens[i]))
+            }
+        }
+    }
+
+    /// Panics if the token is not in the buffer, see [UnbufferedTokenStream::try_token_at].
+    fn token_at(&self, idx: usize) -> Rc<Self::TK> {
+        match self.try_token_at(idx) {
+            Ok(tk) => tk,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Panics if tokens of `interval` are not in the buffer, see [UnbufferedTokenStream::try_text].
+    fn text(&mut self, interval: Interval) -> String {
+        match self.try_text(interval) {
+            Ok(text) => text,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// the tokens are fetched into the buffer till EOF, panics if the first token is not in the buffer anymore.
+    fn all_text(&mut self) -> String {
+        while self.fetch() {}
+        self.text(Interval::new(0, self.buffer_start_index() + self.tokens.len() as isize - 1))
+    }
+}
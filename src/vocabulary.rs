@@ -0,0 +1,141 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use crate::errors::TokensFileError;
+use crate::token::TOKEN_EOF;
+
+const EOF_NAME: &str = "EOF";
+/// the largest type of a `.tokens` file, the tables are indexed by type, so a huge type is rejected
+/// instead of allocating them. Grammars have far fewer token types.
+const MAX_TOKENS_FILE_TYPE: usize = 0xFFFF;
+
+/// [Vocabulary] maps token types to their names of a grammar: the literal name like `'{'`, and the symbolic name
+/// like `ID`. It is created from the name tables of generated code or [crate::interp_file::InterpFile],
+/// or from a `.tokens` file written by the ANTLR tool, see [Vocabulary::parse].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Vocabulary {
+    // names indexed by token type, a type has no name if it is empty.
+    literal_names: Vec<Cow<'static, str>>,
+    symbolic_names: Vec<Cow<'static, str>>,
+    // token types by literal names and symbolic names.
+    token_types: HashMap<Cow<'static, str>, isize>,
+}
+
+impl Vocabulary {
+    /// creates a vocabulary of the name tables indexed by token type, an empty name means none.
+    pub fn new(literal_names: &[&'static str], symbolic_names: &[&'static str]) -> Self {
+        Self::from_names(
+            literal_names.iter().map(|&n| Cow::Borrowed(n)).collect(),
+            symbolic_names.iter().map(|&n| Cow::Borrowed(n)).collect(),
+        )
+    }
+
+    /// creates a vocabulary of the name tables which could be owned, see [Vocabulary::new].
+    // #[inline]
+    pub fn with_names(literal_names: &[Cow<'static, str>], symbolic_names: &[Cow<'static, str>]) -> Self {
+        Self::from_names(literal_names.to_vec(), symbolic_names.to_vec())
+    }
+
+    /// [Vocabulary::load] reads and parses the `.tokens` file at `path`.
+    // #[inline]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TokensFileError> {
+        let content = fs::read_to_string(path)?;
+        Self::parse(&content)
+    }
+
+    /// [Vocabulary::parse] parses the content of a `.tokens` file, which has a `name=type` per line,
+    /// a name quoted by `'` is a literal name, e.g. `'{'=68`, otherwise it is a symbolic name.
+    /// A type larger than 65535 is an invalid line.
+    pub fn parse(content: &str) -> Result<Self, TokensFileError> {
+        let mut literal_names = vec![];
+        let mut symbolic_names = vec![];
+        for (line, text) in content.lines().enumerate().map(|(i, l)| (i + 1, l.trim_end())) {
+            if text.is_empty() {
+                continue;
+            }
+            // a literal name could have `=` in it, the type follows the last one.
+            let invalid = || TokensFileError::InvalidLine { line, text: text.to_string() };
+            let (name, ttype) = text.rsplit_once('=').ok_or_else(invalid)?;
+            let ttype = ttype.parse::<usize>().map_err(|_| invalid())?;
+            if name.is_empty() || ttype > MAX_TOKENS_FILE_TYPE {
+                return Err(invalid());
+            }
+            let names = if name.starts_with('\'') { &mut literal_names } else { &mut symbolic_names };
+            if names.len() <= ttype {
+                names.resize(ttype + 1, Cow::Borrowed(""));
+            }
+            names[ttype] = Cow::Owned(name.to_string());
+        }
+        Ok(Self::from_names(literal_names, symbolic_names))
+    }
+
+    // #[inline]
+    fn from_names(literal_names: Vec<Cow<'static, str>>, symbolic_names: Vec<Cow<'static, str>>) -> Self {
+        let mut token_types = HashMap::new();
+        // literal names are quoted, so they never clash with symbolic names.
+        for names in [&symbolic_names, &literal_names] {
+            for (ttype, name) in names.iter().enumerate().filter(|(_, n)| !n.is_empty()) {
+                token_types.insert(name.clone(), ttype as isize);
+            }
+        }
+        Self { literal_names, symbolic_names, token_types }
+    }
+
+    /// the literal names indexed by token type, an empty name means none.
+    // #[inline(always)]
+    pub fn literal_names(&self) -> &[Cow<'static, str>] {
+        &self.literal_names
+    }
+
+    /// the symbolic names indexed by token type, an empty name means none.
+    // #[inline(always)]
+    pub fn symbolic_names(&self) -> &[Cow<'static, str>] {
+        &self.symbolic_names
+    }
+
+    /// the largest token type of this vocabulary, which has a literal or symbolic name or not.
+    // #[inline(always)]
+    pub fn max_token_type(&self) -> isize {
+        self.literal_names.len().max(self.symbolic_names.len()) as isize - 1
+    }
+
+    /// the literal name of `ttype`, like `'{'`, [None] if it has no literal name.
+    // #[inline]
+    pub fn literal_name(&self, ttype: isize) -> Option<&str> {
+        name_of(&self.literal_names, ttype)
+    }
+
+    /// the symbolic name of `ttype`, like `ID`, [None] if it has no symbolic name. The name of EOF is `EOF`.
+    // #[inline]
+    pub fn symbolic_name(&self, ttype: isize) -> Option<&str> {
+        if ttype == TOKEN_EOF {
+            return Some(EOF_NAME);
+        }
+        name_of(&self.symbolic_names, ttype)
+    }
+
+    /// the name of `ttype` in messages, which is its literal name, or its symbolic name if it has no literal name,
+    /// or the number itself if it has no name at all.
+    pub fn display_name(&self, ttype: isize) -> Cow<'_, str> {
+        match self.literal_name(ttype).or_else(|| self.symbolic_name(ttype)) {
+            Some(name) => Cow::Borrowed(name),
+            None => Cow::Owned(ttype.to_string()),
+        }
+    }
+
+    /// the token type of a literal name like `'{'`, or a symbolic name like `ID`.
+    // #[inline]
+    pub fn token_type(&self, name: &str) -> Option<isize> {
+        if name == EOF_NAME {
+            return Some(TOKEN_EOF);
+        }
+        self.token_types.get(name).copied()
+    }
+}
+
+// #[inline(always)]
+fn name_of<'a>(names: &'a [Cow<'static, str>], ttype: isize) -> Option<&'a str> {
+    let name = names.get(usize::try_from(ttype).ok()?)?;
+    (!name.is_empty()).then_some(name.as_ref())
+}
@@ -1,16 +1,43 @@
+use std::borrow::Cow;
 use lazy_static::lazy_static;
 
+use std::cell::RefCell;
+use std::rc::Rc;
 use std::sync::{Arc, RwLock};
+use std::sync::atomic::AtomicIsize;
 use antlr4rs::atn::ATN;
 use antlr4rs::atn_deserializer::ATNDeserializer;
+use antlr4rs::atn_serializer::ATNSerializer;
+use antlr4rs::case_changing_char_stream::CaseChangingCharStream;
 use antlr4rs::dfa::DFA;
+use antlr4rs::dot_generator::DOTGenerator;
 use antlr4rs::error_listener::ErrorListener;
-use antlr4rs::input_stream::StringStream;
-use antlr4rs::lexer::{BaseLexer, Lexer};
-use antlr4rs::lexer_atn_simulator::BaseLexerATNSimulator;
+use antlr4rs::errors::{ANTLRError, ATNDeserializeErrorKind, ATNSection, InterpFileError, TokensFileError};
+use antlr4rs::char_stream::CharStream;
+use antlr4rs::input_stream::{SharedStringStream, StringStream};
+use antlr4rs::int_stream::IntStream;
+use antlr4rs::interp_file::InterpFile;
+use antlr4rs::lexer_action::{LexerAction, LexerActionType};
+use antlr4rs::lexer_action_executor::LexerActionExecutor;
+use antlr4rs::lexer::{error_display, BaseLexer, LEXER_DEFAULT_MODE, LEXER_MORE, LEXER_SKIP, Lexer, LexerRecognizer, LexerView};
+use antlr4rs::lexer_interpreter::LexerInterpreter;
+use antlr4rs::lexer_snapshot::{LineSnapshots, TextEdit};
+use antlr4rs::lexer_atn_simulator::{BaseLexerATNSimulator, LexerATNSimulator};
+use antlr4rs::misc::murmur3::MurmurHash;
 use antlr4rs::prediction_context::PredictionContextCache;
-use antlr4rs::recognizer::{BaseRecognizer, Recognizer};
-use antlr4rs::token_factory::CommonTokenFactory;
+use antlr4rs::recognizer::{static_names, BaseRecognizer, Recognizer};
+use antlr4rs::rule_context::RuleContext;
+use antlr4rs::token::{BaseToken, Token, TOKEN_DEFAULT_CHANNEL, TOKEN_EOF, TOKEN_HIDDEN_CHANNEL, TOKEN_INVALID_TYPE};
+use antlr4rs::common_token_stream::CommonTokenStream;
+use antlr4rs::list_token_source::ListTokenSource;
+use antlr4rs::token_stream::TokenStream;
+use antlr4rs::token_record::{read_tokens, TokenRecorder};
+use antlr4rs::token_source::TokenSource;
+use antlr4rs::token_factory::{CommonTokenFactory, SpanTokenFactory, TokenFactory};
+use antlr4rs::text_position::TextPosition;
+use antlr4rs::unbuffered_char_stream::UnbufferedCharStream;
+use antlr4rs::unbuffered_token_stream::UnbufferedTokenStream;
+use antlr4rs::vocabulary::Vocabulary;
 
 const CHANNEL_NAMES: &'static [&'static str] = &["DEFAULT_TOKEN_CHANNEL", "HIDDEN"];
 const MODE_NAMES: &'static [&'static str] = &["DEFAULT_MODE"];
@@ -474,6 +501,319 @@ fn test_arishem_lexer_atn_deserialize() {
     println!("{:?}", atn);
 }
 
+#[test]
+fn test_arishem_lexer_atn_try_deserialize() {
+    let atn_der = ATNDeserializer::new(None);
+    // the last lexer action is `skip`: 6, 0, 0
+    let mut data = SERIALIZED_ATN.to_vec();
+    let action_type_offset = data.len() - 3;
+    data[action_type_offset] = 8;
+    let err = atn_der.try_deserialize(&data).unwrap_err();
+    assert_eq!(err.section, ATNSection::LexerActions);
+    assert_eq!(err.kind, ATNDeserializeErrorKind::InvalidLexerActionType);
+    assert_eq!((err.offset, err.value), (action_type_offset, Some(8)));
+
+    let err = atn_der.try_deserialize(&SERIALIZED_ATN[..SERIALIZED_ATN.len() - 1]).unwrap_err();
+    assert_eq!(err.section, ATNSection::LexerActions);
+    assert_eq!(err.kind, ATNDeserializeErrorKind::UnexpectedEnd);
+}
+
+#[test]
+fn test_arishem_lexer_atn_serialize() {
+    let atn = ATNDeserializer::new(None).deserialize(SERIALIZED_ATN);
+    let data = ATNSerializer::new().serialize(&atn);
+    assert_eq!(data, SERIALIZED_ATN);
+}
+
+/// write the tables in the layout of `.interp` file generated by ANTLR tool.
+fn arishem_lexer_interp() -> String {
+    let section = |header: &str, names: &[&str]| {
+        let names: Vec<&str> = names.iter().map(|n| if n.is_empty() { "null" } else { n }).collect();
+        format!("{}\n{}\n\n", header, names.join("\n"))
+    };
+    let atn: Vec<String> = SERIALIZED_ATN.iter().map(|v| v.to_string()).collect();
+    section("token literal names:", LITERAL_NAMES)
+        + &section("token symbolic names:", SYMBOLIC_NAMES)
+        + &section("rule names:", RULE_NAMES)
+        + &section("channel names:", CHANNEL_NAMES)
+        + &section("mode names:", MODE_NAMES)
+        + &format!("atn:\n[{}]", atn.join(", "))
+}
+
+#[test]
+fn test_arishem_lexer_interp_file() {
+    let interp = InterpFile::parse(&arishem_lexer_interp(), None).unwrap();
+    assert_eq!(&*interp.literal_names, LITERAL_NAMES);
+    assert_eq!(&*interp.symbolic_names, SYMBOLIC_NAMES);
+    assert_eq!(&*interp.rule_names, RULE_NAMES);
+    assert_eq!(&*interp.channel_names, CHANNEL_NAMES);
+    assert_eq!(&*interp.mode_names, MODE_NAMES);
+    assert_eq!(ATNSerializer::new().serialize(&interp.atn), SERIALIZED_ATN);
+    let recognizer = interp.new_recognizer("Arishem.g4");
+    assert_eq!(recognizer.rule_names(), RULE_NAMES);
+    // the tables are shared with the recognizer instead of leaked, and freed with the last owner.
+    assert_eq!(Arc::strong_count(&interp.rule_names), 2);
+    assert!(matches!(recognizer.literal_names()[1], Cow::Owned(_)));
+    drop(recognizer);
+    assert_eq!(Arc::strong_count(&interp.rule_names), 1);
+
+    let path = std::env::temp_dir().join("arishem_lexer_test.interp");
+    std::fs::write(&path, arishem_lexer_interp()).unwrap();
+    let loaded = InterpFile::load(&path, None).unwrap();
+    assert_eq!(&*loaded.rule_names, RULE_NAMES);
+    std::fs::remove_file(&path).unwrap();
+
+    let err = InterpFile::parse("rule names:\nT__0\n", None).unwrap_err();
+    assert!(matches!(err, InterpFileError::MissingSection("atn:")));
+    let err = InterpFile::parse("atn:\n[4, 0, x]", None).unwrap_err();
+    assert!(matches!(err, InterpFileError::InvalidATN { line: 2, .. }));
+    let err = InterpFile::parse("atn:\n[4, 0]", None).unwrap_err();
+    assert!(matches!(err, InterpFileError::ATNDeserialize(_)));
+}
+
+#[test]
+fn test_arishem_lexer_interpreter() {
+    let input = "{\"Conditions\": [{\"Lhs\": 12, \"Operator\": \">=\", \"Rhs\": -3.5e2}]}\n";
+    let mut expected = new_arishem_lexer(StringStream::from(input));
+    let interp = InterpFile::parse(&arishem_lexer_interp(), None).unwrap();
+    let mut lexer = interp.new_lexer_interpreter("Arishem.g4", StringStream::from(input));
+    assert_eq!(lexer.channel_names(), CHANNEL_NAMES);
+    assert_eq!(lexer.mode_names(), MODE_NAMES);
+    assert_eq!(lexer.rule_names(), RULE_NAMES);
+    loop {
+        let (tk, expected_tk) = (lexer.next_token(), expected.next_token());
+        assert_eq!((tk.token_type(), tk.text(), tk.start(), tk.stop(), tk.line(), tk.column()),
+                   (expected_tk.token_type(), expected_tk.text(), expected_tk.start(), expected_tk.stop(),
+                    expected_tk.line(), expected_tk.column()));
+        if tk.token_type() == TOKEN_EOF {
+            break;
+        }
+    }
+    lexer.reset();
+    assert_eq!(lexer.next_token().token_type(), 68);
+
+    // an interpreter over the global ATN works the same, DFAs are its own.
+    let mut lexer = LexerInterpreter::new(
+        "Arishem.g4", Arc::clone(&*GLB_ATN), static_names(RULE_NAMES), static_names(LITERAL_NAMES),
+        static_names(SYMBOLIC_NAMES), static_names(CHANNEL_NAMES), static_names(MODE_NAMES),
+        StringStream::from("null"),
+    );
+    assert_eq!(lexer.next_token().token_type(), 61);
+    assert_eq!(lexer.next_token().token_type(), TOKEN_EOF);
+}
+
+#[test]
+fn test_arishem_lexer_atn_to_dot() {
+    let atn = ATNDeserializer::new(None).deserialize(SERIALIZED_ATN);
+    let recog = BaseRecognizer::new(RULE_NAMES, LITERAL_NAMES, SYMBOLIC_NAMES, "Arishem.g4");
+    let dot_gen = DOTGenerator::new(&atn, Some(&recog));
+
+    // T__0 : 'Conditions' ;
+    let dot = dot_gen.rule_to_dot(0).unwrap();
+    assert!(dot.starts_with("digraph ATN {"));
+    assert!(dot.contains("[label=\"'C'\"]"));
+    assert!(dot.contains("RULE_START\\nT__0"));
+    assert!(!dot.contains("T__1"));
+    assert!(dot_gen.rule_to_dot(RULE_NAMES.len()).is_none());
+
+    // the default mode reaches all non-fragment lexer rules, fragment rules are shown as labels of rule transitions.
+    let dot = dot_gen.mode_to_dot(0).unwrap();
+    assert!(dot.contains("TOKEN_START\\nd=0"));
+    let token_rules = atn.states()[atn.mode2start_state_nths()[0]].transitions().len();
+    assert_eq!(dot.matches("RULE_START").count(), token_rules);
+    assert!(dot.contains("[label=\"INT\" style=dashed]"));
+    assert!(dot_gen.atn_to_dot().lines().count() > dot.lines().count());
+}
+
+fn new_arishem_lexer<CS: CharStream>(input: CS) -> BaseLexer<BaseRecognizer, BaseLexerATNSimulator, CommonTokenFactory, CS> {
+    let br = BaseRecognizer::new(RULE_NAMES, LITERAL_NAMES, SYMBOLIC_NAMES, "arishem.g4");
+    let blas = BaseLexerATNSimulator::new(Arc::clone(&*GLB_ATN), Arc::clone(&*GLB_PREDCT_CTX_CACHE), Arc::clone(&*GLB_DECISION_TO_DFA));
+    BaseLexer::new(br, blas, CommonTokenFactory::default(), input)
+}
+
+#[test]
+fn test_arishem_lexer_next_token() {
+    let mut lexer = new_arishem_lexer(StringStream::from("{\"Lhs\":\n12}"));
+    let mut tokens = vec![];
+    loop {
+        let tk = lexer.next_token();
+        tokens.push((tk.token_type(), tk.text().into_owned(), tk.start(), tk.stop(), tk.line(), tk.column()));
+        if tk.token_type() == TOKEN_EOF {
+            break;
+        }
+    }
+    // WS is skipped by its lexer action
+    assert_eq!(tokens, vec![
+        (68, "{".to_string(), 0, 0, 1, 0),
+        (65, "\"".to_string(), 1, 1, 1, 1),
+        (21, "Lhs".to_string(), 2, 4, 1, 2),
+        (65, "\"".to_string(), 5, 5, 1, 5),
+        (64, ":".to_string(), 6, 6, 1, 6),
+        (70, "12".to_string(), 8, 9, 2, 0),
+        (69, "}".to_string(), 10, 10, 2, 2),
+        (TOKEN_EOF, "<EOF>".to_string(), 11, 10, 2, 3),
+    ]);
+    // keep returning EOF at the end of input
+    let eof = lexer.next_token();
+    assert_eq!((eof.token_type(), eof.start(), eof.line(), eof.column()), (TOKEN_EOF, 11, 2, 3));
+
+    // the char starts no token is dropped, the error is kept by recognizer.
+    let mut lexer = new_arishem_lexer(StringStream::from("{\u{7}}"));
+    assert_eq!(lexer.next_token().token_type(), 68);
+    assert!(!lexer.has_error());
+    let tk = lexer.next_token();
+    assert_eq!((tk.token_type(), tk.start(), tk.column()), (69, 2, 2));
+    assert!(matches!(lexer.error(), Some(ANTLRError::LexerNoAltErr { start_index: 1 })));
+    assert_eq!(lexer.next_token().token_type(), TOKEN_EOF);
+
+    let mut lexer = new_arishem_lexer(StringStream::from(""));
+    assert_eq!(lexer.next_token().token_type(), TOKEN_EOF);
+}
+
+/// an [ErrorListener] records (line, column, message) of errors.
+struct ErrorRecorder {
+    errors: Rc<RefCell<Vec<(isize, isize, String)>>>,
+}
+
+impl ErrorListener for ErrorRecorder {
+    fn syntax_error(&mut self, _recognizer: &dyn Recognizer, line: isize, column: isize, msg: &str, err: Option<&ANTLRError>) {
+        assert!(matches!(err, Some(ANTLRError::LexerNoAltErr { .. })));
+        self.errors.borrow_mut().push((line, column, msg.to_string()))
+    }
+}
+
+#[test]
+fn test_arishem_lexer_error_reporting() {
+    assert_eq!(error_display("a\n\r\t\u{7}你"), "a\\n\\r\\t\\u{7}你");
+
+    let errors = Rc::new(RefCell::new(vec![]));
+    let mut lexer = new_arishem_lexer(StringStream::from("{\u{7}}\n \u{1}}"));
+    lexer.add_error_listener(Box::new(ErrorRecorder { errors: Rc::clone(&errors) }));
+    lexer.add_error_listener(Box::new(ErrorRecorder { errors: Rc::clone(&errors) }));
+    let mut types = vec![];
+    loop {
+        let tk = lexer.next_token();
+        types.push(tk.token_type());
+        if tk.token_type() == TOKEN_EOF {
+            break;
+        }
+    }
+    // bad chars are dropped, every listener is notified.
+    assert_eq!(types, vec![68, 69, 26, 69, TOKEN_EOF]);
+    let expected = vec![
+        (1, 1, "token recognition error at: '\\u{7}'".to_string()),
+        (1, 1, "token recognition error at: '\\u{7}'".to_string()),
+        (2, 1, "token recognition error at: '\\u{1}'".to_string()),
+        (2, 1, "token recognition error at: '\\u{1}'".to_string()),
+    ];
+    assert_eq!(*errors.borrow(), expected);
+
+    // bad chars are emitted as error tokens.
+    errors.borrow_mut().clear();
+    let mut lexer = new_arishem_lexer(StringStream::from("{\u{7}\u{1}}"));
+    lexer.add_error_listener(Box::new(ErrorRecorder { errors: Rc::clone(&errors) }));
+    lexer.set_emit_error_tokens(true);
+    assert!(lexer.emit_error_tokens());
+    let mut tokens = vec![];
+    loop {
+        let tk = lexer.next_token();
+        tokens.push((tk.token_type(), tk.text().into_owned(), tk.start(), tk.stop(), tk.column()));
+        if tk.token_type() == TOKEN_EOF {
+            break;
+        }
+    }
+    assert_eq!(tokens, vec![
+        (68, "{".to_string(), 0, 0, 0),
+        (TOKEN_INVALID_TYPE, "\u{7}".to_string(), 1, 1, 1),
+        (TOKEN_INVALID_TYPE, "\u{1}".to_string(), 2, 2, 2),
+        (69, "}".to_string(), 3, 3, 3),
+        (TOKEN_EOF, "<EOF>".to_string(), 4, 3, 4),
+    ]);
+    assert_eq!(errors.borrow().len(), 2);
+
+    // listeners removed are not notified.
+    errors.borrow_mut().clear();
+    let mut lexer = new_arishem_lexer(StringStream::from("\u{7}"));
+    lexer.add_error_listener(Box::new(ErrorRecorder { errors: Rc::clone(&errors) }));
+    lexer.remove_error_listeners();
+    assert_eq!(lexer.next_token().token_type(), TOKEN_EOF);
+    assert!(errors.borrow().is_empty());
+    assert!(lexer.has_error());
+}
+
+#[test]
+fn test_arishem_lexer_token_iterators() {
+    let mut lexer = new_arishem_lexer(StringStream::from("{\"Lhs\":\n12}"));
+    let types: Vec<isize> = lexer.tokens().map(|tk| tk.token_type()).collect();
+    assert_eq!(types, vec![68, 65, 21, 65, 64, 70, 69, TOKEN_EOF]);
+    // the iterator stops after EOF, the lexer keeps returning EOF.
+    let mut tokens = lexer.tokens();
+    assert_eq!(tokens.next().map(|tk| tk.token_type()), Some(TOKEN_EOF));
+    assert!(tokens.next().is_none());
+
+    lexer.reset();
+    let all = lexer.all_tokens();
+    assert_eq!(all.iter().map(|tk| tk.token_type()).collect::<Vec<_>>(), types);
+
+    let texts: Vec<String> = new_arishem_lexer(StringStream::from("null, 1")).into_tokens().map(|tk| tk.text().into_owned()).collect();
+    assert_eq!(texts, vec!["null", ",", " ", "1", "<EOF>"]);
+    let mut into_tokens = new_arishem_lexer(StringStream::from("")).into_tokens();
+    assert_eq!(into_tokens.by_ref().count(), 1);
+    assert_eq!(into_tokens.into_inner().char_index(), 0);
+
+    let (tokens, errors) = new_arishem_lexer(StringStream::from("{\u{7}}\u{1}")).tokens_with_errors();
+    assert_eq!(tokens.iter().map(|tk| tk.token_type()).collect::<Vec<_>>(), vec![68, 69, TOKEN_EOF]);
+    assert!(matches!(errors[..], [ANTLRError::LexerNoAltErr { start_index: 1 }, ANTLRError::LexerNoAltErr { start_index: 3 }]));
+    let (tokens, errors) = new_arishem_lexer(StringStream::from("{}")).tokens_with_errors();
+    assert_eq!(tokens.len(), 3);
+    assert!(errors.is_empty());
+}
+
+#[test]
+fn test_arishem_lexer_case_insensitive() {
+    let mut lexer = new_arishem_lexer(CaseChangingCharStream::new(StringStream::from("NULL,Null"), false));
+    let tokens: Vec<(isize, String)> = lexer.tokens().map(|tk| (tk.token_type(), tk.text().into_owned())).collect();
+    assert_eq!(tokens, vec![
+        (61, "NULL".to_string()),
+        (63, ",".to_string()),
+        (61, "Null".to_string()),
+        (TOKEN_EOF, "<EOF>".to_string()),
+    ]);
+}
+
+#[test]
+fn test_arishem_lexer_unbuffered_input() {
+    let input = "{\"ConditionGroups\": [12, -3.5e2, null],\n\"OpLogic\":\"&&\", abc_1 >= 你}\u{7}";
+    let expected: Vec<TokenTuple> = new_arishem_lexer(StringStream::from(input)).all_tokens().iter().map(token_tuple).collect();
+    let (tokens, errors) = new_arishem_lexer(UnbufferedCharStream::new(input.as_bytes())).tokens_with_errors();
+    assert_eq!(tokens.iter().map(token_tuple).collect::<Vec<_>>(), expected);
+    assert_eq!(errors.len(), 1);
+}
+
+#[test]
+fn test_arishem_lexer_mode_api() {
+    let mut lexer = new_arishem_lexer(StringStream::from(""));
+    assert_eq!(lexer.mode(), LEXER_DEFAULT_MODE);
+    lexer.push_mode(1);
+    lexer.push_mode(2);
+    assert_eq!(lexer.mode(), 2);
+    assert_eq!(lexer.pop_mode(), Some(1));
+    assert_eq!(lexer.pop_mode(), Some(LEXER_DEFAULT_MODE));
+    assert_eq!(lexer.pop_mode(), None);
+    assert_eq!(lexer.mode(), LEXER_DEFAULT_MODE);
+
+    lexer.set_type(70);
+    lexer.set_channel(TOKEN_HIDDEN_CHANNEL);
+    lexer.set_text("42".to_string());
+    assert_eq!((lexer.token_type(), lexer.channel(), lexer.text().as_ref()), (70, TOKEN_HIDDEN_CHANNEL, "42"));
+    let tk = Lexer::emit(&mut lexer);
+    assert_eq!((tk.token_type(), tk.channel(), tk.text().as_ref()), (70, TOKEN_HIDDEN_CHANNEL, "42"));
+    lexer.skip();
+    assert_eq!(lexer.token_type(), LEXER_SKIP);
+    lexer.more();
+    assert_eq!(lexer.token_type(), LEXER_MORE);
+}
+
 #[test]
 fn test_create_new_arishem_lexer() {
     let ss = StringStream::from("{}");
@@ -489,3 +829,727 @@ fn test_create_new_arishem_lexer() {
     };
     println!("create arishem lexer success! {:?}", arishem_lexer.channel_names);
 }
+
+/// drive the simulator directly, returns (token type, text, line, column) of each token matched.
+fn simulate(input: &str) -> Result<Vec<(isize, String, isize, isize)>, ANTLRError> {
+    let recog = BaseRecognizer::new(RULE_NAMES, LITERAL_NAMES, SYMBOLIC_NAMES, "Arishem.g4");
+    let mut sim = BaseLexerATNSimulator::new(Arc::clone(&*GLB_ATN), Arc::clone(&*GLB_PREDCT_CTX_CACHE), Arc::clone(&*GLB_DECISION_TO_DFA));
+    let mut input = StringStream::from(input);
+    let mut tokens = vec![];
+    loop {
+        let (line, column) = (sim.line(), sim.char_position_in_line());
+        let ttype = sim.match_(&mut input, LEXER_DEFAULT_MODE, &recog)?;
+        if ttype == TOKEN_EOF {
+            return Ok(tokens);
+        }
+        tokens.push((ttype, sim.text(&input).into_owned(), line, column));
+    }
+}
+
+#[test]
+fn test_arishem_lexer_atn_simulator_match() {
+    let tokens = simulate("{\"ConditionGroups\": [12, -3.5e2, null],\n\"OpLogic\":\"&&\", abc_1 >= 你}").unwrap();
+    let expected = [
+        (68, "{", 1, 0), (65, "\"", 1, 1), (2, "ConditionGroups", 1, 2), (65, "\"", 1, 17), (64, ":", 1, 18),
+        (26, " ", 1, 19), (66, "[", 1, 20), (70, "12", 1, 21), (63, ",", 1, 23), (26, " ", 1, 24),
+        (78, "-", 1, 25), (70, "3", 1, 26), (62, ".", 1, 27), (70, "5", 1, 28), (71, "e2", 1, 29),
+        (63, ",", 1, 31), (26, " ", 1, 32), (61, "null", 1, 33), (67, "]", 1, 37), (63, ",", 1, 38),
+        (88, "\n", 1, 39), (65, "\"", 2, 0), (16, "OpLogic", 2, 1), (65, "\"", 2, 8), (64, ":", 2, 9),
+        (65, "\"", 2, 10), (74, "&&", 2, 11), (65, "\"", 2, 13), (63, ",", 2, 14), (26, " ", 2, 15),
+        (72, "abc_1", 2, 16), (26, " ", 2, 21), (86, ">=", 2, 22), (26, " ", 2, 24), (73, "你", 2, 25),
+        (69, "}", 2, 26),
+    ];
+    let expected: Vec<(isize, String, isize, isize)> = expected.iter().map(|(t, s, l, c)| (*t, s.to_string(), *l, *c)).collect();
+    assert_eq!(tokens, expected);
+    // the second run goes through the DFA cached by the first one.
+    assert!(GLB_DECISION_TO_DFA[0].read().unwrap().s0.is_some());
+    assert_eq!(simulate("{\"ConditionGroups\": [12, -3.5e2, null],\n\"OpLogic\":\"&&\", abc_1 >= 你}").unwrap(), expected);
+
+    // the longest match wins, keyword wins over name of the same length.
+    let tokens = simulate("Conditions ConditionsX null nullx").unwrap();
+    let types: Vec<isize> = tokens.iter().map(|t| t.0).collect();
+    assert_eq!(types, vec![1, 26, 72, 26, 61, 26, 72]);
+
+    assert_eq!(simulate("").unwrap(), vec![]);
+    assert!(matches!(simulate("{\u{7}"), Err(ANTLRError::LexerNoAltErr { start_index: 1 })));
+}
+
+/// a [Recognizer] records the custom actions executed.
+struct ActionRecorder {
+    base: BaseRecognizer,
+    actions: Rc<RefCell<Vec<(isize, isize)>>>,
+}
+
+impl Recognizer for ActionRecorder {
+    fn literal_names(&self) -> &[Cow<'static, str>] {
+        self.base.literal_names()
+    }
+
+    fn symbolic_names(&self) -> &[Cow<'static, str>] {
+        self.base.symbolic_names()
+    }
+
+    fn vocabulary(&self) -> &Vocabulary {
+        self.base.vocabulary()
+    }
+
+    fn rule_names(&self) -> &[Cow<'static, str>] {
+        self.base.rule_names()
+    }
+
+    fn sempred(&self, local_ctx: Option<Rc<dyn RuleContext>>, rule_idx: isize, pred_idx: isize) -> bool {
+        self.base.sempred(local_ctx, rule_idx, pred_idx)
+    }
+
+    fn precpred(&self, local_ctx: Option<Rc<dyn RuleContext>>, precedence: isize) -> bool {
+        self.base.precpred(local_ctx, precedence)
+    }
+
+    fn state(&self) -> isize {
+        self.base.state()
+    }
+
+    fn set_state(&mut self, state: isize) {
+        self.base.set_state(state)
+    }
+
+    fn action(&mut self, local_ctx: Option<Rc<dyn RuleContext>>, rule_idx: isize, action_idx: isize) {
+        assert!(local_ctx.is_none());
+        self.actions.borrow_mut().push((rule_idx, action_idx))
+    }
+
+    fn add_error_listener(&mut self, l: Box<dyn ErrorListener>) {
+        self.base.add_error_listener(l)
+    }
+
+    fn remove_error_listeners(&mut self) {
+        self.base.remove_error_listeners()
+    }
+
+    fn error_listener_dispatch(&self) -> Rc<RefCell<dyn ErrorListener>> {
+        self.base.error_listener_dispatch()
+    }
+
+    fn has_error(&self) -> bool {
+        self.base.has_error()
+    }
+
+    fn error(&self) -> Option<&ANTLRError> {
+        self.base.error()
+    }
+
+    fn set_error(&mut self, e: ANTLRError) {
+        self.base.set_error(e)
+    }
+}
+
+// custom actions are recorded by [Recognizer::action]
+impl LexerRecognizer for ActionRecorder {}
+
+// lexer action types of the serialized ATN
+const CHANNEL: LexerActionType = 0;
+const CUSTOM: LexerActionType = 1;
+const PUSH_MODE: LexerActionType = 5;
+const SKIP: LexerActionType = 6;
+const TYPE: LexerActionType = 7;
+
+#[test]
+fn test_arishem_lexer_action_executor() {
+    // WS of the grammar is accepted with its skip action.
+    let recog = BaseRecognizer::new(RULE_NAMES, LITERAL_NAMES, SYMBOLIC_NAMES, "Arishem.g4");
+    let mut sim = BaseLexerATNSimulator::new(Arc::clone(&*GLB_ATN), Arc::clone(&*GLB_PREDCT_CTX_CACHE), Arc::clone(&*GLB_DECISION_TO_DFA));
+    let mut input = StringStream::from("\n{");
+    assert_eq!(sim.match_(&mut input, LEXER_DEFAULT_MODE, &recog).unwrap(), 88);
+    let executor = sim.take_lexer_action_executor().unwrap();
+    assert_eq!(executor.lexer_actions(), &[LexerAction::new(SKIP, 0, 0)]);
+    assert!(sim.take_lexer_action_executor().is_none());
+    assert_eq!(sim.match_(&mut input, LEXER_DEFAULT_MODE, &recog).unwrap(), 68);
+    assert!(sim.take_lexer_action_executor().is_none());
+
+    let executor = LexerActionExecutor::append(None, LexerAction::new(TYPE, 70, 0));
+    let executor = LexerActionExecutor::append(Some(&executor), LexerAction::new(CHANNEL, TOKEN_HIDDEN_CHANNEL, 0));
+    // nothing to fix without custom actions.
+    assert!(Arc::ptr_eq(&executor, &executor.fix_offset_before_match(1)));
+    let executor = LexerActionExecutor::append(Some(&executor), LexerAction::new(CUSTOM, 3, 4));
+    let executor = LexerActionExecutor::append(Some(&executor), LexerAction::new(PUSH_MODE, 2, 0));
+    let same = LexerActionExecutor::new(executor.lexer_actions().to_vec());
+    assert_eq!(*executor, same);
+    assert_eq!(executor.murmur(), same.murmur());
+
+    let fixed = executor.fix_offset_before_match(1);
+    assert_ne!(*fixed, *executor);
+    assert!(matches!(fixed.lexer_actions()[2], LexerAction::IndexedCustom(_)));
+    // fixed already
+    assert_eq!(*fixed.fix_offset_before_match(2), *fixed);
+
+    let actions = Rc::new(RefCell::new(vec![]));
+    let recog = ActionRecorder {
+        base: BaseRecognizer::new(RULE_NAMES, LITERAL_NAMES, SYMBOLIC_NAMES, "Arishem.g4"),
+        actions: Rc::clone(&actions),
+    };
+    let sim = BaseLexerATNSimulator::new(Arc::clone(&*GLB_ATN), Arc::clone(&*GLB_PREDCT_CTX_CACHE), Arc::clone(&*GLB_DECISION_TO_DFA));
+    let mut lexer = BaseLexer::new(recog, sim, CommonTokenFactory::default(), StringStream::from("abc"));
+    lexer.input_stream().seek(3);
+    fixed.execute(&mut lexer, 0);
+    assert_eq!(*actions.borrow(), vec![(3, 4)]);
+    assert_eq!((lexer.token_type(), lexer.channel(), lexer.mode()), (70, TOKEN_HIDDEN_CHANNEL, 2));
+    // input is back to the end of token after the indexed action.
+    assert_eq!(lexer.char_index(), 3);
+}
+
+type TokenTuple = (isize, String, isize, isize, isize, isize);
+
+fn token_tuple(tk: &impl Token) -> TokenTuple {
+    (tk.token_type(), tk.text().into_owned(), tk.start(), tk.stop(), tk.line(), tk.column())
+}
+
+/// replace chars `start..end` of `old` by `inserted`, relex and patch the old tokens,
+/// which must be the same as lexing the new buffer from scratch. Returns the range of old tokens replaced.
+fn check_relex(old: &str, start: usize, end: usize, inserted: &str) -> std::ops::Range<usize> {
+    let (mut snapshots, old_tokens) = LineSnapshots::lex(&mut new_arishem_lexer(StringStream::from(old)));
+    let chars: Vec<char> = old.chars().collect();
+    let new: String = chars[..start].iter().chain(inserted.chars().collect::<Vec<_>>().iter()).chain(chars[end..].iter()).collect();
+    let edit = TextEdit { start: start as isize, old_end: end as isize, new_end: (start + inserted.chars().count()) as isize };
+    let relexed = snapshots.relex(&mut new_arishem_lexer(StringStream::from(new.as_str())), &edit);
+
+    let mut patched: Vec<TokenTuple> = old_tokens[..relexed.old_range.start].iter().map(token_tuple).collect();
+    patched.extend(relexed.tokens.iter().map(token_tuple));
+    patched.extend(old_tokens[relexed.old_range.end..].iter().map(|tk| {
+        let (ttype, text, start, stop, line, column) = token_tuple(tk);
+        (ttype, text, start + relexed.index_delta, stop + relexed.index_delta, line + relexed.line_delta, column)
+    }));
+    let (expected_snapshots, expected) = LineSnapshots::lex(&mut new_arishem_lexer(StringStream::from(new.as_str())));
+    assert_eq!(patched, expected.iter().map(token_tuple).collect::<Vec<_>>());
+    assert_eq!(snapshots.snapshots(), expected_snapshots.snapshots());
+    assert_eq!(snapshots.token_count(), expected.len());
+    relexed.old_range
+}
+
+#[test]
+fn test_arishem_lexer_incremental_relex() {
+    let old = "{\"Lhs\": 12,\n\"Rhs\": [1, 2],\n  \"Op\": null\n}";
+    let (snapshots, tokens) = LineSnapshots::lex(&mut new_arishem_lexer(StringStream::from(old)));
+    let lines: Vec<(usize, isize, isize)> = snapshots.snapshots().iter().map(|(t, s)| (*t, s.line, s.index)).collect();
+    // snapshots are taken at the first token boundary on each line, which is after the first token of the line
+    // as the new line is skipped with the WS before it.
+    assert_eq!(lines, vec![(0, 1, 0), (9, 2, 13), (21, 3, 28), (29, 4, 41)]);
+    assert_eq!(snapshots.token_count(), tokens.len());
+
+    // only the tokens of the edited line are relexed.
+    assert_eq!(check_relex(old, 9, 11, "1234"), 0..9);
+    assert_eq!(check_relex(old, 20, 21, "3, 4"), 9..21);
+    // new lines shift the lines of the following tokens.
+    assert_eq!(check_relex(old, 21, 21, "\n3,\n"), 9..21);
+    // lines removed.
+    assert_eq!(check_relex(old, 11, 28, ""), 0..29);
+    // edit at the beginning and at the end.
+    assert_eq!(check_relex(old, 0, 0, "  "), 0..9);
+    assert_eq!(check_relex(old, 41, 41, ",}"), 21..30);
+    assert_eq!(check_relex(old, 35, 39, "12"), 21..29);
+
+    // `e` of line 2 is matched after looking ahead past `+` for an exponent, the snapshot after it examined `x`,
+    // so editing `x` relexes from the line before, where `e+1` is matched as one token.
+    let old = "{\ne+x}";
+    let (snapshots, _) = LineSnapshots::lex(&mut new_arishem_lexer(StringStream::from(old)));
+    let lines: Vec<(isize, isize)> = snapshots.snapshots().iter().map(|(_, s)| (s.index, s.lookahead_index)).collect();
+    assert_eq!(lines, vec![(0, -1), (3, 4)]);
+    assert_eq!(check_relex(old, 4, 5, "1"), 0..6);
+    assert_eq!(check_relex(old, 5, 6, "]"), 2..6);
+
+    // nothing is lexed before, the whole buffer is lexed.
+    let mut snapshots = LineSnapshots::default();
+    let relexed = snapshots.relex(&mut new_arishem_lexer(StringStream::from(old)), &TextEdit { start: 0, old_end: 0, new_end: 6 });
+    let (expected_snapshots, expected) = LineSnapshots::lex(&mut new_arishem_lexer(StringStream::from(old)));
+    assert_eq!(relexed.old_range, 0..0);
+    assert_eq!(relexed.tokens.iter().map(token_tuple).collect::<Vec<_>>(), expected.iter().map(token_tuple).collect::<Vec<_>>());
+    assert_eq!(snapshots.snapshots(), expected_snapshots.snapshots());
+    assert_eq!(snapshots.token_count(), expected.len());
+}
+
+#[test]
+fn test_arishem_lexer_byte_offsets_and_tab_width() {
+    let input = "{\"你😀\":\n\t12,\t\t-3}";
+    let mut lexer = new_arishem_lexer(StringStream::from(input));
+    assert_eq!(lexer.tab_width(), 1);
+    lexer.set_tab_width(4);
+    let tokens = lexer.all_tokens();
+    let actual: Vec<_> = tokens.iter()
+        .map(|tk| (tk.text().into_owned(), tk.start(), tk.line(), tk.column(), tk.byte_range()))
+        .collect();
+    // 😀 starts no token, the tabs are widened to the next multiple of 4.
+    assert_eq!(actual, vec![
+        ("{".to_string(), 0, 1, 0, Some(0..1)),
+        ("\"".to_string(), 1, 1, 1, Some(1..2)),
+        ("你".to_string(), 2, 1, 2, Some(2..5)),
+        ("\"".to_string(), 4, 1, 4, Some(9..10)),
+        (":".to_string(), 5, 1, 5, Some(10..11)),
+        ("12".to_string(), 8, 2, 4, Some(13..15)),
+        (",".to_string(), 10, 2, 6, Some(15..16)),
+        ("-".to_string(), 13, 2, 12, Some(18..19)),
+        ("3".to_string(), 14, 2, 13, Some(19..20)),
+        ("}".to_string(), 15, 2, 14, Some(20..21)),
+        ("<EOF>".to_string(), 16, 2, 15, Some(21..21)),
+    ]);
+    for tk in &tokens[..tokens.len() - 1] {
+        assert_eq!(input[tk.byte_range().unwrap()], tk.text());
+    }
+
+    let quote = TextPosition::of_token(input, &tokens[3]).unwrap();
+    assert_eq!(quote, (
+        TextPosition { byte_offset: 9, line: 0, utf8_column: 9, utf16_column: 5, code_point_column: 4 },
+        TextPosition { byte_offset: 10, line: 0, utf8_column: 10, utf16_column: 6, code_point_column: 5 },
+    ));
+    let (start, end) = TextPosition::of_token(input, &tokens[7]).unwrap();
+    assert_eq!((start.line, start.utf8_column, start.code_point_column, end.byte_offset), (1, 6, 6, 19));
+    assert_eq!(TextPosition::at_byte(input, 18), start);
+    assert_eq!(TextPosition::of_token("", &tokens[7]), None);
+
+    // without byte offsets, char indexes of the token are converted.
+    let mut lexer = new_arishem_lexer(UnbufferedCharStream::new(input.as_bytes()));
+    let unbuffered = lexer.all_tokens();
+    assert!(unbuffered.iter().all(|tk| tk.byte_range().is_none()));
+    for (tk, expected) in unbuffered.iter().zip(&tokens) {
+        assert_eq!(TextPosition::of_token(input, tk), TextPosition::of_token(input, expected));
+    }
+}
+
+#[test]
+#[should_panic(expected = "tab width must be at least 1")]
+fn test_arishem_lexer_zero_tab_width() {
+    new_arishem_lexer(StringStream::from("")).set_tab_width(0);
+}
+
+#[test]
+fn test_arishem_lexer_token_display() {
+    let mut lexer = new_arishem_lexer(StringStream::from("{\"Lhs\":\n12}"));
+    let tokens = lexer.all_tokens();
+    let displayed: Vec<String> = tokens.iter().map(|tk| tk.to_string()).collect();
+    assert_eq!(displayed, vec![
+        "[@-1,0:0='{',<68>,1:0]",
+        "[@-1,1:1='\"',<65>,1:1]",
+        "[@-1,2:4='Lhs',<21>,1:2]",
+        "[@-1,5:5='\"',<65>,1:5]",
+        "[@-1,6:6=':',<64>,1:6]",
+        "[@-1,8:9='12',<70>,2:0]",
+        "[@-1,10:10='}',<69>,2:2]",
+        "[@-1,11:10='<EOF>',<-1>,2:3]",
+    ]);
+    let named: Vec<String> = tokens.iter().map(|tk| tk.display(lexer.vocabulary()).to_string()).collect();
+    assert_eq!(named, vec![
+        "[@-1,0:0='{',<'{'>,1:0]",
+        "[@-1,1:1='\"',<'\"'>,1:1]",
+        "[@-1,2:4='Lhs',<'Lhs'>,1:2]",
+        "[@-1,5:5='\"',<'\"'>,1:5]",
+        "[@-1,6:6=':',<':'>,1:6]",
+        "[@-1,8:9='12',<INT>,2:0]",
+        "[@-1,10:10='}',<'}'>,2:2]",
+        "[@-1,11:10='<EOF>',<EOF>,2:3]",
+    ]);
+
+    let tk = BaseToken::new(88, TOKEN_HIDDEN_CHANNEL, 3, 6, AtomicIsize::new(2), 1, 3, "\t\r\n ".to_string(), false);
+    assert_eq!(tk.to_string(), "[@2,3:6='\\t\\r\\n ',<88>,channel=1,1:3]");
+    assert_eq!(tk.display(lexer.vocabulary()).to_string(), "[@2,3:6='\\t\\r\\n ',<WS>,channel=1,1:3]");
+    let tk = BaseToken::new(500, TOKEN_DEFAULT_CHANNEL, 0, 0, AtomicIsize::new(0), 1, 0, "x".to_string(), false);
+    assert_eq!(tk.display(lexer.vocabulary()).to_string(), "[@0,0:0='x',<500>,1:0]");
+}
+
+fn new_span_lexer<CS: CharStream>(source: &Arc<str>, input: CS) -> BaseLexer<BaseRecognizer, BaseLexerATNSimulator, SpanTokenFactory, CS> {
+    let br = BaseRecognizer::new(RULE_NAMES, LITERAL_NAMES, SYMBOLIC_NAMES, "arishem.g4");
+    let blas = BaseLexerATNSimulator::new(Arc::clone(&*GLB_ATN), Arc::clone(&*GLB_PREDCT_CTX_CACHE), Arc::clone(&*GLB_DECISION_TO_DFA));
+    BaseLexer::new(br, blas, SpanTokenFactory::new(Arc::clone(source)), input)
+}
+
+#[test]
+fn test_arishem_lexer_span_tokens() {
+    let source: Arc<str> = Arc::from("{\"ConditionGroups\": [12, -3.5e2, null],\n\"OpLogic\":\"&&\", abc_1 >= 你}");
+    let expected: Vec<TokenTuple> = new_arishem_lexer(StringStream::from(&*source)).all_tokens().iter().map(token_tuple).collect();
+    let tokens = new_span_lexer(&source, SharedStringStream::from(Arc::clone(&source))).all_tokens();
+    assert_eq!(tokens.iter().map(token_tuple).collect::<Vec<_>>(), expected);
+    let range = source.as_bytes().as_ptr_range();
+    for tk in &tokens {
+        assert!(!tk.is_text_owned());
+        assert!(Arc::ptr_eq(tk.source(), &source));
+        if tk.token_type() != TOKEN_EOF {
+            assert!(matches!(tk.text(), Cow::Borrowed(text) if range.contains(&text.as_ptr())));
+        }
+    }
+    assert_eq!(tokens[1].to_string(), "[@-1,1:1='\"',<65>,1:1]");
+    assert_eq!(tokens[2].clone().text(), "ConditionGroups");
+
+    // text is copied if the stream does not know byte offsets.
+    let mut lexer = new_span_lexer(&source, UnbufferedCharStream::new(source.as_bytes()));
+    let copied = lexer.all_tokens();
+    assert_eq!(copied.iter().map(token_tuple).collect::<Vec<_>>(), expected);
+    assert!(copied[..copied.len() - 1].iter().all(|tk| tk.is_text_owned() && tk.byte_range().is_none()));
+
+    // text is copied if the stream is not the source of the factory, even if they are of the same length.
+    let other: Arc<str> = Arc::from("#".repeat(source.len()));
+    let copied = new_span_lexer(&other, SharedStringStream::from(Arc::clone(&source))).all_tokens();
+    assert_eq!(copied.iter().map(token_tuple).collect::<Vec<_>>(), expected);
+    assert!(copied[..copied.len() - 1].iter().all(|tk| tk.is_text_owned() && Arc::ptr_eq(tk.source(), &other)));
+    let copied = new_span_lexer(&source, StringStream::from(source.to_string())).all_tokens();
+    assert!(copied[..copied.len() - 1].iter().all(|tk| tk.is_text_owned()));
+
+    // explicit text is owned.
+    let factory = SpanTokenFactory::new(Arc::clone(&source));
+    let input = SharedStringStream::from(Arc::clone(&source));
+    let tk = factory.create(&input, 72, Some("renamed".to_string()), TOKEN_DEFAULT_CHANNEL, 2, 16, 1, 2);
+    assert!(tk.is_text_owned());
+    assert_eq!((tk.text(), tk.byte_range()), (Cow::Borrowed("renamed"), Some(2..17)));
+}
+
+#[test]
+fn test_arishem_vocabulary() {
+    let lexer = new_arishem_lexer(StringStream::from(""));
+    let vocabulary = lexer.vocabulary();
+    assert_eq!(vocabulary.max_token_type(), 88);
+    assert_eq!((vocabulary.literal_name(68), vocabulary.symbolic_name(68)), (Some("'{'"), Some("L_BRACE")));
+    assert_eq!((vocabulary.literal_name(70), vocabulary.symbolic_name(70)), (None, Some("INT")));
+    assert_eq!((vocabulary.literal_name(1), vocabulary.symbolic_name(1)), (Some("'Conditions'"), None));
+    assert_eq!((vocabulary.literal_name(TOKEN_EOF), vocabulary.symbolic_name(TOKEN_EOF)), (None, Some("EOF")));
+    assert_eq!(vocabulary.symbolic_name(89), None);
+    let names: Vec<_> = [68, 70, 88, TOKEN_EOF, 0, 500, -5].into_iter().map(|t| vocabulary.display_name(t)).collect();
+    assert_eq!(names, vec!["'{'", "INT", "WS", "EOF", "0", "500", "-5"]);
+    assert_eq!(vocabulary.token_type("'{'"), Some(68));
+    assert_eq!(vocabulary.token_type("L_BRACE"), Some(68));
+    assert_eq!(vocabulary.token_type("'>='"), Some(86));
+    assert_eq!(vocabulary.token_type("EOF"), Some(TOKEN_EOF));
+    assert_eq!(vocabulary.token_type("ID"), None);
+
+    // recognizers of generated code could share the rule names and one vocabulary kept in statics.
+    let (rule_names, shared) = (static_names(RULE_NAMES), Arc::new(Vocabulary::new(LITERAL_NAMES, SYMBOLIC_NAMES)));
+    let recognizers: Vec<BaseRecognizer> = (0..2)
+        .map(|_| BaseRecognizer::with_vocabulary(Arc::clone(&rule_names), Arc::clone(&shared), "Arishem.g4"))
+        .collect();
+    assert!(std::ptr::eq(recognizers[0].vocabulary(), recognizers[1].vocabulary()));
+    assert_eq!(Arc::strong_count(&shared), 3);
+    assert_eq!(recognizers[0].literal_names(), LITERAL_NAMES);
+    assert_eq!(recognizers[1].symbolic_names(), SYMBOLIC_NAMES);
+    assert_eq!(recognizers[1].rule_names(), RULE_NAMES);
+
+    // the .tokens file written by the ANTLR tool, implicit tokens are named T__n.
+    let mut content = String::new();
+    for ttype in 1..=88 {
+        match SYMBOLIC_NAMES.get(ttype).filter(|n| !n.is_empty()) {
+            Some(name) => content += &format!("{}={}\n", name, ttype),
+            None => content += &format!("T__{}={}\n", ttype - 1, ttype),
+        }
+    }
+    for (ttype, name) in LITERAL_NAMES.iter().enumerate().filter(|(_, n)| !n.is_empty()) {
+        content += &format!("{}={}\n", name, ttype);
+    }
+    let parsed = Vocabulary::parse(&content).unwrap();
+    assert_eq!(parsed.max_token_type(), 88);
+    for ttype in -1..=89 {
+        assert_eq!(parsed.literal_name(ttype), vocabulary.literal_name(ttype));
+        assert_eq!(parsed.display_name(ttype), vocabulary.display_name(ttype));
+    }
+    assert_eq!(parsed.token_type("'='"), Some(35));
+    assert_eq!(parsed.token_type("'''"), Some(30));
+    assert_eq!(parsed.token_type("T__0"), Some(1));
+    assert_eq!(parsed.symbolic_name(1), Some("T__0"));
+
+    let e = Vocabulary::parse("ID=1\n\nINT\n").unwrap_err();
+    assert!(matches!(&e, TokensFileError::InvalidLine { line: 3, text } if text == "INT"));
+    assert_eq!(e.to_string(), "invalid line 3 in tokens file: 'INT'");
+    assert!(matches!(Vocabulary::parse("ID=x"), Err(TokensFileError::InvalidLine { line: 1, .. })));
+    assert!(matches!(Vocabulary::parse("=1"), Err(TokensFileError::InvalidLine { line: 1, .. })));
+    // the names are not allocated up to a huge type.
+    assert!(matches!(Vocabulary::parse("ID=1\nX=4000000000"), Err(TokensFileError::InvalidLine { line: 2, .. })));
+    assert_eq!(Vocabulary::parse("X=65535").unwrap().max_token_type(), 65535);
+    assert!(matches!(Vocabulary::load("not/exist/Arishem.tokens"), Err(TokensFileError::Io(_))));
+    assert_eq!(Vocabulary::parse("").unwrap().max_token_type(), -1);
+}
+
+#[test]
+fn test_arishem_lexer_record_and_replay() {
+    let input = "{\"ConditionGroups\": [12, -3.5e2, null],\n\"OpLogic\":\"&&\", abc_1 >= 你}";
+    let mut recorder = TokenRecorder::new(vec![]).unwrap();
+    let recorded = recorder.record_all(&mut new_arishem_lexer(StringStream::from(input))).unwrap();
+    let bytes = recorder.into_inner().unwrap();
+    // the header, and 8 bytes at most for a token of a short text.
+    assert!(bytes.len() < 8 + recorded.len() * 8 + input.len());
+
+    let replayed = read_tokens(bytes.as_slice()).unwrap();
+    assert_eq!(replayed.iter().map(token_tuple).collect::<Vec<_>>(), recorded.iter().map(token_tuple).collect::<Vec<_>>());
+    assert!(replayed.iter().zip(&recorded).all(|(a, b)| a.channel() == b.channel()));
+    let mut source = ListTokenSource::new(replayed);
+    assert_eq!((source.line(), source.char_position_in_line()), (1, 0));
+    let tokens = source.all_tokens();
+    assert_eq!(tokens.iter().map(token_tuple).collect::<Vec<_>>(), recorded.iter().map(token_tuple).collect::<Vec<_>>());
+    // keep returning the EOF of the list.
+    assert_eq!(token_tuple(&source.next_token()), token_tuple(recorded.last().unwrap()));
+
+    assert_eq!(read_tokens(&b"ANTLRTK\x02"[..]).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    assert_eq!(read_tokens(&bytes[..bytes.len() - 1]).unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+    // a forged text length is not allocated up front.
+    let mut forged = b"ANTLRTK\x01\x02\x00\x00\x00\x02\x00".to_vec();
+    forged.extend_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01]);
+    forged.extend_from_slice(b"abc");
+    assert_eq!(read_tokens(forged.as_slice()).unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+    // a forged start at isize::MAX overflows with the stop after it.
+    let mut forged = b"ANTLRTK\x01\x02\x00".to_vec();
+    forged.extend_from_slice(&[0xFE, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01]);
+    forged.extend_from_slice(b"\x02\x00\x00\x00");
+    assert_eq!(read_tokens(forged.as_slice()).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    assert!(read_tokens(&bytes[..8]).unwrap().is_empty());
+}
+
+#[test]
+fn test_list_token_source_eof() {
+    // EOF is created after the last token, where the lexer would create it.
+    let mut lexer_tokens = new_arishem_lexer(StringStream::from("{\"Lhs\":\n12}")).all_tokens();
+    let lexer_eof = lexer_tokens.pop().unwrap();
+    let mut source = ListTokenSource::new(lexer_tokens);
+    assert_eq!(source.tokens().len(), 7);
+    let tokens = source.all_tokens();
+    assert_eq!(tokens.len(), 8);
+    let eof = tokens.last().unwrap();
+    assert_eq!(token_tuple(eof), token_tuple(&lexer_eof));
+    assert_eq!((source.line(), source.char_position_in_line()), (2, 3));
+
+    let text = |text: &str, start, stop, line, column| {
+        BaseToken::new(72, TOKEN_DEFAULT_CHANNEL, start, stop, AtomicIsize::new(-1), line, column, text.to_string(), false)
+    };
+    let mut source = ListTokenSource::new(vec![text("a", 0, 0, 1, 0), text("b\ncd", 1, 4, 1, 1)]);
+    assert_eq!((source.line(), source.char_position_in_line()), (1, 0));
+    source.next_token();
+    assert_eq!((source.line(), source.char_position_in_line()), (1, 1));
+    source.next_token();
+    assert_eq!((source.line(), source.char_position_in_line()), (2, 2));
+    let eof = source.next_token();
+    assert_eq!(token_tuple(&eof), (TOKEN_EOF, "<EOF>".to_string(), 5, 4, 2, 2));
+
+    let mut source = ListTokenSource::new(vec![]);
+    assert_eq!((source.line(), source.char_position_in_line()), (1, 0));
+    assert_eq!(token_tuple(&source.next_token()), (TOKEN_EOF, "<EOF>".to_string(), 0, -1, 1, 0));
+    assert_eq!(source.next_token().token_type(), TOKEN_EOF);
+    source.reset();
+    assert_eq!(source.tokens().len(), 0);
+}
+
+#[test]
+fn test_arishem_common_token_stream() {
+    let input = "{\"Lhs\": 12, \"Op\": >=}";
+    let lexer = new_arishem_lexer(StringStream::from(input).with_source_name("rule.json"));
+    let mut stream = CommonTokenStream::new(lexer, TOKEN_DEFAULT_CHANNEL);
+    assert_eq!(stream.source_name(), "rule.json");
+    assert_eq!(stream.la(1), 68);
+    assert_eq!(stream.lt(3).unwrap().text(), "Lhs");
+    assert_eq!(stream.size(), 3);
+    while stream.la(1) != TOKEN_EOF {
+        stream.consume();
+    }
+    assert_eq!(stream.lt(-1).unwrap().token_type(), 69);
+    assert_eq!(stream.lb(2).unwrap().token_type(), 86);
+    assert_eq!(stream.all_text(), input);
+    assert_eq!(stream.number_of_on_channel_tokens(), stream.size() as usize);
+}
+
+#[test]
+fn test_arishem_unbuffered_token_stream() {
+    let input = "{\"Lhs\": 12, \"Op\": >=}\n".repeat(1000);
+    let new_stream = || UnbufferedTokenStream::new(new_arishem_lexer(UnbufferedCharStream::new(input.as_bytes())));
+    let num_tokens = new_arishem_lexer(StringStream::from(input.as_str())).all_tokens().len();
+
+    // the buffer never grows without marks.
+    let mut stream = new_stream();
+    let mut max_buffer_len = 0;
+    while stream.la(1) != TOKEN_EOF {
+        assert_eq!(stream.lt(1).unwrap().token_index(), stream.index());
+        stream.lt(2);
+        max_buffer_len = max_buffer_len.max(stream.buffer_len());
+        stream.consume();
+    }
+    assert_eq!(stream.index() as usize, num_tokens - 1);
+    assert_eq!(max_buffer_len, 2);
+
+    let mut stream = new_stream();
+    let marker = stream.mark();
+    while stream.la(1) != TOKEN_EOF {
+        stream.consume();
+    }
+    assert_eq!(stream.buffer_len(), num_tokens);
+    // newlines are skipped by the lexer.
+    assert_eq!(stream.all_text(), input.replace('\n', ""));
+    stream.release(marker);
+    assert_eq!(stream.buffer_len(), 1);
+}
+
+#[test]
+fn test_arishem_lexer_atn_simulator_unbuffered_input() {
+    // `e+` is a prefix of an exponent, the simulator backs out to `e` and pins the chars looked ahead itself.
+    let input = "e+x e-1 E+";
+    let br = BaseRecognizer::new(RULE_NAMES, LITERAL_NAMES, SYMBOLIC_NAMES, "arishem.g4");
+    let mut blas = BaseLexerATNSimulator::new(Arc::clone(&*GLB_ATN), Arc::clone(&*GLB_PREDCT_CTX_CACHE), Arc::clone(&*GLB_DECISION_TO_DFA));
+    let mut stream = UnbufferedCharStream::new(input.as_bytes());
+    let mut matched = vec![];
+    while stream.la(1) != TOKEN_EOF {
+        let start = stream.index();
+        let ttype = blas.match_(&mut stream, LEXER_DEFAULT_MODE, &br).unwrap();
+        matched.push((ttype, start, stream.index()));
+    }
+    let expected: Vec<_> = new_arishem_lexer(StringStream::from(input)).all_tokens().iter()
+        .filter(|tk| tk.token_type() != TOKEN_EOF)
+        .map(|tk| (tk.token_type(), tk.start(), tk.stop() + 1))
+        .collect();
+    assert_eq!(matched, expected);
+    assert_eq!(&matched[..3], [(72, 0, 1), (77, 1, 2), (72, 2, 3)]);
+}
+
+/// a lexer ATN of the grammar:
+/// ```antlr
+/// FIRST : {getCharPositionInLine() == 0}? 'a' ;
+/// A : 'a' ;
+/// WS : [\t- ] {newlines += getText() == "\n"; skip();} ;
+/// ```
+const PREDICATED_LEXER_ATN: &'static [i32] = &[
+    4, 0, 3,
+    // states: 0 token start, 1..=6 rule start and stop of FIRST, A and WS, 7 predicate of FIRST, 8 'a' of FIRST,
+    // 9 'a' of A, 10 chars of WS, 11 action of WS
+    12, 6, -1, 2, 0, 7, 0, 2, 1, 7, 1, 2, 2, 7, 2, 1, 0, 1, 0, 1, 1, 1, 2, 1, 2,
+    0, 0,
+    // rules with their token types, modes and sets
+    3, 1, 1, 3, 2, 5, 3,
+    1, 0,
+    0,
+    11,
+    0, 1, 1, 0, 0, 0,
+    0, 3, 1, 0, 0, 0,
+    0, 5, 1, 0, 0, 0,
+    1, 7, 1, 0, 0, 0,
+    7, 8, 4, 0, 0, 0,
+    8, 2, 5, 97, 0, 0,
+    3, 9, 1, 0, 0, 0,
+    9, 4, 5, 97, 0, 0,
+    5, 10, 1, 0, 0, 0,
+    10, 11, 2, 9, 32, 0,
+    11, 6, 6, 2, 0, 0,
+    // decisions
+    1, 0,
+    // lexer actions: custom action 0 of WS
+    1, 1, 2, 0,
+];
+
+/// a [Recognizer] of [PREDICATED_LEXER_ATN], whose predicate and action work on the lexer.
+struct PredicatedRecognizer {
+    base: BaseRecognizer,
+    newlines: usize,
+}
+
+impl Recognizer for PredicatedRecognizer {
+    fn literal_names(&self) -> &[Cow<'static, str>] {
+        self.base.literal_names()
+    }
+
+    fn symbolic_names(&self) -> &[Cow<'static, str>] {
+        self.base.symbolic_names()
+    }
+
+    fn vocabulary(&self) -> &Vocabulary {
+        self.base.vocabulary()
+    }
+
+    fn rule_names(&self) -> &[Cow<'static, str>] {
+        self.base.rule_names()
+    }
+
+    fn sempred(&self, _local_ctx: Option<Rc<dyn RuleContext>>, _rule_idx: isize, _pred_idx: isize) -> bool {
+        panic!("the predicate needs the lexer")
+    }
+
+    fn precpred(&self, local_ctx: Option<Rc<dyn RuleContext>>, precedence: isize) -> bool {
+        self.base.precpred(local_ctx, precedence)
+    }
+
+    fn state(&self) -> isize {
+        self.base.state()
+    }
+
+    fn set_state(&mut self, state: isize) {
+        self.base.set_state(state)
+    }
+
+    fn action(&mut self, _local_ctx: Option<Rc<dyn RuleContext>>, _rule_idx: isize, _action_idx: isize) {
+        panic!("the action needs the lexer")
+    }
+
+    fn add_error_listener(&mut self, l: Box<dyn ErrorListener>) {
+        self.base.add_error_listener(l)
+    }
+
+    fn remove_error_listeners(&mut self) {
+        self.base.remove_error_listeners()
+    }
+
+    fn error_listener_dispatch(&self) -> Rc<RefCell<dyn ErrorListener>> {
+        self.base.error_listener_dispatch()
+    }
+
+    fn has_error(&self) -> bool {
+        self.base.has_error()
+    }
+
+    fn error(&self) -> Option<&ANTLRError> {
+        self.base.error()
+    }
+
+    fn set_error(&mut self, e: ANTLRError) {
+        self.base.set_error(e)
+    }
+}
+
+impl LexerRecognizer for PredicatedRecognizer {
+    fn lexer_sempred(&self, lexer: &mut LexerView<'_>, rule_idx: isize, pred_idx: isize) -> bool {
+        assert_eq!((rule_idx, pred_idx), (0, 0));
+        assert_eq!((lexer.text(), lexer.mode()), (Cow::Borrowed(""), LEXER_DEFAULT_MODE));
+        lexer.char_position_in_line() == 0
+    }
+
+    fn lexer_action<LAS, TF, CS>(lexer: &mut BaseLexer<Self, LAS, TF, CS>, rule_idx: isize, action_idx: isize)
+        where LAS: LexerATNSimulator, TF: TokenFactory, CS: CharStream {
+        assert_eq!((rule_idx, action_idx), (2, 0));
+        if lexer.text() == "\n" {
+            lexer.recognizer_mut().newlines += 1;
+        }
+        lexer.skip();
+    }
+}
+
+#[test]
+fn test_lexer_predicates_and_actions_on_lexer() {
+    let atn = Arc::new(ATNDeserializer::new(None).deserialize(PREDICATED_LEXER_ATN));
+    let decision_to_dfa = Arc::new(vec![RwLock::new(DFA::new(&atn, atn.decision2state_nth[0], 0))]);
+    let recog = PredicatedRecognizer {
+        base: BaseRecognizer::new(&["FIRST", "A", "WS"], &[], &["", "FIRST", "A", "WS"], "Predicated.g4"),
+        newlines: 0,
+    };
+    let sim = BaseLexerATNSimulator::new(atn, Arc::new(RwLock::new(PredictionContextCache::new())), decision_to_dfa);
+    let mut lexer = BaseLexer::new(recog, sim, CommonTokenFactory::default(), StringStream::from("a a\n a\na"));
+    let tokens: Vec<_> = lexer.all_tokens().iter().map(|tk| (tk.token_type(), tk.line(), tk.column())).collect();
+    // `a` is FIRST at the start of a line only.
+    assert_eq!(tokens, vec![(1, 1, 0), (2, 1, 2), (2, 2, 1), (1, 3, 0), (TOKEN_EOF, 3, 1)]);
+    assert_eq!(lexer.recognizer().newlines, 2);
+}
+
+#[test]
+fn test_lexer_atn_try_deserialize_edges() {
+    let atn_der = ATNDeserializer::new(None);
+    let offset_of = |edge: &[i32]| PREDICATED_LEXER_ATN.windows(6).position(|w| w == edge).unwrap();
+
+    // the predicate of FIRST as a precedence predicate.
+    let mut data = PREDICATED_LEXER_ATN.to_vec();
+    let type_offset = offset_of(&[7, 8, 4, 0, 0, 0]) + 2;
+    data[type_offset] = 10;
+    let err = atn_der.try_deserialize(&data).unwrap_err();
+    assert_eq!((err.section, err.kind), (ATNSection::Edges, ATNDeserializeErrorKind::InvalidTransitionType));
+    assert_eq!((err.offset, err.value), (type_offset, Some(10)));
+
+    // the action of WS refers to the second lexer action, but there is only one.
+    let mut data = PREDICATED_LEXER_ATN.to_vec();
+    let action_offset = offset_of(&[11, 6, 6, 2, 0, 0]) + 4;
+    data[action_offset] = 1;
+    let err = atn_der.try_deserialize(&data).unwrap_err();
+    assert_eq!((err.section, err.kind), (ATNSection::Edges, ATNDeserializeErrorKind::InvalidLexerActionIndex));
+    assert_eq!((err.offset, err.value), (action_offset, Some(1)));
+}
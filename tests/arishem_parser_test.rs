@@ -1,4 +1,15 @@
+use antlr4rs::atn_deserialize_option::ATNDeserializeOption;
 use antlr4rs::atn_deserializer::ATNDeserializer;
+use antlr4rs::atn_serializer::ATNSerializer;
+use antlr4rs::dot_generator::DOTGenerator;
+use antlr4rs::atn_state::ATNState;
+use antlr4rs::transition::Transition;
+use antlr4rs::atn_type::ATNType;
+use antlr4rs::errors::{ATNDeserializeErrorKind, ATNSection, InterpFileError};
+use antlr4rs::input_stream::StringStream;
+use antlr4rs::lexer_interpreter::LexerInterpreter;
+use antlr4rs::recognizer::Names;
+use std::sync::Arc;
 
 pub const SERIALIZED_ATN: &'static [i32] = &[
     4, 1, 88, 649, 2, 0, 7, 0, 2, 1, 7, 1, 2, 2, 7, 2, 2, 3, 7, 3, 2, 4, 7,
@@ -279,3 +290,247 @@ fn test_arishem_parser_atn_deserialize() {
     let parser_atn = atn_der.deserialize(SERIALIZED_ATN);
     println!("{:?}", parser_atn);
 }
+
+#[test]
+fn test_arishem_parser_atn_try_deserialize() {
+    let atn_der = ATNDeserializer::new(None);
+    assert!(atn_der.try_deserialize(SERIALIZED_ATN).is_ok());
+
+    // truncated in header
+    let err = atn_der.try_deserialize(&SERIALIZED_ATN[..2]).unwrap_err();
+    assert_eq!(err.section, ATNSection::Header);
+    assert_eq!(err.kind, ATNDeserializeErrorKind::UnexpectedEnd);
+    assert_eq!(err.offset, 2);
+    assert_eq!(err.value, None);
+
+    // truncated in the last section
+    let err = atn_der.try_deserialize(&SERIALIZED_ATN[..SERIALIZED_ATN.len() - 1]).unwrap_err();
+    assert_eq!(err.section, ATNSection::Decisions);
+    assert_eq!(err.kind, ATNDeserializeErrorKind::UnexpectedEnd);
+    assert_eq!(err.offset, SERIALIZED_ATN.len() - 1);
+
+    let mut data = SERIALIZED_ATN.to_vec();
+    data[0] = 3;
+    let err = atn_der.try_deserialize(&data).unwrap_err();
+    assert_eq!(err.section, ATNSection::Header);
+    assert_eq!(err.kind, ATNDeserializeErrorKind::UnsupportedVersion);
+    assert_eq!((err.offset, err.value), (0, Some(3)));
+
+    // type of the first state
+    let mut data = SERIALIZED_ATN.to_vec();
+    data[4] = 13;
+    let err = atn_der.try_deserialize(&data).unwrap_err();
+    assert_eq!(err.section, ATNSection::States);
+    assert_eq!(err.kind, ATNDeserializeErrorKind::InvalidStateType);
+    assert_eq!((err.offset, err.value), (4, Some(13)));
+    assert_eq!(err.to_string(), "could not deserialize ATN: invalid state type in states section at offset 4 (value 13)");
+
+    // the last decision refer to a state out of range
+    let mut data = SERIALIZED_ATN.to_vec();
+    let last = data.len() - 1;
+    data[last] = 649;
+    let err = atn_der.try_deserialize(&data).unwrap_err();
+    assert_eq!(err.section, ATNSection::Decisions);
+    assert_eq!(err.kind, ATNDeserializeErrorKind::InvalidStateNth);
+    assert_eq!((err.offset, err.value), (last, Some(649)));
+}
+
+#[test]
+fn test_arishem_parser_atn_serialize() {
+    let atn = ATNDeserializer::new(None).deserialize(SERIALIZED_ATN);
+    let data = ATNSerializer::new().serialize(&atn);
+    assert_eq!(data, SERIALIZED_ATN);
+    // serialized data is deserialized into the same ATN again.
+    let again = ATNDeserializer::new(None).deserialize(&data);
+    assert_eq!(ATNSerializer::new().serialize(&again), data);
+}
+
+#[test]
+fn test_arishem_parser_atn_display() {
+    let atn = ATNDeserializer::new(None).deserialize(SERIALIZED_ATN);
+    let state = &atn.states()[10];
+    assert_eq!(state.to_string(), "10");
+
+    let trans: Vec<String> = atn.states().iter().flat_map(|s| s.transitions()).map(|t| t.to_string()).collect();
+    assert!(trans.iter().any(|t| t == "epsilon"));
+    assert!(trans.iter().any(|t| t.starts_with("rule_")));
+    // sets are shown with braces unless there is only one element.
+    assert!(trans.iter().any(|t| t.starts_with('{') && t.ends_with('}')));
+    // atoms are shown as their labels, EOF as `<EOF>`.
+    assert_eq!(Transition::new(5, 0, 68, 0, 0, &vec![]).to_string(), "68");
+    assert_eq!(Transition::new(5, 0, 0, 0, 1, &vec![]).to_string(), "<EOF>");
+
+    let dot = DOTGenerator::new(&atn, None).atn_to_dot();
+    assert_eq!(dot.matches("shape=doublecircle").count(), atn.rule2stop_state_nths().len());
+}
+
+#[test]
+#[should_panic(expected = "could not deserialize ATN")]
+fn test_arishem_parser_atn_deserialize_panic() {
+    ATNDeserializer::new(None).deserialize(&SERIALIZED_ATN[..100]);
+}
+
+#[test]
+#[should_panic(expected = "the ATN must be a lexer ATN")]
+fn test_arishem_parser_atn_lexer_interpreter_panic() {
+    let atn = Arc::new(ATNDeserializer::new(None).deserialize(SERIALIZED_ATN));
+    let none = Names::default;
+    LexerInterpreter::new("Arishem.g4", atn, none(), none(), none(), none(), none(), StringStream::from(""));
+}
+
+#[test]
+fn test_arishem_parser_atn_lexer_interpreter_try_new() {
+    let atn = Arc::new(ATNDeserializer::new(None).deserialize(SERIALIZED_ATN));
+    let none = Names::default;
+    let err = LexerInterpreter::try_new("Arishem.g4", atn, none(), none(), none(), none(), none(), StringStream::from(""))
+        .err().unwrap();
+    assert!(matches!(err, InterpFileError::UnexpectedGrammarType { expected: ATNType::Lexer, found: ATNType::Parser }));
+    assert_eq!(err.to_string(), "the ATN must be a lexer ATN, but got Parser");
+}
+
+#[test]
+fn test_arishem_parser_rule_bypass_transitions() {
+    let plain = ATNDeserializer::new(None).deserialize(SERIALIZED_ATN);
+    let atn = ATNDeserializer::new(Some(ATNDeserializeOption {
+        gen_rule_bypass_transitions: true,
+        ..Default::default()
+    })).deserialize(SERIALIZED_ATN);
+
+    let rules_num = atn.rule2start_state_nths().len();
+    assert_eq!(rules_num, 73);
+    // bypass start, bypass stop and the match state for each rule
+    assert_eq!(atn.states().len(), plain.states().len() + 3 * rules_num);
+    assert_eq!(atn.decision2state_nth.len(), plain.decision2state_nth.len() + rules_num);
+
+    for rule_idx in 0..rules_num {
+        let token_type = atn.rule2token_type()[rule_idx];
+        assert_eq!(token_type, atn.max_token_type() + rule_idx as isize + 1);
+
+        // rule start -> bypass start
+        let rule_start_nth = atn.rule2start_state_nths()[rule_idx];
+        let rule_start = &atn.states()[rule_start_nth];
+        assert_eq!(rule_start.transitions().len(), 1);
+        assert!(rule_start.transitions()[0].is_epsilon());
+        let bypass_start = &atn.states()[rule_start.transitions()[0].target_nth()];
+        assert!(matches!(bypass_start, ATNState::BlockStart(_)));
+        assert_eq!(bypass_start.rule_index(), rule_idx);
+        assert_eq!(atn.decision2state_nth[plain.decision2state_nth.len() + rule_idx], bypass_start.state_nth());
+
+        // alternatives of the rule leave from bypass start now, plus the one matches the rule token type.
+        let plain_rule_start = &plain.states()[rule_start_nth];
+        assert_eq!(bypass_start.transitions().len(), plain_rule_start.transitions().len() + 1);
+        let match_state = &atn.states()[bypass_start.transitions().last().unwrap().target_nth()];
+        assert!(matches!(match_state, ATNState::Basic(_)));
+        let bypass_stop_nth = match &match_state.transitions()[0] {
+            Transition::Atom(at) => {
+                assert_eq!(at.label(), token_type);
+                match_state.transitions()[0].target_nth()
+            }
+            t => panic!("unexpected transition of match state: {:?}", t),
+        };
+
+        // bypass stop -> rule stop, it is the only way to reach rule stop.
+        let bypass_stop = &atn.states()[bypass_stop_nth];
+        assert!(matches!(bypass_stop, ATNState::BlockEnd(_)));
+        let rule_stop_nth = atn.rule2stop_state_nths()[rule_idx];
+        assert_eq!(bypass_stop.transitions()[0].target_nth(), rule_stop_nth);
+        let into_rule_stop: Vec<usize> = atn.states().iter()
+            .filter(|state| state.transitions().iter().any(|t| t.target_nth() == rule_stop_nth))
+            .map(|state| state.state_nth())
+            .collect();
+        assert_eq!(into_rule_stop, vec![bypass_stop_nth]);
+    }
+}
+
+/// `e : e '+' e | INT ;` with `'+'=1` and `INT=2`, rewritten by ANTLR tool into
+/// `e[int _p] : INT ({precpred(_ctx, 2)}? '+' e[3])* ;`
+const LEFT_RECURSIVE_ATN: &'static [i32] = &[
+    4, 1, 2,
+    // states: 0 rule start, 1 rule stop, 2 INT, 3 star loop entry, 4 star loopback, 5 star block start,
+    // 6 precedence predicate, 7 '+', 8 block end, 9 loop end, 10 e[3], 11 after e[3]
+    12, 2, 0, 7, 0, 1, 0, 10, 0, 9, 0, 5, 0, 8, 1, 0, 1, 0, 8, 0, 12, 0, 4, 1, 0, 1, 0,
+    // no non-greedy states, rule start 0 is left recursive.
+    0, 1, 0,
+    // rules, modes and sets
+    1, 0, 0, 0,
+    12,
+    0, 2, 1, 0, 0, 0,
+    2, 3, 5, 2, 0, 0,
+    3, 5, 1, 0, 0, 0,
+    3, 9, 1, 0, 0, 0,
+    4, 3, 1, 0, 0, 0,
+    5, 6, 1, 0, 0, 0,
+    6, 7, 10, 2, 0, 0,
+    7, 10, 5, 1, 0, 0,
+    8, 4, 1, 0, 0, 0,
+    9, 1, 1, 0, 0, 0,
+    10, 11, 3, 0, 0, 3,
+    11, 8, 1, 0, 0, 0,
+    // decisions
+    1, 3,
+];
+
+#[test]
+fn test_left_recursive_rule_bypass_transitions() {
+    let options = || Some(ATNDeserializeOption { gen_rule_bypass_transitions: true, ..Default::default() });
+    let plain = ATNDeserializer::new(None).deserialize(LEFT_RECURSIVE_ATN);
+    assert_eq!(plain.states().len(), 12);
+    let atn = ATNDeserializer::new(options()).deserialize(LEFT_RECURSIVE_ATN);
+    assert_eq!(atn.rule2token_type(), &[3]);
+    // bypass start 12, bypass stop 13 and the match state 14.
+    assert_eq!(atn.states().len(), 15);
+    assert_eq!(atn.decision2state_nth, vec![3, 12]);
+    let targets = |nth: usize| atn.states()[nth].transitions().iter().map(|t| t.target_nth()).collect::<Vec<_>>();
+
+    // rule start -> bypass start -> (INT ... | match e) -> bypass stop -> star loop entry.
+    assert_eq!(targets(0), vec![12]);
+    assert!(matches!(atn.states()[12], ATNState::BlockStart(_)));
+    assert_eq!(atn.states()[12].rule_index(), 0);
+    assert_eq!(targets(12), vec![2, 14]);
+    match &atn.states()[14].transitions()[..] {
+        [Transition::Atom(at)] => assert_eq!(at.label(), 3),
+        t => panic!("unexpected transitions of match state: {:?}", t),
+    }
+    assert_eq!(targets(14), vec![13]);
+    assert!(matches!(atn.states()[13], ATNState::BlockEnd(_)));
+    assert_eq!(targets(13), vec![3]);
+
+    // the prefix of the rule ends at the star loop entry instead of the rule stop, which is left to the loop end,
+    // and the loopback still enters the loop.
+    assert_eq!(targets(2), vec![13]);
+    assert_eq!(targets(4), vec![3]);
+    assert_eq!(targets(3), vec![5, 9]);
+    assert_eq!(targets(9), vec![1]);
+    for nth in [1, 5, 6, 7, 8, 10, 11] {
+        assert_eq!(targets(nth), plain.states()[nth].transitions().iter().map(|t| t.target_nth()).collect::<Vec<_>>());
+    }
+
+    // the loop end leaves to a state other than the rule stop, no star loop entry ends the prefix.
+    let mut data = LEFT_RECURSIVE_ATN.to_vec();
+    let loop_end_edge = data.len() - 2 - 3 * 6;
+    assert_eq!(data[loop_end_edge..loop_end_edge + 2], [9, 1]);
+    data[loop_end_edge + 1] = 11;
+    assert!(ATNDeserializer::new(None).try_deserialize(&data).is_ok());
+    let err = ATNDeserializer::new(options()).try_deserialize(&data).unwrap_err();
+    assert_eq!(err.section, ATNSection::Rules);
+    assert!(matches!(err.kind, ATNDeserializeErrorKind::Verification { state_nth: 0, .. }));
+}
+
+#[test]
+fn test_left_recursive_rule_bypass_transitions_serialize() {
+    let options = || Some(ATNDeserializeOption { gen_rule_bypass_transitions: true, ..Default::default() });
+    let atn = ATNDeserializer::new(options()).deserialize(LEFT_RECURSIVE_ATN);
+    // the bypass states are written as ordinary states, which are not in the data of the tool.
+    let data = ATNSerializer::new().serialize(&atn);
+    assert_ne!(data, LEFT_RECURSIVE_ATN);
+    let bypassed = ATNDeserializer::new(None).deserialize(&data);
+    assert_eq!(bypassed.states().len(), 15);
+    assert_eq!(bypassed.decision2state_nth, atn.decision2state_nth);
+    for (state, expected) in bypassed.states().iter().zip(atn.states()) {
+        let targets = |s: &ATNState| s.transitions().iter().map(|t| t.target_nth()).collect::<Vec<_>>();
+        assert_eq!(targets(state), targets(expected));
+    }
+    assert_eq!(ATNSerializer::new().serialize(&bypassed), data);
+    // generating the bypass transitions again wraps the rule once more.
+    assert_eq!(ATNDeserializer::new(options()).deserialize(&data).states().len(), 18);
+}
@@ -1,6 +1,13 @@
+use antlr4rs::case_changing_char_stream::CaseChangingCharStream;
 use antlr4rs::char_stream::CharStream;
-use antlr4rs::input_stream::{StringStream, ByteStream, CodePoint16BitStream, CodePoint32BitStream, CodePoint8BitStream};
-use antlr4rs::int_stream::EOF;
+use antlr4rs::code_point::{CodePoints, SurrogatePolicy, Utf8};
+use antlr4rs::encoding::{decode, DecodingPolicy, Encoding};
+use antlr4rs::errors::{DecodeError, UnbufferedTextError, Utf16Error};
+use antlr4rs::unbuffered_char_stream::UnbufferedCharStream;
+use std::borrow::Cow;
+use std::io::Read;
+use antlr4rs::input_stream::{FileStream, StringStream, ByteStream, CodePoint16BitStream, CodePoint32BitStream, CodePoint8BitStream, Utf16Stream};
+use antlr4rs::int_stream::{EOF, IntStream};
 
 #[test]
 fn test_input_stream() {
@@ -50,6 +57,77 @@ fn test_input_stream() {
     assert_eq!(input.text(3, 5), "好§，");
     assert_eq!(input.text(0, 5), "A你4好§，");
     assert_eq!(input.text(3, 10), "好§，\\❤");
+    assert!(matches!(input.text(0, 7), Cow::Borrowed(r#"A你4好§，\❤"#)));
+    assert_eq!(input.text(8, 10), "");
+    assert_eq!(input.text(5, 4), "");
+}
+
+#[test]
+fn test_utf8_code_points() {
+    let text = Utf8::new("a❤😀".to_string());
+    assert_eq!(text.size(), 3);
+    assert_eq!(text.code_point_at(2), Some('😀' as u32));
+    // char indexes, not byte indexes.
+    assert_eq!(text.code_point_at(3), None);
+    assert_eq!(text.code_point_at(5), None);
+    assert!(matches!(text.text_range(1, 2), Cow::Borrowed("❤😀")));
+    assert_eq!(text.as_str(), "a❤😀");
+    assert_eq!((text.byte_offset(2), text.byte_offset(3), text.byte_offset(4)), (Some(4), Some(8), None));
+    assert_eq!("a❤😀".byte_offset(2), Some(4));
+    assert_eq!(StringStream::from("a❤😀").byte_offset(3), Some(8));
+    assert_eq!(StringStream::from("a❤😀").byte_offset(-1), None);
+    assert_eq!(CodePoint32BitStream::new(vec![0x61]).byte_offset(0), None);
+    assert_eq!(StringStream::from("a❤😀").utf8_text(), Some("a❤😀"));
+    assert_eq!(CodePoint32BitStream::new(vec![0x61]).utf8_text(), None);
+
+    let ascii = Utf8::new("abc");
+    assert_eq!(ascii.size(), 3);
+    assert_eq!(ascii.code_point_at(1), Some('b' as u32));
+    assert_eq!(ascii.code_point_at(3), None);
+    assert!(matches!(ascii.text_range(1, 9), Cow::Borrowed("bc")));
+    assert_eq!(ascii.into_inner(), "abc");
+
+    let empty = Utf8::new("");
+    assert_eq!(empty.size(), 0);
+    assert_eq!(empty.code_point_at(0), None);
+    assert_eq!(empty.text_range(0, 0), "");
+
+    // scanning a long string visits each char once.
+    let long = "好a".repeat(50_000);
+    let mut input = StringStream::from(long.as_str());
+    let mut n = 0;
+    while input.la(1) != EOF {
+        n += 1;
+        input.consume();
+    }
+    assert_eq!(n, 100_000);
+    assert_eq!(input.text(99_998, 99_999), "好a");
+}
+
+#[test]
+fn test_utf8_sparse_index() {
+    // mostly ASCII with a single non-ASCII char, the index is a small fraction of the text.
+    let mut text = "a".repeat(1 << 20);
+    text.insert(1000, 'é');
+    let utf8 = Utf8::new(text.clone());
+    assert!(utf8.index_size() <= text.len() / 16 + 16, "index takes {} bytes", utf8.index_size());
+    assert_eq!(Utf8::new("abc").index_size(), 0);
+    assert_eq!(utf8.size(), (1 << 20) + 1);
+    assert_eq!(utf8.code_point_at(1000), Some('é' as u32));
+    assert_eq!(utf8.code_point_at(1001), Some('a' as u32));
+    assert_eq!(utf8.byte_offset(1001), Some(1002));
+    assert_eq!(utf8.byte_offset(utf8.size()), Some(text.len()));
+    assert_eq!(utf8.text_range(999, 1001), "aéa");
+
+    // every char of mixed widths, across block boundaries.
+    let text: String = (0..1000).map(|i| ['a', 'é', '好', '😀'][i * 7 % 4]).collect();
+    let utf8 = Utf8::new(text.as_str());
+    assert!(text.char_indices().enumerate().all(|(pos, (i, c))| {
+        utf8.byte_offset(pos) == Some(i) && utf8.code_point_at(pos) == Some(c as u32)
+    }));
+    assert_eq!(utf8.byte_offset(1000), Some(text.len()));
+    assert_eq!(utf8.code_point_at(1000), None);
+    assert_eq!(utf8.text_range(60, 70), text.chars().skip(60).take(11).collect::<String>());
 }
 
 #[test]
@@ -123,4 +201,245 @@ fn test_code_point_32bit_stream() {
     assert_eq!(v.text(1, 2), "¤¥");
     assert_eq!(v.text(3, 5), "¦§");
     assert_eq!(v.text(0, 5), "£¤¥¦§");
-}
\ No newline at end of file
+}
+#[test]
+fn test_case_changing_char_stream() {
+    let mut input = CaseChangingCharStream::new(StringStream::from("SeLect ÄÖ ß ǅ"), true);
+    assert_eq!(input.size(), 13);
+    let symbols: Vec<isize> = (1..=14).map(|i| input.la(i)).collect();
+    let expected: Vec<isize> = "SELECT ÄÖ ß Ǆ".chars().map(|c| c as isize).chain([EOF]).collect();
+    // `ß` is upper cased into two chars, so it is kept.
+    assert_eq!(symbols, expected);
+    input.consume();
+    input.consume();
+    assert_eq!((input.index(), input.la(1), input.la(-1)), (2, 'L' as isize, 'E' as isize));
+    input.seek(7);
+    assert_eq!(input.la(1), 'Ä' as isize);
+    // text is the original one
+    assert_eq!(input.text(0, 5), "SeLect");
+    assert_eq!(input.source_name(), input.inner().source_name());
+
+    let mut input = CaseChangingCharStream::new(CodePoint32BitStream::new(vec!['Σ' as u32, 'A' as u32, 0x1F600]), false);
+    assert_eq!((input.la(1), input.la(2), input.la(3), input.la(4)), ('σ' as isize, 'a' as isize, 0x1F600, EOF));
+    assert_eq!(input.text(0, 1), "ΣA");
+    let mut inner = input.into_inner();
+    assert_eq!(inner.la(1), 'Σ' as isize);
+}
+
+#[test]
+fn test_utf16_stream() {
+    let text = "a😀你𠀀b";
+    let units: Vec<u16> = text.encode_utf16().collect();
+    assert_eq!(units.len(), 7);
+    let mut input = Utf16Stream::from_utf16(&units, SurrogatePolicy::Error).unwrap();
+    // surrogate pairs are one symbol each
+    assert_eq!(input.size(), 5);
+    let symbols: Vec<isize> = (1..=6).map(|i| input.la(i)).collect();
+    let expected: Vec<isize> = text.chars().map(|c| c as isize).chain([EOF]).collect();
+    assert_eq!(symbols, expected);
+    input.consume();
+    input.consume();
+    assert_eq!((input.index(), input.la(1), input.la(-1)), (2, '你' as isize, '😀' as isize));
+    assert_eq!(input.text(0, 4), text);
+    assert_eq!(input.text(1, 3), "😀你𠀀");
+
+    // unpaired surrogates: a lone high one in the middle and a lone low one at the end.
+    let units = [0x61, 0xD83D, 0x62, 0xDE00];
+    let mut input = Utf16Stream::from_utf16(&units, SurrogatePolicy::Replace).unwrap();
+    assert_eq!((input.size(), input.la(2), input.la(4)), (4, 0xFFFD, 0xFFFD));
+    assert_eq!(input.text(0, 3), "a\u{FFFD}b\u{FFFD}");
+    let mut input = Utf16Stream::from_utf16(&units, SurrogatePolicy::Preserve).unwrap();
+    assert_eq!((input.size(), input.la(2), input.la(4)), (4, 0xD83D, 0xDE00));
+    assert_eq!(input.text(0, 3), "a\u{FFFD}b\u{FFFD}");
+    let err = Utf16Stream::from_utf16(&units, SurrogatePolicy::Error).unwrap_err();
+    assert_eq!(err, Utf16Error { index: 1, unit: 0xD83D });
+    assert_eq!(err.to_string(), "unpaired surrogate 0xD83D at index 1 of UTF-16 input");
+    let err = Utf16Stream::from_utf16(&units[2..], SurrogatePolicy::Error).unwrap_err();
+    assert_eq!(err, Utf16Error { index: 1, unit: 0xDE00 });
+    assert_eq!(SurrogatePolicy::default(), SurrogatePolicy::Replace);
+}
+
+/// a [Read] returns at most `chunk` bytes each time, to split UTF-8 sequences between reads.
+struct ChunkedReader {
+    data: Vec<u8>,
+    pos: usize,
+    chunk: usize,
+}
+
+impl Read for ChunkedReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.chunk.min(buf.len()).min(self.data.len() - self.pos);
+        buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[test]
+fn test_unbuffered_char_stream() {
+    let reader = ChunkedReader { data: "a你😀b".as_bytes().to_vec(), pos: 0, chunk: 1 };
+    let mut input = UnbufferedCharStream::new(reader);
+    assert_eq!(input.source_name(), "<unknown>");
+    input.set_source_name("export.log");
+    assert_eq!(input.source_name(), "export.log");
+    assert_eq!((input.la(1), input.la(-1)), ('a' as isize, EOF));
+    input.consume();
+    // nothing is marked, chars consumed are discarded.
+    assert_eq!((input.index(), input.la(-1), input.buffer_start_index()), (1, 'a' as isize, 1));
+    assert!(input.try_text(0, 0).is_err());
+    assert_eq!(input.la(2), '😀' as isize);
+
+    let marker = input.mark();
+    input.consume();
+    input.consume();
+    assert_eq!((input.la(1), input.la(-1), input.la(-2)), ('b' as isize, '😀' as isize, '你' as isize));
+    assert_eq!(input.text(1, 2), "你😀");
+    // not read yet
+    assert!(input.try_text(1, 10).is_err());
+    input.seek(1);
+    assert_eq!((input.index(), input.la(1), input.la(-1)), (1, '你' as isize, 'a' as isize));
+    input.seek(3);
+    input.consume();
+    assert_eq!((input.index(), input.la(1)), (4, EOF));
+    // clamped at the end of input
+    assert_eq!(input.text(1, 10), "你😀b");
+    input.release(marker);
+    assert_eq!(input.buffer_start_index(), 4);
+    let err = input.try_text(1, 2).unwrap_err();
+    assert_eq!(err, UnbufferedTextError { start: 1, end: 2, buffer_start: 4, buffer_end: 4 });
+    assert_eq!(input.la(-1), 'b' as isize);
+    assert!(input.take_io_error().is_none());
+
+    // invalid UTF-8, truncated sequence at the end of input.
+    let reader = ChunkedReader { data: b"a\xffb\xe4\xbd".to_vec(), pos: 0, chunk: 2 };
+    let mut input = UnbufferedCharStream::new(reader);
+    let marker = input.mark();
+    let symbols: Vec<isize> = (1..=5).map(|i| input.la(i)).collect();
+    assert_eq!(symbols, vec!['a' as isize, 0xFFFD, 'b' as isize, 0xFFFD, EOF]);
+    input.release(marker);
+}
+
+#[test]
+#[should_panic(expected = "can not seek to index 0 before the buffer")]
+fn test_unbuffered_char_stream_seek_out_of_buffer() {
+    let mut input = UnbufferedCharStream::new("ab".as_bytes());
+    input.consume();
+    input.seek(0);
+}
+
+#[test]
+#[should_panic(expected = "release is called with an invalid marker")]
+fn test_unbuffered_char_stream_release_out_of_order() {
+    let mut input = UnbufferedCharStream::new("ab".as_bytes());
+    let first = input.mark();
+    input.mark();
+    input.release(first);
+}
+
+fn temp_file(name: &str, content: &[u8]) -> std::path::PathBuf {
+    let path = std::env::temp_dir().join(format!("antlr4rs-{}-{}", std::process::id(), name));
+    std::fs::write(&path, content).unwrap();
+    path
+}
+
+#[test]
+fn test_file_stream() {
+    let mut input = StringStream::from("a");
+    assert_eq!(input.source_name(), "source from string");
+    input.set_source_name("<stdin>");
+    assert_eq!(input.source_name(), "<stdin>");
+
+    let path = temp_file("file_stream.txt", "a😀\nb".as_bytes());
+    let mut input = FileStream::open(&path).unwrap();
+    assert_eq!(input.source_name(), path.to_string_lossy());
+    assert_eq!(input.size(), 4);
+    assert_eq!(input.la(2), '😀' as isize);
+    assert_eq!(input.text(1, 3), "😀\nb");
+
+    let invalid = temp_file("file_stream_invalid.txt", b"a\xFFb");
+    assert_eq!(FileStream::open(&invalid).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(&invalid).unwrap();
+    assert_eq!(FileStream::open(&path).unwrap_err().kind(), std::io::ErrorKind::NotFound);
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn test_mmap_stream() {
+    use antlr4rs::input_stream::MmapStream;
+
+    let path = temp_file("mmap_stream.txt", "a😀\nb".as_bytes());
+    let mut input = unsafe { MmapStream::open(&path) }.unwrap();
+    assert_eq!(input.source_name(), path.to_string_lossy());
+    assert_eq!(input.size(), 4);
+    assert_eq!(input.la(2), '😀' as isize);
+    assert_eq!(input.text(1, 3), "😀\nb");
+    drop(input);
+
+    let empty = temp_file("mmap_stream_empty.txt", b"");
+    let mut input = unsafe { MmapStream::open(&empty) }.unwrap();
+    assert_eq!(input.la(1), EOF);
+
+    let invalid = temp_file("mmap_stream_invalid.txt", b"a\xFFb");
+    assert_eq!(unsafe { MmapStream::open(&invalid) }.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+    for p in [path, empty, invalid] {
+        std::fs::remove_file(p).unwrap();
+    }
+}
+
+#[test]
+fn test_decode_bytes() {
+    let text = "a§😀";
+    let utf16le: Vec<u8> = text.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    let utf16be: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+    let utf32le: Vec<u8> = text.chars().flat_map(|c| (c as u32).to_le_bytes()).collect();
+    let utf32be: Vec<u8> = text.chars().flat_map(|c| (c as u32).to_be_bytes()).collect();
+    let cases: [(&[u8], Encoding, Vec<u8>); 5] = [
+        (&[0xEF, 0xBB, 0xBF], Encoding::Utf8, text.as_bytes().to_vec()),
+        (&[0xFF, 0xFE], Encoding::Utf16Le, utf16le),
+        (&[0xFE, 0xFF], Encoding::Utf16Be, utf16be),
+        (&[0xFF, 0xFE, 0, 0], Encoding::Utf32Le, utf32le),
+        (&[0, 0, 0xFE, 0xFF], Encoding::Utf32Be, utf32be),
+    ];
+    for (bom, encoding, bytes) in cases {
+        let with_bom = [bom, &bytes].concat();
+        assert_eq!(Encoding::detect_bom(&with_bom), Some((encoding, bom.len())));
+        assert_eq!(decode(&with_bom, None, DecodingPolicy::Error).unwrap(), text, "{}", encoding);
+        assert_eq!(decode(&with_bom, Some(encoding), DecodingPolicy::Error).unwrap(), text, "{}", encoding);
+        assert_eq!(decode(&bytes, Some(encoding), DecodingPolicy::Error).unwrap(), text, "{}", encoding);
+    }
+    // UTF-8 without BOM, a BOM of another encoding is kept as text.
+    assert_eq!(decode("a§".as_bytes(), None, DecodingPolicy::Error).unwrap(), "a§");
+    assert_eq!(decode(b"\xEF\xBB\xBFa", Some(Encoding::Latin1), DecodingPolicy::Error).unwrap(), "ï»¿a");
+    assert_eq!(decode(b"\xA7\xE9", Some(Encoding::Latin1), DecodingPolicy::Error).unwrap(), "§é");
+
+    // invalid sequences.
+    assert_eq!(decode(b"a\xFFb\xE4\xBD", None, DecodingPolicy::Replace).unwrap(), "a\u{FFFD}b\u{FFFD}");
+    assert_eq!(decode(b"a\xFFb", None, DecodingPolicy::Error), Err(DecodeError { encoding: Encoding::Utf8, index: 1 }));
+    assert_eq!(decode(b"\xEF\xBB\xBFab\xE4\xBD", None, DecodingPolicy::Error), Err(DecodeError { encoding: Encoding::Utf8, index: 5 }));
+    // an unpaired surrogate, then an odd byte at the end.
+    let utf16 = [0x61, 0x00, 0x00, 0xD8, 0x62, 0x00, 0x63];
+    assert_eq!(decode(&utf16, Some(Encoding::Utf16Le), DecodingPolicy::Replace).unwrap(), "a\u{FFFD}b\u{FFFD}");
+    let e = decode(&utf16, Some(Encoding::Utf16Le), DecodingPolicy::Error).unwrap_err();
+    assert_eq!(e, DecodeError { encoding: Encoding::Utf16Le, index: 2 });
+    assert_eq!(e.to_string(), "invalid UTF-16LE sequence at byte 2 of input");
+    assert_eq!(decode(&utf16[4..], Some(Encoding::Utf16Le), DecodingPolicy::Error).unwrap_err().index, 2);
+    let utf32 = [0x61, 0, 0, 0, 0, 0xD8, 0, 0, 0x62];
+    assert_eq!(decode(&utf32, Some(Encoding::Utf32Le), DecodingPolicy::Replace).unwrap(), "a\u{FFFD}\u{FFFD}");
+    assert_eq!(decode(&utf32, Some(Encoding::Utf32Le), DecodingPolicy::Error).unwrap_err().index, 4);
+
+    // indexes of the stream are code points.
+    let bytes: Vec<u8> = [0xFF, 0xFE].into_iter().chain("x😀y".encode_utf16().flat_map(u16::to_le_bytes)).collect();
+    let mut input = StringStream::from_bytes(&bytes, None, DecodingPolicy::Error).unwrap();
+    assert_eq!(input.size(), 3);
+    assert_eq!(input.la(2), '😀' as isize);
+    assert_eq!(input.la(3), 'y' as isize);
+    assert_eq!(input.text(1, 2), "😀y");
+
+    let path = temp_file("file_stream_utf16.txt", &bytes);
+    let input = FileStream::open(&path).unwrap();
+    assert_eq!(input.text(0, 2), "x😀y");
+    let input = FileStream::open_with(&path, Some(Encoding::Latin1), DecodingPolicy::Error).unwrap();
+    assert_eq!(input.size(), bytes.len() as isize);
+    std::fs::remove_file(&path).unwrap();
+}
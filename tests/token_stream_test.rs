@@ -0,0 +1,251 @@
+use antlr4rs::buffered_token_stream::BufferedTokenStream;
+use antlr4rs::common_token_stream::CommonTokenStream;
+use antlr4rs::errors::UnbufferedTokenError;
+use antlr4rs::int_stream::{EOF, IntStream};
+use antlr4rs::interval_set::{Interval, IntervalSet};
+use antlr4rs::list_token_source::ListTokenSource;
+use antlr4rs::rule_context::BaseRuleContext;
+use antlr4rs::token_stream::TokenStream;
+use antlr4rs::token::{BaseToken, Token, TOKEN_DEFAULT_CHANNEL, TOKEN_HIDDEN_CHANNEL, TOKEN_INVALID_TYPE};
+use antlr4rs::unbuffered_token_stream::UnbufferedTokenStream;
+use std::sync::atomic::AtomicIsize;
+
+const ID: isize = 1;
+const WS: isize = 2;
+const COMMENT: isize = 3;
+const COMMENT_CHANNEL: isize = 2;
+
+/// tokens of `a b\n/*c*/d`, whitespaces are hidden and the comment is on its own channel.
+fn new_token_source() -> ListTokenSource {
+    let mut start = 0;
+    let mut line = 1;
+    let mut column = 0;
+    let tokens = [(ID, TOKEN_DEFAULT_CHANNEL, "a"), (WS, TOKEN_HIDDEN_CHANNEL, " "), (ID, TOKEN_DEFAULT_CHANNEL, "b"),
+        (WS, TOKEN_HIDDEN_CHANNEL, "\n"), (COMMENT, COMMENT_CHANNEL, "/*c*/"), (ID, TOKEN_DEFAULT_CHANNEL, "d")]
+        .into_iter()
+        .map(|(ttype, channel, text)| {
+            let len = text.chars().count() as isize;
+            let tk = BaseToken::new(ttype, channel, start, start + len - 1, AtomicIsize::new(-1), line, column, text.to_string(), false);
+            start += len;
+            if text == "\n" {
+                (line, column) = (line + 1, 0);
+            } else {
+                column += len;
+            }
+            tk
+        })
+        .collect();
+    ListTokenSource::new(tokens)
+}
+
+fn texts<T: Token>(tokens: &[std::rc::Rc<T>]) -> Vec<String> {
+    tokens.iter().map(|tk| tk.text().to_string()).collect()
+}
+
+#[test]
+fn test_buffered_token_stream() {
+    let mut stream = BufferedTokenStream::new(new_token_source());
+    // tokens are fetched on demand.
+    assert_eq!(stream.la(1), ID);
+    assert_eq!(stream.size(), 1);
+    assert_eq!(stream.lt(3).unwrap().text(), "b");
+    assert_eq!(stream.size(), 3);
+    assert!(stream.lt(0).is_none());
+    assert!(stream.lt(-1).is_none());
+
+    // tokens on all channels are seen.
+    stream.consume();
+    assert_eq!(stream.index(), 1);
+    assert_eq!(stream.la(1), WS);
+    assert_eq!(stream.la(-1), ID);
+    assert_eq!(stream.lb(1).unwrap().text(), "a");
+    assert!(stream.lb(2).is_none());
+    stream.seek(4);
+    assert_eq!(stream.lt(1).unwrap().text(), "/*c*/");
+    assert_eq!(stream.lt(3).unwrap().token_type(), EOF);
+    assert_eq!(stream.lt(100).unwrap().token_type(), EOF);
+    stream.seek(100);
+    assert_eq!(stream.index(), 6);
+    assert_eq!(stream.la(1), EOF);
+
+    stream.fill();
+    assert_eq!(stream.size(), 7);
+    assert!(stream.tokens().iter().enumerate().all(|(i, tk)| tk.token_index() == i as isize));
+    assert_eq!(stream.token_at(2).text(), "b");
+    assert_eq!(stream.all_text(), "a b\n/*c*/d");
+    assert_eq!(stream.source_name(), "<unknown>");
+}
+
+#[test]
+fn test_common_token_stream() {
+    let mut stream = CommonTokenStream::new(new_token_source(), TOKEN_DEFAULT_CHANNEL);
+    assert_eq!(stream.channel(), TOKEN_DEFAULT_CHANNEL);
+    assert_eq!(stream.index(), -1);
+    assert_eq!(stream.la(1), ID);
+    assert_eq!(stream.index(), 0);
+    assert_eq!(stream.lt(2).unwrap().text(), "b");
+    assert_eq!(stream.lt(3).unwrap().text(), "d");
+    assert_eq!(stream.lt(4).unwrap().token_type(), EOF);
+    assert_eq!(stream.lt(100).unwrap().token_type(), EOF);
+
+    // tokens off the channel are skipped.
+    stream.consume();
+    assert_eq!(stream.index(), 2);
+    assert_eq!(stream.lt(-1).unwrap().text(), "a");
+    stream.consume();
+    assert_eq!(stream.index(), 5);
+    assert_eq!(stream.lb(1).unwrap().text(), "b");
+    assert_eq!(stream.lb(2).unwrap().text(), "a");
+    assert!(stream.lb(3).is_none());
+    assert!(stream.lb(0).is_none());
+    stream.consume();
+    assert_eq!(stream.index(), 6);
+    assert_eq!(stream.la(1), EOF);
+    assert_eq!(stream.la(-1), ID);
+
+    stream.seek(1);
+    assert_eq!(stream.index(), 2);
+    stream.seek(3);
+    assert_eq!(stream.index(), 5);
+    stream.seek(100);
+    assert_eq!(stream.index(), 6);
+
+    // off-channel tokens are still buffered, indexed and counted.
+    assert_eq!(stream.size(), 7);
+    assert_eq!(stream.number_of_on_channel_tokens(), 4);
+    assert_eq!(stream.token_at(4).text(), "/*c*/");
+    assert_eq!(stream.text(Interval::new(1, 4)), " b\n/*c*/");
+    assert_eq!(stream.text(Interval::new(4, 100)), "/*c*/d");
+    assert_eq!(stream.text(Interval::new(-1, 4)), "");
+    assert_eq!(stream.all_text(), "a b\n/*c*/d");
+    // a context without children is matched from the first token.
+    assert_eq!(stream.text_of(&BaseRuleContext::new(None, -1)), "a");
+}
+
+#[test]
+fn test_common_token_stream_other_channel() {
+    let mut stream = CommonTokenStream::new(new_token_source(), COMMENT_CHANNEL);
+    assert_eq!(stream.index(), -1);
+    assert_eq!(stream.lt(1).unwrap().text(), "/*c*/");
+    assert_eq!(stream.index(), 4);
+    assert_eq!(stream.lt(2).unwrap().token_type(), EOF);
+    assert!(stream.lb(1).is_none());
+}
+
+#[test]
+fn test_token_stream_get_tokens() {
+    let mut stream = CommonTokenStream::new(new_token_source(), TOKEN_DEFAULT_CHANNEL);
+    assert_eq!(texts(&stream.get_tokens(0, 100, None)), ["a", " ", "b", "\n", "/*c*/", "d"]);
+    assert_eq!(texts(&stream.get_tokens(1, 3, None)), [" ", "b", "\n"]);
+    assert!(stream.get_tokens(3, 1, None).is_empty());
+    let mut types = IntervalSet::new();
+    types.add_one(ID);
+    types.add_one(COMMENT);
+    assert_eq!(texts(&stream.get_tokens(0, 100, Some(&types))), ["a", "b", "/*c*/", "d"]);
+}
+
+#[test]
+fn test_token_stream_hidden_tokens() {
+    let mut stream = CommonTokenStream::new(new_token_source(), TOKEN_DEFAULT_CHANNEL);
+    assert_eq!(texts(&stream.hidden_tokens_to_right(0, None)), [" "]);
+    assert_eq!(texts(&stream.hidden_tokens_to_right(2, None)), ["\n", "/*c*/"]);
+    assert_eq!(texts(&stream.hidden_tokens_to_right(2, Some(COMMENT_CHANNEL))), ["/*c*/"]);
+    assert_eq!(texts(&stream.hidden_tokens_to_right(3, Some(TOKEN_HIDDEN_CHANNEL))), Vec::<String>::new());
+    assert!(stream.hidden_tokens_to_right(5, None).is_empty());
+    assert!(stream.hidden_tokens_to_right(6, None).is_empty());
+
+    assert!(stream.hidden_tokens_to_left(0, None).is_empty());
+    assert_eq!(texts(&stream.hidden_tokens_to_left(2, None)), [" "]);
+    assert_eq!(texts(&stream.hidden_tokens_to_left(5, None)), ["\n", "/*c*/"]);
+    assert_eq!(texts(&stream.hidden_tokens_to_left(5, Some(TOKEN_HIDDEN_CHANNEL))), ["\n"]);
+    assert_eq!(texts(&stream.hidden_tokens_to_left(4, None)), ["\n"]);
+}
+
+#[test]
+#[should_panic(expected = "can not consume EOF")]
+fn test_token_stream_consume_eof() {
+    let mut stream = CommonTokenStream::new(ListTokenSource::new(vec![]), TOKEN_DEFAULT_CHANNEL);
+    assert_eq!(stream.la(1), EOF);
+    stream.consume();
+}
+
+#[test]
+#[should_panic(expected = "token index 7 out of range 0..7")]
+fn test_token_stream_hidden_tokens_out_of_range() {
+    let mut stream = BufferedTokenStream::new(new_token_source());
+    stream.hidden_tokens_to_left(7, None);
+}
+
+#[test]
+fn test_unbuffered_token_stream() {
+    let mut stream = UnbufferedTokenStream::new(new_token_source());
+    assert_eq!(stream.buffer_len(), 1);
+    assert_eq!(stream.la(1), ID);
+    assert_eq!(stream.lt(3).unwrap().text(), "b");
+    assert_eq!(stream.buffer_len(), 3);
+    assert!(stream.lt(-1).is_none());
+    assert_eq!(stream.la(-1), TOKEN_INVALID_TYPE);
+
+    stream.consume();
+    assert_eq!(stream.index(), 1);
+    assert_eq!(stream.lt(-1).unwrap().text(), "a");
+    // consumed tokens are discarded without marks, tokens looked ahead are kept.
+    assert_eq!(stream.try_token_at(0).unwrap_err(), UnbufferedTokenError { index: 0, buffer_start: 1, buffer_end: 3 });
+    assert_eq!(stream.token_at(2).text(), "b");
+    stream.consume();
+    stream.consume();
+    assert_eq!(stream.index(), 3);
+    assert_eq!((stream.buffer_start_index(), stream.buffer_len()), (3, 1));
+    assert_eq!(stream.lt(-1).unwrap().text(), "b");
+    assert_eq!(stream.try_token_at(0).unwrap_err(), UnbufferedTokenError { index: 0, buffer_start: 3, buffer_end: 4 });
+    assert_eq!(stream.lt(1).unwrap().token_index(), 3);
+
+    // tokens are kept while a mark is outstanding.
+    let marker = stream.mark();
+    stream.consume();
+    stream.consume();
+    assert_eq!(stream.index(), 5);
+    assert_eq!(stream.text(Interval::new(3, 5)), "\n/*c*/d");
+    assert_eq!(stream.lt(-2).unwrap().text(), "\n");
+    stream.seek(3);
+    assert_eq!(stream.lt(-1).unwrap().text(), "b");
+    assert_eq!(stream.lt(1).unwrap().text(), "\n");
+    stream.seek(5);
+    stream.release(marker);
+    assert_eq!(stream.buffer_start_index(), 5);
+    assert_eq!(stream.lt(-1).unwrap().text(), "/*c*/");
+    let buffer_end = 5 + stream.buffer_len() as isize;
+    assert_eq!(stream.try_seek(3).unwrap_err(), UnbufferedTokenError { index: 3, buffer_start: 5, buffer_end });
+    assert_eq!(stream.try_text(Interval::new(0, 5)).unwrap_err().index, 0);
+    assert_eq!(stream.index(), 5);
+
+    stream.seek(100);
+    assert_eq!(stream.index(), 6);
+    assert_eq!(stream.la(1), EOF);
+    assert_eq!(stream.lt(1).unwrap().token_index(), 6);
+    assert_eq!(stream.lt(100).unwrap().token_type(), EOF);
+    assert_eq!(stream.text(Interval::new(5, 100)), "d");
+    assert_eq!(stream.source_name(), "<unknown>");
+}
+
+#[test]
+fn test_unbuffered_token_stream_all_text() {
+    let mut stream = UnbufferedTokenStream::new(new_token_source());
+    let marker = stream.mark();
+    assert_eq!(stream.all_text(), "a b\n/*c*/d");
+    while stream.la(1) != EOF {
+        stream.consume();
+    }
+    assert_eq!(stream.buffer_len(), 7);
+    stream.release(marker);
+    assert_eq!((stream.buffer_start_index(), stream.buffer_len()), (6, 1));
+}
+
+#[test]
+#[should_panic(expected = "token 0 is out of the buffer 2..3 of unbuffered token stream")]
+fn test_unbuffered_token_stream_seek_out_of_buffer() {
+    let mut stream = UnbufferedTokenStream::new(new_token_source());
+    stream.consume();
+    stream.consume();
+    stream.seek(0);
+}